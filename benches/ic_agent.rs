@@ -0,0 +1,48 @@
+//! What the IC repository spends on agents and delegations for each booking, before it makes any
+//! call: an agent with its own HTTP transport against one on the shared transport, and a delegation
+//! signed per call against one renewed shortly before it expires. Nothing is sent over the network,
+//! so the handshakes the shared transport saves are not measured.
+//!
+//! ```bash
+//! cargo bench --bench ic_agent
+//! ```
+
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ic_agent::identity::Secp256k1Identity;
+use ic_agent::Identity;
+use k256::elliptic_curve::rand_core::OsRng;
+use offchain::canister::canister::{Canisters, RenewingCanisters};
+use offchain::identity::delegated_identity::DEFAULT_DELEGATION_LIFETIME;
+use offchain::identity::ic::IcNetwork;
+
+fn identity() -> Arc<Secp256k1Identity> {
+    Arc::new(Secp256k1Identity::from_private_key(k256::SecretKey::random(&mut OsRng)))
+}
+
+fn customer_agent(c: &mut Criterion) {
+    let network = IcNetwork::live();
+    let customer = identity();
+
+    let mut group = c.benchmark_group("customer agent");
+    group.bench_function("own transport", |b| b.iter(|| Canisters::set_arc_id(&IcNetwork::live(), customer.clone()).unwrap()));
+    group.bench_function("shared transport", |b| b.iter(|| Canisters::set_arc_id(&network, customer.clone()).unwrap()));
+    group.finish();
+}
+
+fn admin_canisters(c: &mut Criterion) {
+    let network = IcNetwork::live();
+    let admin: Arc<dyn Identity> = identity();
+    let renewing = RenewingCanisters::new(network.clone(), admin.clone(), DEFAULT_DELEGATION_LIFETIME);
+
+    let mut group = c.benchmark_group("admin canisters");
+    group.bench_function("delegation per call", |b| {
+        b.iter(|| RenewingCanisters::new(IcNetwork::live(), admin.clone(), DEFAULT_DELEGATION_LIFETIME).get().unwrap())
+    });
+    group.bench_function("renewed before expiry", |b| b.iter(|| renewing.get().unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, customer_agent, admin_canisters);
+criterion_main!(benches);
@@ -33,7 +33,9 @@ mod build_common {
         canister_id_mod
     }
 
-    fn build_canister_ids(out_dir: &str, is_dev: bool) -> Result<()> {
+    /// The IDs of both networks, as defaults for `IcNetwork::live` and `IcNetwork::local`; which
+    /// one is used is chosen at runtime.
+    fn build_canister_ids(out_dir: &str) -> Result<()> {
         let can_ids = read_candid_ids()?;
         let mut local_can_ids = Vec::<(String, Principal)>::new();
         let mut ic_can_ids = Vec::<(String, Principal)>::new();
@@ -42,29 +44,19 @@ mod build_common {
             ic_can_ids.push((canister, can_id.ic));
         }
 
-        let canister_id_mod_contents = if is_dev {
-            let local_canister_id_mod = generate_canister_id_mod(local_can_ids);
-            format!(
-                r#"
-                mod local {{
-                    {local_canister_id_mod}
-                }}
-        
-                pub use local::*;
-                "#
-            )
-        } else {
-            let ic_canister_id_mod = generate_canister_id_mod(ic_can_ids);
-            format!(
-                r#"
-                mod ic {{
-                    {ic_canister_id_mod}
-                }}
-        
-                pub use ic::*;
-                "#
-            )
-        };
+        let local_canister_id_mod = generate_canister_id_mod(local_can_ids);
+        let ic_canister_id_mod = generate_canister_id_mod(ic_can_ids);
+        let canister_id_mod_contents = format!(
+            r#"
+            pub mod local {{
+                {local_canister_id_mod}
+            }}
+
+            pub mod ic {{
+                {ic_canister_id_mod}
+            }}
+            "#
+        );
         
         let canister_id_mod_path = PathBuf::from(out_dir).join("canister_ids.rs");
         fs::write(canister_id_mod_path, canister_id_mod_contents)?;
@@ -73,11 +65,8 @@ mod build_common {
     }
 
     fn build_did_intf() -> Result<()> {
-        dotenv::dotenv().ok();
         println!("cargo:rerun-if-changed=./did/*");
 
-        let is_dev = env::var("BACKEND").unwrap_or("LIVE".to_string()) == "LOCAL";
-
         let mut candid_config: candid_parser::bindings::rust::Config = candid_parser::bindings::rust::Config::new();
         candid_config.set_target(candid_parser::bindings::rust::Target::Agent);
         candid_config.set_type_attributes(
@@ -131,7 +120,7 @@ mod build_common {
         let binding_mod_file = PathBuf::from(&out_dir).join("did").join("mod.rs");
         fs::write(binding_mod_file, did_mod_contents)?;
 
-        build_canister_ids(&out_dir, is_dev)?;
+        build_canister_ids(&out_dir)?;
 
         Ok(())
     }
@@ -145,6 +134,8 @@ mod build_common {
 
 fn main() -> Result<()> {
     // dotenv::dotenv().ok();
+    // sqlx::migrate! embeds the migrations at compile time
+    println!("cargo:rerun-if-changed=migrations");
     build_common::build_common()?;
 
     Ok(())
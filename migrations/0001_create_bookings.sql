@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS bookings (
+    booking_id BIGINT PRIMARY KEY,
+    car_id BIGINT NOT NULL,
+    customer_name TEXT NOT NULL,
+    customer_email TEXT NOT NULL,
+    customer_age SMALLINT NOT NULL,
+    country_code INTEGER NOT NULL,
+    mobile_number TEXT NOT NULL,
+    pan TEXT NOT NULL,
+    aadhar TEXT NOT NULL,
+    start_time BIGINT NOT NULL,
+    end_time BIGINT NOT NULL,
+    total_amount DOUBLE PRECISION,
+    status TEXT NOT NULL,
+    payment_link TEXT,
+    payment_id TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS bookings_customer_email_idx ON bookings (customer_email);
+
+CREATE INDEX IF NOT EXISTS bookings_car_id_time_idx ON bookings (car_id, start_time, end_time);
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS outbox_events (
+    id BIGSERIAL PRIMARY KEY,
+    booking_id BIGINT NOT NULL,
+    payload JSONB NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at BIGINT NOT NULL,
+    completed_handlers TEXT[] NOT NULL DEFAULT '{}',
+    last_error TEXT,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS outbox_events_due_idx ON outbox_events (next_attempt_at, id) WHERE status = 'pending';
+
+CREATE INDEX IF NOT EXISTS outbox_events_dead_lettered_idx ON outbox_events (id) WHERE status = 'dead_lettered';
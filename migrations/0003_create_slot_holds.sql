@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS slot_holds (
+    booking_id BIGINT PRIMARY KEY,
+    car_id BIGINT NOT NULL,
+    holder_email TEXT NOT NULL,
+    start_time BIGINT NOT NULL,
+    end_time BIGINT NOT NULL,
+    expires_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS slot_holds_car_idx ON slot_holds (car_id, expires_at);
@@ -0,0 +1,4 @@
+-- Mobile numbers were stored without their country code; store them in E.164 form.
+UPDATE bookings
+SET mobile_number = '+' || country_code || mobile_number
+WHERE mobile_number NOT LIKE '+%';
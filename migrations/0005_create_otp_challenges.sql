@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS otp_challenges (
+    contact TEXT PRIMARY KEY,
+    code_hash TEXT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    sends_in_window INTEGER NOT NULL,
+    window_started_at BIGINT NOT NULL,
+    last_sent_at BIGINT NOT NULL
+);
@@ -0,0 +1,15 @@
+-- PAN, Aadhaar, email and mobile number are now sealed by the service before they are stored,
+-- and looked up through blind indexes. Rows written before this migration are re-encrypted by
+-- the service at startup.
+ALTER TABLE bookings ADD COLUMN IF NOT EXISTS customer_email_index TEXT;
+ALTER TABLE bookings ADD COLUMN IF NOT EXISTS mobile_number_index TEXT;
+ALTER TABLE bookings ADD COLUMN IF NOT EXISTS pii_key_id TEXT;
+
+DROP INDEX IF EXISTS bookings_customer_email_idx;
+CREATE INDEX IF NOT EXISTS bookings_customer_email_index_idx ON bookings (customer_email_index);
+CREATE INDEX IF NOT EXISTS bookings_mobile_number_index_idx ON bookings (mobile_number_index);
+CREATE INDEX IF NOT EXISTS bookings_pii_key_id_idx ON bookings (pii_key_id);
+
+-- Holds only need to tell customers apart, so they keep the blind index rather than the address.
+-- Holds placed before this migration no longer match their holder and expire on their own.
+ALTER TABLE slot_holds RENAME COLUMN holder_email TO holder_index;
@@ -0,0 +1,21 @@
+-- Bookings remember the principal of the customer who made them, so that data export and erasure
+-- requests can be made by principal as well as by email address.
+ALTER TABLE bookings ADD COLUMN IF NOT EXISTS customer_principal TEXT;
+CREATE INDEX IF NOT EXISTS bookings_customer_principal_idx ON bookings (customer_principal);
+
+-- Erased bookings keep their financial record, but their customer details are blanked.
+ALTER TABLE bookings ADD COLUMN IF NOT EXISTS erased_at BIGINT;
+
+CREATE INDEX IF NOT EXISTS outbox_events_booking_id_idx ON outbox_events (booking_id);
+
+-- Every data subject request and its outcome. Subjects are named by kind only, so that nothing of
+-- an erased customer is kept; the booking IDs lead to the financial records that remain.
+CREATE TABLE IF NOT EXISTS data_subject_requests (
+    id BIGSERIAL PRIMARY KEY,
+    request TEXT NOT NULL,
+    subject TEXT,
+    outcome TEXT NOT NULL,
+    reason TEXT,
+    booking_ids BIGINT[] NOT NULL,
+    requested_at BIGINT NOT NULL
+);
@@ -0,0 +1,7 @@
+-- The admin key last rotated to, sealed like customer details, which every instance switches to.
+CREATE TABLE IF NOT EXISTS admin_keys (
+    id SMALLINT PRIMARY KEY CHECK (id = 1),
+    principal TEXT NOT NULL,
+    sealed_key TEXT NOT NULL,
+    activated_at BIGINT NOT NULL
+);
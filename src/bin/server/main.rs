@@ -1,26 +1,91 @@
+use anyhow::Context;
 use offchain::config::Config;
+use offchain::domain::transactions::event_bus::EventBus;
+use offchain::domain::transactions::outbox::{OutboxConfig, OutboxDispatcher};
 use offchain::domain::transactions::service::Service;
 use offchain::inbound::http::{HttpServer, HttpServerConfig};
+use offchain::outbound::audit_log::AuditLog;
+use offchain::outbound::booking_store::BookingStore;
+use offchain::outbound::document_store::DocumentStorage;
 use offchain::outbound::email_client::EmailClient;
+use offchain::outbound::otp_sender::OtpSender;
 use offchain::outbound::payment_client::{ PaymentClient, PaymentConfig};
 use offchain::outbound::prometheus::Prometheus;
+use offchain::outbound::sms_client::SmsClient;
 use offchain::outbound::ic_agent::IcAgentTransactionRepository;
+use offchain::outbound::webhook_client::WebhookClient;
+use offchain::utils::redaction::RedactingFields;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let config = Config::from_env()?;
+    let mut config = Config::from_env()?;
 
-    // A minimal tracing middleware for request logging.
-    tracing_subscriber::fmt::init();
+    // A minimal tracing middleware for request logging, which keeps customer details out of the logs.
+    tracing_subscriber::fmt().fmt_fields(RedactingFields).init();
+
+    config.ic_network.fetch_root_key().await?;
+    tracing::info!(
+        "Calling the backend canister {} at {} as {} ({} key)",
+        config.ic_network.backend_id(),
+        config.ic_network.url(),
+        config.admin_identity.principal(),
+        config.admin_identity.key_type()
+    );
+    let backend_id = config.ic_network.backend_id();
 
     let prometheus = Prometheus::new();
-    let payment_client = PaymentClient::new(PaymentConfig { payment_key: config.razorpay_key, payment_secret: config.razorpay_secret });
+    let payment_client = PaymentClient::new(PaymentConfig {
+        payment_key: config.razorpay_key,
+        payment_secret: config.razorpay_secret,
+        callback_url: config.payment_callback_url,
+    });
     let email_client = EmailClient::new(config.email_config);
-    let ic_agent = IcAgentTransactionRepository::new(config.admin_private_key);
-    let offchain_service = Service::new(ic_agent, prometheus, email_client, payment_client);
+    let otp_sender = OtpSender::new(email_client.clone(), SmsClient::new(config.sms_config));
+    let webhook_client = WebhookClient::new(config.webhook_urls);
+    let booking_store = BookingStore::new(&config.booking_store).await?;
+    let document_store = DocumentStorage::new(&config.document_store);
+
+    // Seal rows written before encryption or under a retired key, without delaying startup.
+    let reencrypt_store = booking_store.clone();
+    tokio::spawn(async move {
+        match reencrypt_store.reencrypt_customer_details().await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!("Re-encrypted customer details of {} bookings", count),
+            Err(e) => tracing::error!("Failed to re-encrypt customer details: {:?}", e),
+        }
+    });
+
+    // Every reaction to bookings subscribes here; the service only publishes events.
+    let event_bus = EventBus::new()
+        .on_booking_event(email_client)
+        .on_booking_event(prometheus.clone())
+        .on_booking_event(AuditLog::new())
+        .on_booking_event(webhook_client)
+        .on_transaction_event(prometheus)
+        .on_transaction_event(AuditLog::new());
+
+    let ic_agent = IcAgentTransactionRepository::new(config.ic_network, config.admin_identity, config.delegation_lifetime)
+        .context("Failed to build the IC agent")?
+        .with_call_config(config.canister_call_config)
+        .with_events(event_bus.clone());
+
+    let outbox_dispatcher = OutboxDispatcher::new(booking_store.clone(), event_bus.clone(), OutboxConfig::default());
+    tokio::spawn(outbox_dispatcher.run());
+
+    let offchain_service = Service::new(ic_agent, payment_client, booking_store, document_store, config.document_cipher, otp_sender, event_bus)
+        .with_verifier(config.verifier)
+        .with_hold_duration(config.slot_hold_duration)
+        .with_age_policy(config.age_policy);
+
+    // Adopt the admin key rotated to through any instance, including before this one started.
+    tokio::spawn(offchain_service.clone().poll_admin_key());
 
     let server_config = HttpServerConfig {
         port: &config.server_port,
+        admin_api_token: config.admin_api_token.as_deref(),
+        session_issuer: config.session_issuer,
+        backend_id,
+        client_ip_header: config.client_ip_header.as_deref(),
     };
     let http_server = HttpServer::new(offchain_service, server_config).await?;
     http_server.run().await
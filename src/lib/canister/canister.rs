@@ -1,11 +1,10 @@
-use std::{env, sync::Arc};
-use candid::Principal;
-use ic_agent::{identity::DelegatedIdentity, Identity};
+use std::{sync::{Arc, Mutex}, time::{Duration, SystemTime, UNIX_EPOCH}};
+use anyhow::anyhow;
+use candid::{CandidType, Principal};
+use ic_agent::{identity::DelegatedIdentity, AgentError, Identity};
 use serde::{Deserialize, Serialize};
 
-use crate::{canister::backend::Backend, identity::{delegated_identity::DelegatedIdentityWire, ic::AgentWrapper}};
-
-use super::BACKEND_ID;
+use crate::{canister::backend::Backend, identity::{delegated_identity::{DelegatedIdentityWire, DELEGATION_RENEWAL_MARGIN}, ic::{AgentWrapper, IcNetwork}}};
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct CanistersAuthWire {
@@ -17,8 +16,8 @@ pub struct CanistersAuthWire {
 }
 
 impl CanistersAuthWire {
-    pub fn canisters(self) -> Result<Canisters<true>, k256::elliptic_curve::Error> {
-        let unauth = Canisters::<false>::default();
+    pub fn canisters(self, network: &IcNetwork) -> anyhow::Result<Canisters<true>> {
+        let unauth = Canisters::anonymous(network)?;
 
         let id: DelegatedIdentity = self.id.try_into()?;
         let arc_id = Arc::new(id);
@@ -31,7 +30,7 @@ impl CanistersAuthWire {
             // id: Some(arc_id),
             user_principal: self.user_principal,
             expiry: self.expiry,
-            backend_principal: BACKEND_ID,
+            backend_principal: network.backend_id(),
             // profile_details: Some(self.profile_details),
         })
     }
@@ -47,21 +46,30 @@ pub struct Canisters<const AUTH: bool> {
     // profile_details: Option<ProfileDetails>,
 }
 
-impl Default for Canisters<false> {
-    fn default() -> Self {
-        Self {
-            agent: AgentWrapper::build(|b| b),
+impl<const A: bool> std::fmt::Debug for Canisters<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Canisters")
+            .field("backend_principal", &self.backend_principal)
+            .field("expiry", &self.expiry)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Canisters<false> {
+    pub fn anonymous(network: &IcNetwork) -> Result<Self, AgentError> {
+        Ok(Self {
+            agent: AgentWrapper::build(network, |b| b)?,
             // id: None,
             user_principal: Principal::anonymous(),
             expiry: 0,
-            backend_principal: BACKEND_ID,
+            backend_principal: network.backend_id(),
             // profile_details: None,
-        }
+        })
     }
 }
 
 impl Canisters<true> {
-    pub fn authenticated(id: DelegatedIdentity) -> Canisters<true> {
+    pub fn authenticated(network: &IcNetwork, id: DelegatedIdentity) -> Result<Canisters<true>, AgentError> {
         let expiry = id
             .delegation_chain()
             .iter()
@@ -70,35 +78,41 @@ impl Canisters<true> {
             });
         let id = Arc::new(id);
 
-        Self {
-            agent: AgentWrapper::build(|b| b.with_arc_identity(id.clone())),
+        Ok(Self {
+            agent: AgentWrapper::build(network, |b| b.with_arc_identity(id.clone()))?,
             // id: Some(id),
             user_principal: Principal::anonymous(),
             expiry,
-            backend_principal: BACKEND_ID,
+            backend_principal: network.backend_id(),
             // profile_details: None,
-        }
+        })
     }
 
     pub fn expiry_ns(&self) -> u64 {
         self.expiry
     }
 
+    /// Whether the delegation expires before `now_ns + margin`, so that a call made now may be
+    /// rejected.
+    pub fn expires_within(&self, margin: Duration, now_ns: u64) -> bool {
+        self.expiry <= now_ns.saturating_add(margin.as_nanos() as u64)
+    }
+
     // pub fn identity(&self) -> &DelegatedIdentity {
     //     self.id
     //         .as_ref()
     //         .expect("Authenticated canisters must have an identity")
     // }
 
-    pub fn set_arc_id(id: Arc<impl Identity + 'static>) -> Canisters<true> {
-        Self {
-            agent: AgentWrapper::build(|b| b.with_arc_identity(id.clone())),
+    pub fn set_arc_id(network: &IcNetwork, id: Arc<impl Identity + 'static>) -> Result<Canisters<true>, AgentError> {
+        Ok(Self {
+            agent: AgentWrapper::build(network, |b| b.with_arc_identity(id.clone()))?,
             // id: Some(Arc::new(id)),
             user_principal: Principal::anonymous(),
             expiry: 0,
-            backend_principal: BACKEND_ID,
+            backend_principal: network.backend_id(),
             // profile_details: None,
-        }
+        })
     }
 
 
@@ -113,24 +127,82 @@ impl Canisters<true> {
     }
 }
 
+/// Canisters authenticated with short-lived delegations from a long-lived `identity`, which are
+/// renewed shortly before they expire. The long-lived key never signs calls itself.
+#[derive(Clone)]
+pub struct RenewingCanisters {
+    network: IcNetwork,
+    identity: Arc<dyn Identity>,
+    lifetime: Duration,
+    current: Arc<Mutex<Option<Canisters<true>>>>,
+}
+
+impl std::fmt::Debug for RenewingCanisters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RenewingCanisters").field("lifetime", &self.lifetime).finish_non_exhaustive()
+    }
+}
+
+impl RenewingCanisters {
+    /// `lifetime` must be longer than [DELEGATION_RENEWAL_MARGIN], or every call renews.
+    pub fn new(network: IcNetwork, identity: Arc<dyn Identity>, lifetime: Duration) -> Self {
+        Self {
+            network,
+            identity,
+            lifetime,
+            current: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Canisters whose delegation outlives [DELEGATION_RENEWAL_MARGIN], delegating anew if the
+    /// current one doesn't.
+    pub fn get(&self) -> anyhow::Result<Canisters<true>> {
+        let now_ns = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos() as u64;
+        let mut current = self.current.lock().map_err(|_| anyhow!("Delegation cache is poisoned"))?;
+        if let Some(canisters) = current.as_ref().filter(|c| !c.expires_within(DELEGATION_RENEWAL_MARGIN, now_ns)) {
+            return Ok(canisters.clone());
+        }
+
+        let wire = DelegatedIdentityWire::delegate(self.identity.as_ref(), self.network.backend_id(), self.lifetime);
+        let canisters = Canisters::authenticated(&self.network, wire.try_into()?)?;
+        tracing::debug!("Renewed delegation, expiring at {}", canisters.expiry_ns());
+        *current = Some(canisters.clone());
+        Ok(canisters)
+    }
+}
+
 impl<const A: bool> Canisters<A> {
     pub async fn backend(&self) -> Backend<'_> {
-        let agent = self.agent.get_agent();
-        // dotenv::dotenv().ok();
-        // let live = env::var("BACKEND").unwrap_or("LIVE".to_string()) == "LIVE" ;
-        let live = true;
-        if !live {
-            agent.fetch_root_key().await.unwrap();
-        }
-        Backend(self.backend_principal, agent)
+        // The root key of a local replica was set when the agent was built.
+        Backend(self.backend_principal, self.agent.get_agent())
     }
+
+    /// Succeeds only if the caller controls the backend canister: the management canister
+    /// reports the status of a canister to its controllers alone, and the call changes nothing.
+    pub async fn check_backend_controller(&self) -> anyhow::Result<()> {
+        let arg = candid::encode_args((CanisterIdRecord { canister_id: self.backend_principal },))?;
+        self.agent
+            .get_agent()
+            .update(&Principal::management_canister(), "canister_status")
+            .with_effective_canister_id(self.backend_principal)
+            .with_arg(arg)
+            .call_and_wait()
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(CandidType)]
+struct CanisterIdRecord {
+    canister_id: Principal,
 }
 
 pub async fn do_canister_auth(
+    network: &IcNetwork,
     auth: DelegatedIdentityWire,
 ) -> anyhow::Result<CanistersAuthWire > {
     let id = auth.clone().try_into()?;
-    let canisters = Canisters::<true>::authenticated(id);
+    let canisters = Canisters::<true>::authenticated(network, id)?;
 
     // let user = canisters.authenticated_user().await;
 
@@ -140,7 +212,7 @@ pub async fn do_canister_auth(
         id: auth,
         user_principal: canisters.user_principal,
         expiry: canisters.expiry,
-        backend_principal: BACKEND_ID,
+        backend_principal: canisters.backend_principal,
     };
 
     Ok(cans_wire)
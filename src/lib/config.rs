@@ -1,13 +1,28 @@
-use std::{env, time::{SystemTime, UNIX_EPOCH}};
+use std::{env, time::{Duration, SystemTime, UNIX_EPOCH}};
 
 use anyhow::{anyhow, Context};
+use candid::Principal;
 
-use crate::outbound::email_client::EmailConfig;
+use crate::domain::transactions::models::age_policy::{AgePolicy, AgeRange};
+use crate::domain::transactions::models::document::DocumentCipher;
+use crate::domain::transactions::models::pii::PiiCipher;
+use crate::domain::transactions::models::verification::Verifier;
+use crate::identity::admin::{AdminIdentity, AdminKeySource};
+use crate::identity::delegated_identity::DELEGATION_RENEWAL_MARGIN;
+use crate::identity::ic::IcNetwork;
+use crate::identity::session::SessionIssuer;
+use crate::outbound::{booking_store::BookingStoreConfig, email_client::EmailConfig, postgres::PostgresConfig};
+use crate::outbound::canister_call::CanisterCallConfig;
+use crate::outbound::{document_store::DocumentStoreConfig, s3_document_store::S3Config, sms_client::SmsConfig};
 
 const SERVER_PORT_KEY: &str = "SERVER_PORT";
 
 const BACKEND_LIVE_OR_LOCAL: &str = "BACKEND";
 
+const IC_URL: &str = "IC_URL";
+
+const BACKEND_CANISTER_ID: &str = "BACKEND_CANISTER_ID";
+
 const EMAIL_CLIENT_ID: &str = "EMAIL_CLIENT_ID";
 
 const EMAIL_CLIENT_SECRET: &str = "EMAIL_CLIENT_SECRET";
@@ -18,18 +33,99 @@ const EMAIL_REFRESH_TOKEN: &str = "EMAIL_REFRESH_TOKEN";
 
 const ADMIN_PRIVATE_KEY: &str = "FUEL_DAO_CANISTER_CONTROLLER_PRIVATE_KEY";
 
+const ADMIN_PRIVATE_KEY_FILE: &str = "FUEL_DAO_CANISTER_CONTROLLER_PRIVATE_KEY_FILE";
+
+const ADMIN_PRIVATE_KEY_PASSPHRASE: &str = "FUEL_DAO_CANISTER_CONTROLLER_PRIVATE_KEY_PASSPHRASE";
+
 const RAZORPAY_KEY: &str = "RAZORPAY_KEY";
 
 const RAZORPAY_SECRET: &str = "RAZORPAY_SECRET";
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+const BOOKING_STORE: &str = "BOOKING_STORE";
+
+const DATABASE_URL: &str = "DATABASE_URL";
+
+const DATABASE_MAX_CONNECTIONS: &str = "DATABASE_MAX_CONNECTIONS";
+
+const PII_ENCRYPTION_KEYS: &str = "PII_ENCRYPTION_KEYS";
+
+const PII_ACTIVE_KEY_ID: &str = "PII_ACTIVE_KEY_ID";
+
+const PII_INDEX_KEY: &str = "PII_INDEX_KEY";
+
+const WEBHOOK_URLS: &str = "WEBHOOK_URLS";
+
+const ADMIN_API_TOKEN: &str = "ADMIN_API_TOKEN";
+
+const CLIENT_IP_HEADER: &str = "CLIENT_IP_HEADER";
+
+/// Set by Fly on every machine of the app.
+const FLY_APP_NAME: &str = "FLY_APP_NAME";
+
+const SLOT_HOLD_MINUTES: &str = "SLOT_HOLD_MINUTES";
+
+const CAR_AGE_LIMITS: &str = "CAR_AGE_LIMITS";
+
+const CAR_CATEGORIES: &str = "CAR_CATEGORIES";
+
+const DOCUMENT_ENCRYPTION_KEY: &str = "DOCUMENT_ENCRYPTION_KEY";
+
+const DOCUMENT_STORE: &str = "DOCUMENT_STORE";
+
+const DOCUMENT_DIR: &str = "DOCUMENT_DIR";
+
+const S3_ENDPOINT: &str = "S3_ENDPOINT";
+
+const S3_BUCKET: &str = "S3_BUCKET";
+
+const S3_REGION: &str = "S3_REGION";
+
+const S3_ACCESS_KEY_ID: &str = "S3_ACCESS_KEY_ID";
+
+const S3_SECRET_ACCESS_KEY: &str = "S3_SECRET_ACCESS_KEY";
+
+const VERIFICATION_SECRET: &str = "VERIFICATION_SECRET";
+
+const SESSION_SECRET: &str = "SESSION_SECRET";
+
+const SESSION_TTL_MINUTES: &str = "SESSION_TTL_MINUTES";
+
+const DELEGATION_LIFETIME_MINUTES: &str = "DELEGATION_LIFETIME_MINUTES";
+
+const IC_CALL_DEADLINE_SECONDS: &str = "IC_CALL_DEADLINE_SECONDS";
+
+const IC_CALL_MAX_RETRIES: &str = "IC_CALL_MAX_RETRIES";
+
+const IC_INGRESS_EXPIRY_SECONDS: &str = "IC_INGRESS_EXPIRY_SECONDS";
+
+const SMS_ACCOUNT_SID: &str = "SMS_ACCOUNT_SID";
+
+const SMS_AUTH_TOKEN: &str = "SMS_AUTH_TOKEN";
+
+const SMS_FROM: &str = "SMS_FROM";
+
+#[derive(Debug, Clone)]
 pub struct Config {
     pub server_port: String,
-    pub live_or_local: String,
+    pub ic_network: IcNetwork,
+    pub payment_callback_url: String,
     pub email_config: EmailConfig,
-    pub admin_private_key: String,
+    pub admin_identity: AdminIdentity,
+    pub delegation_lifetime: Duration,
+    pub canister_call_config: CanisterCallConfig,
     pub razorpay_key: String, 
     pub razorpay_secret: String,
+    pub booking_store: BookingStoreConfig,
+    pub webhook_urls: Vec<String>,
+    pub admin_api_token: Option<String>,
+    pub client_ip_header: Option<String>,
+    pub slot_hold_duration: Duration,
+    pub age_policy: AgePolicy,
+    pub document_store: DocumentStoreConfig,
+    pub document_cipher: DocumentCipher,
+    pub verifier: Verifier,
+    pub session_issuer: SessionIssuer,
+    pub sms_config: SmsConfig,
 }
 
 impl Config {
@@ -40,13 +136,162 @@ impl Config {
         let server_port = load_env(SERVER_PORT_KEY).unwrap_or("50051".to_string());
 
 
-        let live_or_local = load_env(BACKEND_LIVE_OR_LOCAL).unwrap_or("LIVE".to_string());
+        // A local replica's root key is fetched at startup, see [IcNetwork::fetch_root_key].
+        let (ic_network, payment_callback_url) = match load_env(BACKEND_LIVE_OR_LOCAL).unwrap_or("LIVE".to_string()).as_str() {
+            "LIVE" => (IcNetwork::live(), "https://fuelev.in/payment"),
+            "LOCAL" => (IcNetwork::local(), "http://localhost:8080/payment"),
+            other => return Err(anyhow!("Unknown backend {other}, expected LIVE or LOCAL")),
+        };
+        let ic_network = match load_env(IC_URL) {
+            Ok(url) => {
+                let parsed = reqwest::Url::parse(url.trim()).with_context(|| format!("Failed to parse {IC_URL}"))?;
+                if !matches!(parsed.scheme(), "http" | "https") || !parsed.has_host() {
+                    return Err(anyhow!("{IC_URL} must be an http or https URL with a host, got {url}"));
+                }
+                ic_network.with_url(url.trim())
+            }
+            Err(_) => ic_network,
+        };
+        // Without an ID, the one listed for the network in did/canister_ids.json.
+        let ic_network = match load_env(BACKEND_CANISTER_ID) {
+            Ok(id) => ic_network.with_backend_id(
+                Principal::from_text(id.trim()).with_context(|| format!("Failed to parse {BACKEND_CANISTER_ID}"))?,
+            ),
+            Err(_) => ic_network,
+        };
+        // Messages must expire before the delegation they were signed under is renewed.
+        let ingress_expiry_seconds: u64 = load_env(IC_INGRESS_EXPIRY_SECONDS)
+            .unwrap_or("240".to_string())
+            .parse()
+            .context("Failed to parse ingress expiry seconds")?;
+        if !(1..=DELEGATION_RENEWAL_MARGIN.as_secs()).contains(&ingress_expiry_seconds) {
+            return Err(anyhow!(
+                "{IC_INGRESS_EXPIRY_SECONDS} must be between 1 and {}, got {ingress_expiry_seconds}",
+                DELEGATION_RENEWAL_MARGIN.as_secs()
+            ));
+        }
+        let ic_network = ic_network.with_ingress_expiry(Duration::from_secs(ingress_expiry_seconds));
+
+        let canister_call_config = CanisterCallConfig {
+            deadline: Duration::from_secs(
+                load_env(IC_CALL_DEADLINE_SECONDS)
+                    .unwrap_or("30".to_string())
+                    .parse()
+                    .context("Failed to parse canister call deadline seconds")?,
+            ),
+            max_retries: load_env(IC_CALL_MAX_RETRIES)
+                .unwrap_or("3".to_string())
+                .parse()
+                .context("Failed to parse canister call max retries")?,
+            ..CanisterCallConfig::default()
+        };
 
         let razorpay_key = load_env(RAZORPAY_KEY).ok();
         
         let razorpay_secret = load_env(RAZORPAY_SECRET).ok();
         
-        let admin_private_key = load_env(ADMIN_PRIVATE_KEY).map_err(|f| anyhow!("Failed to get admin private key {f:?}"))?;
+        let admin_key_source = match (load_env(ADMIN_PRIVATE_KEY), load_env(ADMIN_PRIVATE_KEY_FILE)) {
+            (Ok(pem), Err(_)) => AdminKeySource::Pem(pem),
+            (Err(_), Ok(path)) => AdminKeySource::File(path.into()),
+            (Ok(_), Ok(_)) => return Err(anyhow!("Set only one of {ADMIN_PRIVATE_KEY} and {ADMIN_PRIVATE_KEY_FILE}")),
+            (Err(_), Err(_)) => return Err(anyhow!("Set {ADMIN_PRIVATE_KEY} or {ADMIN_PRIVATE_KEY_FILE}")),
+        };
+        let admin_identity = AdminIdentity::load(&admin_key_source, load_env(ADMIN_PRIVATE_KEY_PASSPHRASE).ok().as_deref())
+            .context("Failed to load the admin identity")?;
+
+        // Delegations for the admin key are renewed 5 minutes before they expire.
+        let delegation_lifetime_minutes: u64 = load_env(DELEGATION_LIFETIME_MINUTES)
+            .unwrap_or("60".to_string())
+            .parse()
+            .context("Failed to parse delegation lifetime minutes")?;
+        if delegation_lifetime_minutes < 10 {
+            return Err(anyhow!("{DELEGATION_LIFETIME_MINUTES} must be at least 10, got {delegation_lifetime_minutes}"));
+        }
+
+        let booking_store = match load_env(BOOKING_STORE).unwrap_or("MEMORY".to_string()).as_str() {
+            "MEMORY" => BookingStoreConfig::InMemory,
+            "POSTGRES" => BookingStoreConfig::Postgres(PostgresConfig {
+                url: load_env(DATABASE_URL)?,
+                max_connections: load_env(DATABASE_MAX_CONNECTIONS)
+                    .unwrap_or("10".to_string())
+                    .parse()
+                    .context("Failed to parse database max connections")?,
+                pii_cipher: PiiCipher::from_base64(
+                    &load_env(PII_ACTIVE_KEY_ID)?,
+                    &load_env(PII_ENCRYPTION_KEYS)?,
+                    &load_env(PII_INDEX_KEY)?,
+                )?,
+            }),
+            other => return Err(anyhow!("Unknown booking store {other}, expected MEMORY or POSTGRES")),
+        };
+
+        let webhook_urls = load_env(WEBHOOK_URLS)
+            .map(|urls| urls.split(',').map(|url| url.trim().to_string()).filter(|url| !url.is_empty()).collect())
+            .unwrap_or_default();
+
+        let admin_api_token = load_env(ADMIN_API_TOKEN).ok();
+        let client_ip_header = load_env(CLIENT_IP_HEADER).ok();
+
+        // Razorpay rejects payment links that expire in less than 15 minutes.
+        let slot_hold_minutes: u64 = load_env(SLOT_HOLD_MINUTES)
+            .unwrap_or("20".to_string())
+            .parse()
+            .context("Failed to parse slot hold minutes")?;
+        if slot_hold_minutes < 15 {
+            return Err(anyhow!("{SLOT_HOLD_MINUTES} must be at least 15, got {slot_hold_minutes}"));
+        }
+
+        let age_policy = load_age_policy()?;
+
+        let document_store = match load_env(DOCUMENT_STORE).unwrap_or("FILESYSTEM".to_string()).as_str() {
+            "FILESYSTEM" => DocumentStoreConfig::Filesystem(load_env(DOCUMENT_DIR).unwrap_or("documents".to_string()).into()),
+            "S3" => DocumentStoreConfig::S3(S3Config {
+                endpoint: load_env(S3_ENDPOINT)?,
+                bucket: load_env(S3_BUCKET)?,
+                region: load_env(S3_REGION).unwrap_or("us-east-1".to_string()),
+                access_key_id: load_env(S3_ACCESS_KEY_ID)?,
+                secret_access_key: load_env(S3_SECRET_ACCESS_KEY)?,
+            }),
+            other => return Err(anyhow!("Unknown document store {other}, expected FILESYSTEM or S3")),
+        };
+
+        // Fly runs the app on several machines, which must all see the same bookings, holds, codes
+        // and documents.
+        if let Ok(app) = load_env(FLY_APP_NAME) {
+            if matches!(booking_store, BookingStoreConfig::InMemory) {
+                return Err(anyhow!("{BOOKING_STORE} must be POSTGRES when running on Fly as {app}"));
+            }
+            if matches!(document_store, DocumentStoreConfig::Filesystem(_)) {
+                return Err(anyhow!("{DOCUMENT_STORE} must be S3 when running on Fly as {app}"));
+            }
+        }
+
+        let document_cipher = DocumentCipher::from_base64(&load_env(DOCUMENT_ENCRYPTION_KEY)?)?;
+
+        // Verification tokens are checked by whichever instance serves the payment request.
+        let verification_secret = load_env(VERIFICATION_SECRET)?;
+        if verification_secret.len() < 32 {
+            return Err(anyhow!("{VERIFICATION_SECRET} must be at least 32 characters long"));
+        }
+        let verifier = Verifier::new(verification_secret.as_bytes());
+
+        // Session tokens are checked by whichever instance serves the booking request.
+        let session_secret = load_env(SESSION_SECRET)?;
+        if session_secret.len() < 32 {
+            return Err(anyhow!("{SESSION_SECRET} must be at least 32 characters long"));
+        }
+        let session_ttl_minutes: u64 = load_env(SESSION_TTL_MINUTES)
+            .unwrap_or("15".to_string())
+            .parse()
+            .context("Failed to parse session TTL minutes")?;
+        let session_issuer = SessionIssuer::new(session_secret.as_bytes()).with_ttl(Duration::from_secs(session_ttl_minutes * 60));
+
+        // Every payment link needs a verified mobile number, so the server can't run without SMS.
+        let sms_config = SmsConfig {
+            account_sid: load_env(SMS_ACCOUNT_SID)?,
+            auth_token: load_env(SMS_AUTH_TOKEN)?,
+            from: load_env(SMS_FROM)?,
+        };
 
         let email_config =   EmailConfig {
                 client_id: load_env(EMAIL_CLIENT_ID).ok(),
@@ -58,15 +303,60 @@ impl Config {
 
         Ok(Config {
             server_port,
-            live_or_local, 
+            ic_network,
+            payment_callback_url: payment_callback_url.to_string(),
             email_config,
-            admin_private_key: admin_private_key,
+            admin_identity,
+            delegation_lifetime: Duration::from_secs(delegation_lifetime_minutes * 60),
+            canister_call_config,
             razorpay_key: razorpay_key.context("Failed to get razorpay payment ket")?, 
             razorpay_secret: razorpay_secret.context("Failed to get razorpay payment secret")?,
+            booking_store,
+            webhook_urls,
+            admin_api_token,
+            client_ip_header,
+            slot_hold_duration: Duration::from_secs(slot_hold_minutes * 60),
+            age_policy,
+            document_store,
+            document_cipher,
+            verifier,
+            session_issuer,
+            sms_config,
         })
     }
 }
 
+/// Parse `CAR_AGE_LIMITS` (`category=min-max,...`) and `CAR_CATEGORIES` (`car_id=category,...`).
+fn load_age_policy() -> anyhow::Result<AgePolicy> {
+    let mut policy = AgePolicy::new();
+    for (category, range) in load_pairs(CAR_AGE_LIMITS)? {
+        let range: AgeRange = range.parse()?;
+        policy = policy.with_category(&category, range);
+    }
+    for (car_id, category) in load_pairs(CAR_CATEGORIES)? {
+        let car_id = car_id.parse().with_context(|| format!("Failed to parse car ID {car_id} in {CAR_CATEGORIES}"))?;
+        // A misspelt category would otherwise let anyone 18+ rent the car.
+        if !policy.has_category(&category) {
+            return Err(anyhow!("Car {car_id} in {CAR_CATEGORIES} has category {category}, which {CAR_AGE_LIMITS} does not set"));
+        }
+        policy = policy.with_car(car_id, &category);
+    }
+    Ok(policy)
+}
+
+fn load_pairs(key: &str) -> anyhow::Result<Vec<(String, String)>> {
+    load_env(key)
+        .unwrap_or_default()
+        .split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            pair.split_once('=')
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                .ok_or_else(|| anyhow!("Expected key=value in {key}, got {pair}"))
+        })
+        .collect()
+}
+
 fn load_env(key: &str) -> anyhow::Result<String> {
     env::var(key).with_context(|| format!("failed to load environment variable {}", key))
 }
\ No newline at end of file
@@ -0,0 +1,112 @@
+/*!
+   Module `event_bus` lets outbound adapters subscribe to what happens in the transaction domain
+   without the [Service](super::service::Service) knowing about them.
+
+   Subscribers are registered once at startup. [BookingEventHandler]s receive the durable
+   [BookingEvent](crate::domain::transactions::models::event::BookingEvent)s through the
+   [OutboxDispatcher](super::outbox::OutboxDispatcher); [TransactionEventHandler]s receive
+   [TransactionEvent]s as soon as the service, or an adapter such as the canister repository,
+   publishes them.
+*/
+
+use std::sync::Arc;
+
+use crate::domain::transactions::models::event::TransactionEvent;
+use crate::domain::transactions::ports::{BookingEventHandler, TransactionEventHandler};
+
+/// The subscribers of the transaction domain. Cloning is cheap and shares the subscribers.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    booking_handlers: Vec<Arc<dyn BookingEventHandler>>,
+    transaction_handlers: Vec<Arc<dyn TransactionEventHandler>>,
+}
+
+impl std::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventBus")
+            .field("booking_handlers", &self.booking_handlers.iter().map(|h| h.name()).collect::<Vec<_>>())
+            .field("transaction_handlers", &self.transaction_handlers.iter().map(|h| h.name()).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Subscribe `handler` to booking events delivered from the outbox.
+    pub fn on_booking_event(mut self, handler: impl BookingEventHandler) -> Self {
+        self.booking_handlers.push(Arc::new(handler));
+        self
+    }
+
+    /// Subscribe `handler` to transaction events as they are published.
+    pub fn on_transaction_event(mut self, handler: impl TransactionEventHandler) -> Self {
+        self.transaction_handlers.push(Arc::new(handler));
+        self
+    }
+
+    // Getter for booking_handlers
+    pub fn booking_handlers(&self) -> &[Arc<dyn BookingEventHandler>] {
+        &self.booking_handlers
+    }
+
+    /// Hand `event` to every [TransactionEventHandler] in turn, logging any that fail.
+    pub async fn publish(&self, event: TransactionEvent) {
+        for handler in &self.transaction_handlers {
+            if let Err(e) = handler.handle(&event).await {
+                tracing::warn!("Handler {} failed on {}: {}", handler.name(), event.name(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::domain::transactions::ports::HandlerFuture;
+
+    #[derive(Clone, Default)]
+    struct Recorder {
+        fail: bool,
+        events: Arc<Mutex<Vec<TransactionEvent>>>,
+    }
+
+    impl TransactionEventHandler for Recorder {
+        fn name(&self) -> &'static str {
+            "recorder"
+        }
+
+        fn handle<'a>(&'a self, event: &'a TransactionEvent) -> HandlerFuture<'a> {
+            Box::pin(async move {
+                self.events.lock().unwrap().push(event.clone());
+                if self.fail {
+                    Err("unavailable".to_string())
+                } else {
+                    Ok(())
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish_reaches_every_handler_after_a_failure() {
+        let failing = Recorder {
+            fail: true,
+            ..Recorder::default()
+        };
+        let healthy = Recorder::default();
+        let bus = EventBus::new()
+            .on_transaction_event(failing.clone())
+            .on_transaction_event(healthy.clone());
+
+        let event = TransactionEvent::TransactionCreated { booking_id: 1 };
+        bus.publish(event.clone()).await;
+
+        assert_eq!(*failing.events.lock().unwrap(), vec![event.clone()]);
+        assert_eq!(*healthy.events.lock().unwrap(), vec![event]);
+    }
+}
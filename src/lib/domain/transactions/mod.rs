@@ -1,3 +1,5 @@
 pub mod service;
 pub mod ports;
 pub mod models;
+pub mod outbox;
+pub mod event_bus;
\ No newline at end of file
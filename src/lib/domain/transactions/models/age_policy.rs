@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use super::transaction::ErrorCode;
+
+/// The category of cars without one of their own.
+pub const DEFAULT_CAR_CATEGORY: &str = "standard";
+
+/// The youngest and, optionally, oldest age at which a customer may drive a category of car.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AgeRange {
+    pub min: u8,
+    pub max: Option<u8>,
+}
+
+impl Default for AgeRange {
+    fn default() -> Self {
+        Self { min: 18, max: None }
+    }
+}
+
+impl Display for AgeRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.max {
+            Some(max) => write!(f, "{}-{}", self.min, max),
+            None => write!(f, "{}-", self.min),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Error)]
+#[error("{0} is not a valid age range, expected e.g. 21-70 or 21-")]
+pub struct AgeRangeError(String);
+
+impl FromStr for AgeRange {
+    type Err = AgeRangeError;
+
+    /// Parse `min-max`, or `min-` for no maximum.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || AgeRangeError(s.to_string());
+        let (min, max) = s.trim().split_once('-').ok_or_else(error)?;
+        let min = min.trim().parse().map_err(|_| error())?;
+        let max = match max.trim() {
+            "" => None,
+            max => Some(max.parse().map_err(|_| error())?),
+        };
+        match max {
+            Some(max) if max < min => Err(error()),
+            _ => Ok(Self { min, max }),
+        }
+    }
+}
+
+/// Which customers may rent which cars, by the car's category.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AgePolicy {
+    categories: HashMap<String, AgeRange>,
+    cars: HashMap<u64, String>,
+}
+
+impl AgePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the ages allowed for `category`. Cars of unknown categories use the [AgeRange]
+    /// of [DEFAULT_CAR_CATEGORY], or 18+ if that is not set either.
+    pub fn with_category(mut self, category: &str, range: AgeRange) -> Self {
+        self.categories.insert(category.to_string(), range);
+        self
+    }
+
+    /// Put `car_id` in `category`. Cars are in [DEFAULT_CAR_CATEGORY] unless set here.
+    pub fn with_car(mut self, car_id: u64, category: &str) -> Self {
+        self.cars.insert(car_id, category.to_string());
+        self
+    }
+
+    /// Whether ages are set for `category`, or it is [DEFAULT_CAR_CATEGORY], which is 18+ unless
+    /// set.
+    pub fn has_category(&self, category: &str) -> bool {
+        category == DEFAULT_CAR_CATEGORY || self.categories.contains_key(category)
+    }
+
+    fn category(&self, car_id: u64) -> &str {
+        self.cars.get(&car_id).map(String::as_str).unwrap_or(DEFAULT_CAR_CATEGORY)
+    }
+
+    fn range(&self, category: &str) -> AgeRange {
+        self.categories
+            .get(category)
+            .or_else(|| self.categories.get(DEFAULT_CAR_CATEGORY))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Check that a customer who is `age` on the first day of the booking may drive `car_id`.
+    pub fn check(&self, car_id: u64, age: u8) -> Result<(), AgeRestrictionError> {
+        let category = self.category(car_id);
+        let range = self.range(category);
+        if age < range.min {
+            Err(AgeRestrictionError::TooYoung {
+                age,
+                minimum: range.min,
+                category: category.to_string(),
+            })
+        } else if let Some(maximum) = range.max.filter(|maximum| age > *maximum) {
+            Err(AgeRestrictionError::TooOld {
+                age,
+                maximum,
+                category: category.to_string(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum AgeRestrictionError {
+    #[error("Drivers of {category} cars must be at least {minimum}, but would be {age} on the start date")]
+    TooYoung { age: u8, minimum: u8, category: String },
+
+    #[error("Drivers of {category} cars must be at most {maximum}, but would be {age} on the start date")]
+    TooOld { age: u8, maximum: u8, category: String },
+}
+
+impl ErrorCode for AgeRestrictionError {
+    fn code(&self) -> &'static str {
+        match self {
+            AgeRestrictionError::TooYoung { .. } => "driver_too_young",
+            AgeRestrictionError::TooOld { .. } => "driver_too_old",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_age_is_checked_against_the_car_category() {
+        let policy = AgePolicy::new()
+            .with_category("premium", "21-70".parse().unwrap())
+            .with_car(7, "premium");
+
+        assert_eq!(policy.check(1, 18), Ok(()));
+        assert_eq!(policy.check(7, 21), Ok(()));
+        assert_eq!(
+            policy.check(7, 20),
+            Err(AgeRestrictionError::TooYoung { age: 20, minimum: 21, category: "premium".to_string() })
+        );
+        assert_eq!(
+            policy.check(7, 71),
+            Err(AgeRestrictionError::TooOld { age: 71, maximum: 70, category: "premium".to_string() })
+        );
+    }
+
+    #[test]
+    fn test_only_configured_categories_and_the_default_are_known() {
+        let policy = AgePolicy::new().with_category("premium", "21-70".parse().unwrap());
+
+        assert!(policy.has_category("premium"));
+        assert!(policy.has_category(DEFAULT_CAR_CATEGORY));
+        assert!(!policy.has_category("premum"));
+    }
+
+    #[test]
+    fn test_age_range_parsing() {
+        assert_eq!("21-".parse::<AgeRange>().unwrap(), AgeRange { min: 21, max: None });
+        assert_eq!("18-75".parse::<AgeRange>().unwrap(), AgeRange { min: 18, max: Some(75) });
+        assert!("30-21".parse::<AgeRange>().is_err());
+        assert!("21".parse::<AgeRange>().is_err());
+    }
+}
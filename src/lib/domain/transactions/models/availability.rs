@@ -0,0 +1,222 @@
+use serde::Serialize;
+use thiserror::Error;
+
+use super::booking::BookingRepositoryError;
+
+/// The longest range a single availability request may cover, in seconds (92 days).
+pub const MAX_AVAILABILITY_RANGE: u64 = 92 * 24 * 60 * 60;
+
+/// A half-open interval `[start, end)` of unix timestamps in seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interval {
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntervalStatus {
+    Booked,
+    /// Held for a customer who has been sent a payment link, until they pay or the hold expires.
+    Held,
+    Free,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub struct AvailabilityInterval {
+    pub start: u64,
+    pub end: u64,
+    pub status: IntervalStatus,
+}
+
+/// The booked, held and free intervals of a car between `from` and `to`, in chronological order
+/// and covering the whole range without gaps.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Availability {
+    car_id: u64,
+    from: u64,
+    to: u64,
+    intervals: Vec<AvailabilityInterval>,
+}
+
+impl Availability {
+    /// Build the calendar for `range` from the car's `booked` intervals, which may overlap each
+    /// other, arrive in any order, and extend beyond `range`.
+    pub fn from_booked(car_id: u64, range: &AvailabilityRange, booked: Vec<Interval>) -> Self {
+        Self::from_slots(car_id, range, booked, Vec::new())
+    }
+
+    /// Build the calendar for `range` from the car's `booked` and `held` intervals, as in
+    /// [Availability::from_booked]. Where a hold overlaps a booking, the time is booked.
+    pub fn from_slots(car_id: u64, range: &AvailabilityRange, booked: Vec<Interval>, held: Vec<Interval>) -> Self {
+        let clip = |slot: &Interval| Interval { start: slot.start.max(range.from), end: slot.end.min(range.to) };
+        let slots: Vec<(Interval, IntervalStatus)> = booked
+            .iter()
+            .map(|slot| (clip(slot), IntervalStatus::Booked))
+            .chain(held.iter().map(|slot| (clip(slot), IntervalStatus::Held)))
+            .filter(|(slot, _)| slot.start < slot.end)
+            .collect();
+
+        let mut bounds: Vec<u64> = slots.iter().flat_map(|(slot, _)| [slot.start, slot.end]).collect();
+        bounds.extend([range.from, range.to]);
+        bounds.sort_unstable();
+        bounds.dedup();
+
+        let mut intervals: Vec<AvailabilityInterval> = Vec::new();
+        for pair in bounds.windows(2) {
+            let (start, end) = (pair[0], pair[1]);
+            let covering = |status| slots.iter().any(|(slot, s)| *s == status && slot.start <= start && end <= slot.end);
+            let status = if covering(IntervalStatus::Booked) {
+                IntervalStatus::Booked
+            } else if covering(IntervalStatus::Held) {
+                IntervalStatus::Held
+            } else {
+                IntervalStatus::Free
+            };
+            match intervals.last_mut() {
+                Some(last) if last.status == status => last.end = end,
+                _ => intervals.push(AvailabilityInterval { start, end, status }),
+            }
+        }
+
+        Self {
+            car_id,
+            from: range.from,
+            to: range.to,
+            intervals,
+        }
+    }
+
+    // Getter for car_id
+    pub fn car_id(&self) -> u64 {
+        self.car_id
+    }
+
+    // Getter for intervals
+    pub fn intervals(&self) -> &[AvailabilityInterval] {
+        &self.intervals
+    }
+}
+
+/// A validated `[from, to)` range for an availability request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AvailabilityRange {
+    from: u64,
+    to: u64,
+}
+
+impl AvailabilityRange {
+    pub fn new(from: u64, to: u64) -> Result<Self, AvailabilityError> {
+        if to <= from {
+            Err(AvailabilityError::InvalidRange { from, to })
+        } else if to - from > MAX_AVAILABILITY_RANGE {
+            Err(AvailabilityError::RangeTooLong { max_seconds: MAX_AVAILABILITY_RANGE })
+        } else {
+            Ok(Self { from, to })
+        }
+    }
+
+    // Getter for from
+    pub fn from(&self) -> u64 {
+        self.from
+    }
+
+    // Getter for to
+    pub fn to(&self) -> u64 {
+        self.to
+    }
+
+    /// Whether `other` lies within this range.
+    pub fn contains(&self, other: &AvailabilityRange) -> bool {
+        self.from <= other.from && other.to <= self.to
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AvailabilityError {
+    #[error("Invalid range: {to} must be greater than {from}")]
+    InvalidRange { from: u64, to: u64 },
+
+    #[error("Range must not be longer than {max_seconds} seconds")]
+    RangeTooLong { max_seconds: u64 },
+
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error),
+}
+
+impl From<BookingRepositoryError> for AvailabilityError {
+    fn from(e: BookingRepositoryError) -> Self {
+        match e {
+            BookingRepositoryError::Unknown(cause) => Self::Unknown(cause),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn booked(start: u64, end: u64) -> Interval {
+        Interval { start, end }
+    }
+
+    fn interval(start: u64, end: u64, status: IntervalStatus) -> AvailabilityInterval {
+        AvailabilityInterval { start, end, status }
+    }
+
+    #[test]
+    fn test_free_and_booked_intervals_cover_the_range() {
+        let range = AvailabilityRange::new(100, 1000).unwrap();
+        // Out of order, overlapping, adjacent and partly outside the range
+        let slots = vec![booked(600, 700), booked(50, 200), booked(150, 300), booked(300, 400), booked(950, 2000)];
+
+        let availability = Availability::from_booked(7, &range, slots);
+
+        assert_eq!(
+            availability.intervals(),
+            [
+                interval(100, 400, IntervalStatus::Booked),
+                interval(400, 600, IntervalStatus::Free),
+                interval(600, 700, IntervalStatus::Booked),
+                interval(700, 950, IntervalStatus::Free),
+                interval(950, 1000, IntervalStatus::Booked),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_holds_are_shown_where_the_car_is_not_booked() {
+        let range = AvailabilityRange::new(100, 1000).unwrap();
+        // Overlapping the booking, adjacent to it, and overlapping each other
+        let held = vec![booked(250, 350), booked(400, 500), booked(450, 600)];
+
+        let availability = Availability::from_slots(7, &range, vec![booked(300, 400)], held);
+
+        assert_eq!(
+            availability.intervals(),
+            [
+                interval(100, 250, IntervalStatus::Free),
+                interval(250, 300, IntervalStatus::Held),
+                interval(300, 400, IntervalStatus::Booked),
+                interval(400, 600, IntervalStatus::Held),
+                interval(600, 1000, IntervalStatus::Free),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_bookings_is_one_free_interval() {
+        let range = AvailabilityRange::new(100, 1000).unwrap();
+        let availability = Availability::from_booked(7, &range, vec![booked(0, 50), booked(2000, 3000)]);
+        assert_eq!(availability.intervals(), [interval(100, 1000, IntervalStatus::Free)]);
+    }
+
+    #[test]
+    fn test_range_is_validated() {
+        assert!(matches!(AvailabilityRange::new(10, 10), Err(AvailabilityError::InvalidRange { .. })));
+        assert!(matches!(
+            AvailabilityRange::new(0, MAX_AVAILABILITY_RANGE + 1),
+            Err(AvailabilityError::RangeTooLong { .. })
+        ));
+    }
+}
@@ -0,0 +1,203 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use super::transaction::Transaction;
+
+/// A [Transaction] as recorded by the offchain service, together with its payment lifecycle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Booking {
+    transaction: Transaction,
+    total_amount: Option<f64>,
+    status: BookingStatus,
+    payment_link: Option<String>,
+    payment_id: Option<String>,
+    customer_principal: Option<String>,
+    erased_at: Option<u64>,
+}
+
+impl Booking {
+    // Constructor: a booking that has been validated by the canister but not yet paid for
+    pub fn awaiting_payment(transaction: Transaction, total_amount: f64) -> Self {
+        Self {
+            transaction,
+            total_amount: Some(total_amount),
+            status: BookingStatus::AwaitingPayment,
+            payment_link: None,
+            payment_id: None,
+            customer_principal: None,
+            erased_at: None,
+        }
+    }
+
+    // Constructor: used by repositories to rebuild a stored booking
+    pub fn new(
+        transaction: Transaction,
+        total_amount: Option<f64>,
+        status: BookingStatus,
+        payment_link: Option<String>,
+        payment_id: Option<String>,
+    ) -> Self {
+        Self {
+            transaction,
+            total_amount,
+            status,
+            payment_link,
+            payment_id,
+            customer_principal: None,
+            erased_at: None,
+        }
+    }
+
+    pub fn with_payment_link(mut self, payment_link: String) -> Self {
+        self.payment_link = Some(payment_link);
+        self
+    }
+
+    /// The principal of the customer that made the booking, as they signed in.
+    pub fn with_customer_principal(mut self, customer_principal: Option<String>) -> Self {
+        self.customer_principal = customer_principal;
+        self
+    }
+
+    /// Marks the booking as reserved on the canister against `payment_id`.
+    ///
+    /// The customer details of an erased booking stay erased, even though the canister still
+    /// returns them.
+    pub fn reserved(mut self, transaction: Transaction, payment_id: String) -> Self {
+        if self.erased_at.is_none() {
+            self.transaction = transaction;
+        }
+        self.status = BookingStatus::Reserved;
+        self.payment_id = Some(payment_id);
+        self
+    }
+
+    /// Marks the booking as cancelled before it was paid for, by the customer or because no
+    /// payment link could be created for it.
+    pub fn cancelled(mut self) -> Self {
+        self.status = BookingStatus::Cancelled;
+        self
+    }
+
+    /// Erase the customer's details at the unix timestamp `erased_at`, keeping the car, dates,
+    /// amount and payment references that make up the financial record.
+    pub fn erased(mut self, erased_at: u64) -> Self {
+        let transaction = &self.transaction;
+        self.transaction = Transaction::erased(
+            transaction.booking_id(),
+            transaction.car_id(),
+            transaction.age().clone(),
+            transaction.start_time(),
+            transaction.end_time(),
+        );
+        self.customer_principal = None;
+        self.erased_at = Some(erased_at);
+        self
+    }
+
+    // Getter for booking_id
+    pub fn booking_id(&self) -> u64 {
+        self.transaction.booking_id()
+    }
+
+    // Getter for transaction
+    pub fn transaction(&self) -> &Transaction {
+        &self.transaction
+    }
+
+    // Getter for total_amount
+    pub fn total_amount(&self) -> Option<f64> {
+        self.total_amount
+    }
+
+    // Getter for status
+    pub fn status(&self) -> BookingStatus {
+        self.status
+    }
+
+    // Getter for payment_link
+    pub fn payment_link(&self) -> Option<&str> {
+        self.payment_link.as_deref()
+    }
+
+    // Getter for payment_id
+    pub fn payment_id(&self) -> Option<&str> {
+        self.payment_id.as_deref()
+    }
+
+    // Getter for customer_principal
+    pub fn customer_principal(&self) -> Option<&str> {
+        self.customer_principal.as_deref()
+    }
+
+    // Getter for erased_at
+    pub fn erased_at(&self) -> Option<u64> {
+        self.erased_at
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BookingStatus {
+    AwaitingPayment,
+    Reserved,
+    Cancelled,
+}
+
+impl BookingStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BookingStatus::AwaitingPayment => "awaiting_payment",
+            BookingStatus::Reserved => "reserved",
+            BookingStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl Display for BookingStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Clone, Debug, Error)]
+#[error("{invalid_status} is not a valid booking status")]
+pub struct BookingStatusError {
+    pub invalid_status: String,
+}
+
+impl FromStr for BookingStatus {
+    type Err = BookingStatusError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "awaiting_payment" => Ok(BookingStatus::AwaitingPayment),
+            "reserved" => Ok(BookingStatus::Reserved),
+            "cancelled" => Ok(BookingStatus::Cancelled),
+            _ => Err(BookingStatusError {
+                invalid_status: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// Errors that may occur while cancelling a [Booking].
+#[derive(Debug, Error)]
+pub enum CancelBookingError {
+    #[error("Booking {booking_id} not found")]
+    NotFound { booking_id: u64 },
+
+    #[error("Booking {booking_id} is paid for and reserved, so it can't be cancelled")]
+    AlreadyReserved { booking_id: u64 },
+
+    #[error(transparent)]
+    Storage(#[from] BookingRepositoryError),
+}
+
+/// Errors that may occur while reading or writing [Booking]s.
+#[derive(Debug, Error)]
+pub enum BookingRepositoryError {
+    #[error(transparent)]
+    Unknown(#[from] anyhow::Error), // For any storage backend errors.
+}
@@ -0,0 +1,248 @@
+use std::fmt::{Display, Formatter};
+
+use candid::Principal;
+use serde::Serialize;
+use thiserror::Error;
+
+use super::booking::{Booking, BookingRepositoryError};
+use super::document::{Document, DocumentError, DocumentId};
+use super::outbox::OutboxEvent;
+use super::transaction::EmailAddress;
+use super::verification::VerificationError;
+
+/// The customer whose data is exported or erased under the Digital Personal Data Protection Act,
+/// identified by the email address they booked with or the principal of their Internet Identity.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DataSubject {
+    Email(EmailAddress),
+    Principal(Principal),
+}
+
+impl DataSubject {
+    /// How the subject is identified, for logs that must not contain the identifier itself.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DataSubject::Email(_) => "email",
+            DataSubject::Principal(_) => "principal",
+        }
+    }
+}
+
+impl Display for DataSubject {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataSubject::Email(email) => write!(f, "email {email}"),
+            DataSubject::Principal(principal) => write!(f, "principal {principal}"),
+        }
+    }
+}
+
+/// Which right a data subject request exercises.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataRequestKind {
+    Export,
+    Erasure,
+}
+
+impl DataRequestKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataRequestKind::Export => "export",
+            DataRequestKind::Erasure => "erasure",
+        }
+    }
+}
+
+/// How a data subject request ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataRequestOutcome {
+    Completed,
+    Failed,
+    /// Turned away before it reached the service, e.g. for a wrong admin token.
+    Refused,
+}
+
+impl DataRequestOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataRequestOutcome::Completed => "completed",
+            DataRequestOutcome::Failed => "failed",
+            DataRequestOutcome::Refused => "refused",
+        }
+    }
+}
+
+/// A data subject request as kept in the durable record of requests.
+///
+/// The subject is named by its kind only, so that the record keeps nothing of a customer whose
+/// data was erased; the booking IDs tie the request to the financial records that remain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DataRequestRecord {
+    pub request: DataRequestKind,
+    /// [DataSubject::kind], or `None` if the request was refused before its subject was read.
+    pub subject: Option<&'static str>,
+    pub outcome: DataRequestOutcome,
+    pub reason: Option<String>,
+    pub booking_ids: Vec<u64>,
+    pub requested_at: u64,
+}
+
+/// Everything the service holds about a [DataSubject].
+///
+/// KYC documents are listed but not included; staff download them through the document
+/// endpoint, which audits every download.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CustomerDataExport {
+    pub exported_at: u64,
+    pub bookings: Vec<BookingRecord>,
+    pub documents: Vec<Document>,
+    /// Documents that are stored but could not be read, e.g. because they are corrupt.
+    pub unreadable_documents: Vec<DocumentRef>,
+    /// The booking events, and the emails and webhooks they were delivered to.
+    pub notifications: Vec<OutboxEvent>,
+    pub payments: Vec<PaymentRecord>,
+}
+
+/// A booking with its customer details in full.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BookingRecord {
+    pub booking_id: u64,
+    pub car_id: u64,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub status: &'static str,
+    pub name: String,
+    pub email_address: String,
+    pub age: u8,
+    pub mobile_number: String,
+    pub pan: String,
+    pub aadhar: String,
+    pub customer_principal: Option<String>,
+    pub erased_at: Option<u64>,
+}
+
+impl From<&Booking> for BookingRecord {
+    fn from(booking: &Booking) -> Self {
+        let transaction = booking.transaction();
+        Self {
+            booking_id: booking.booking_id(),
+            car_id: transaction.car_id(),
+            start_time: transaction.start_time(),
+            end_time: transaction.end_time(),
+            status: booking.status().as_str(),
+            name: transaction.name().0.clone(),
+            email_address: transaction.email().0.clone(),
+            age: transaction.age().value(),
+            mobile_number: transaction.phone_number().e164(),
+            pan: transaction.pan().expose().to_string(),
+            aadhar: transaction.aadhar().expose().to_string(),
+            customer_principal: booking.customer_principal().map(str::to_string),
+            erased_at: booking.erased_at(),
+        }
+    }
+}
+
+/// The payment side of a booking, which is kept when the customer's details are erased.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PaymentRecord {
+    pub booking_id: u64,
+    pub amount: Option<f64>,
+    pub payment_link: Option<String>,
+    pub payment_id: Option<String>,
+}
+
+impl From<&Booking> for PaymentRecord {
+    fn from(booking: &Booking) -> Self {
+        Self {
+            booking_id: booking.booking_id(),
+            amount: booking.total_amount(),
+            payment_link: booking.payment_link().map(str::to_string),
+            payment_id: booking.payment_id().map(str::to_string),
+        }
+    }
+}
+
+/// What an erasure removed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ErasureReport {
+    pub erased_at: u64,
+    /// The bookings whose customer details were anonymised. Their amounts, dates and payment
+    /// references are kept, as tax and payment regulations require.
+    pub booking_ids: Vec<u64>,
+    pub documents_deleted: usize,
+    /// Documents that could still not be deleted after retrying, to be deleted by hand. Their
+    /// bookings are erased, so the subject no longer leads to them.
+    pub documents_not_deleted: Vec<DocumentRef>,
+}
+
+/// A stored document, by booking and document ID.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DocumentRef {
+    pub booking_id: u64,
+    pub document_id: DocumentId,
+}
+
+#[derive(Debug, Error)]
+pub enum DataRequestError {
+    #[error(transparent)]
+    Storage(#[from] anyhow::Error),
+}
+
+impl From<BookingRepositoryError> for DataRequestError {
+    fn from(e: BookingRepositoryError) -> Self {
+        match e {
+            BookingRepositoryError::Unknown(cause) => Self::Storage(cause),
+        }
+    }
+}
+
+impl From<DocumentError> for DataRequestError {
+    fn from(e: DocumentError) -> Self {
+        Self::Storage(anyhow::anyhow!("Failed to access documents: {e}"))
+    }
+}
+
+impl From<VerificationError> for DataRequestError {
+    fn from(e: VerificationError) -> Self {
+        Self::Storage(anyhow::anyhow!("Failed to access verification codes: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::transactions::models::booking::BookingStatus;
+    use crate::domain::transactions::models::transaction::Transaction;
+
+    fn booking() -> Booking {
+        let transaction = Transaction::test_fixture(7, 101);
+        Booking::awaiting_payment(transaction, 2500.0)
+            .with_payment_link("https://rzp.io/l/abc".to_string())
+            .with_customer_principal(Some(Principal::anonymous().to_text()))
+    }
+
+    #[test]
+    fn test_erasure_keeps_only_the_financial_record() {
+        let booking = booking().reserved(booking().transaction().clone(), "pay_123".to_string());
+        let erased = booking.clone().erased(1734600000);
+
+        let record = BookingRecord::from(&erased);
+        assert_eq!(record.erased_at, Some(1734600000));
+        assert_eq!(record.customer_principal, None);
+        let personal = [&record.name, &record.email_address, &record.mobile_number, &record.pan, &record.aadhar];
+        for original in ["Test User", "test@example.com", "9876543210", "ABCPU1234F", "234567890124"] {
+            assert!(personal.iter().all(|value| !value.contains(original)), "{original} was kept");
+        }
+        assert_eq!(PaymentRecord::from(&erased), PaymentRecord::from(&booking));
+        assert_eq!((record.car_id, record.start_time, record.end_time), (101, 1734556800, 1734564000));
+    }
+
+    #[test]
+    fn test_reserving_an_erased_booking_keeps_it_erased() {
+        let erased = booking().erased(1734600000);
+        let reserved = erased.clone().reserved(booking().transaction().clone(), "pay_123".to_string());
+
+        assert_eq!(reserved.status(), BookingStatus::Reserved);
+        assert_eq!(reserved.transaction(), erased.transaction());
+    }
+}
@@ -0,0 +1,381 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, OsRng, Payload};
+use aes_gcm::{AeadCore, Aes256Gcm, Key, KeyInit, Nonce};
+use base64::engine::general_purpose;
+use base64::Engine;
+use serde::Serialize;
+use thiserror::Error;
+
+use super::transaction::ErrorCode;
+
+/// The largest document accepted, after decoding the multipart body.
+pub const MAX_DOCUMENT_SIZE: usize = 5 * 1024 * 1024;
+
+/// Length of the AES-GCM nonce prepended to every sealed document.
+const NONCE_LEN: usize = 12;
+
+/// Random bytes in a [DocumentId], so that IDs cannot be guessed.
+const ID_RANDOM_BYTES: usize = 16;
+
+/// What a KYC document proves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentKind {
+    DrivingLicence,
+    Aadhaar,
+    Pan,
+}
+
+impl DocumentKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::DrivingLicence => "driving_licence",
+            Self::Aadhaar => "aadhaar",
+            Self::Pan => "pan",
+        }
+    }
+}
+
+impl FromStr for DocumentKind {
+    type Err = DocumentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "driving_licence" => Ok(Self::DrivingLicence),
+            "aadhaar" => Ok(Self::Aadhaar),
+            "pan" => Ok(Self::Pan),
+            other => Err(DocumentError::InvalidKind { kind: other.to_string() }),
+        }
+    }
+}
+
+/// The file formats accepted for documents, recognised by their contents rather than the
+/// content type the client claims.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum DocumentContentType {
+    #[serde(rename = "image/jpeg")]
+    Jpeg,
+    #[serde(rename = "image/png")]
+    Png,
+    #[serde(rename = "application/pdf")]
+    Pdf,
+}
+
+impl DocumentContentType {
+    /// Recognise the format of `contents` from its leading magic bytes.
+    pub fn sniff(contents: &[u8]) -> Option<Self> {
+        if contents.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(Self::Jpeg)
+        } else if contents.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if contents.starts_with(b"%PDF-") {
+            Some(Self::Pdf)
+        } else {
+            None
+        }
+    }
+
+    pub fn mime(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Pdf => "application/pdf",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::Pdf => "pdf",
+        }
+    }
+}
+
+/// Identifies a document within its booking: its kind and a random suffix, e.g.
+/// `aadhaar-5f0c9e2a7b1d4c3e8a6f0b2d9c7e1a4f`.
+///
+/// IDs only ever contain lowercase letters, digits, `_` and `-`, so adapters may use them as
+/// file names or object keys as they are.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(transparent)]
+pub struct DocumentId(String);
+
+impl DocumentId {
+    /// A new random ID for a document of `kind`.
+    pub fn generate(kind: DocumentKind) -> Self {
+        let mut random = [0u8; ID_RANDOM_BYTES];
+        OsRng.fill_bytes(&mut random);
+        let suffix: String = random
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        Self(format!("{}-{}", kind.as_str(), suffix))
+    }
+
+    pub fn parse(id: &str) -> Result<Self, DocumentError> {
+        let valid = id
+            .split_once('-')
+            .is_some_and(|(kind, suffix)| {
+                kind.parse::<DocumentKind>().is_ok()
+                    && suffix.len() == ID_RANDOM_BYTES * 2
+                    && suffix.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+            });
+        if valid {
+            Ok(Self(id.to_string()))
+        } else {
+            Err(DocumentError::NotFound { document_id: id.to_string() })
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for DocumentId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A KYC document uploaded for a booking.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Document {
+    id: DocumentId,
+    booking_id: u64,
+    kind: DocumentKind,
+    content_type: DocumentContentType,
+    size: usize,
+}
+
+impl Document {
+    /// Validate an upload of `contents`, whose client claimed `declared_content_type`.
+    ///
+    /// # Errors
+    ///
+    /// - [DocumentError::Empty] or [DocumentError::TooLarge] if the size is out of bounds.
+    /// - [DocumentError::UnsupportedType] unless `contents` is a JPEG, PNG or PDF matching
+    ///   `declared_content_type`, if one was given.
+    pub fn new(
+        booking_id: u64,
+        kind: DocumentKind,
+        declared_content_type: Option<&str>,
+        contents: &[u8],
+    ) -> Result<Self, DocumentError> {
+        if contents.is_empty() {
+            return Err(DocumentError::Empty);
+        }
+        if contents.len() > MAX_DOCUMENT_SIZE {
+            return Err(DocumentError::TooLarge { size: contents.len(), max: MAX_DOCUMENT_SIZE });
+        }
+        let unsupported = || DocumentError::UnsupportedType {
+            content_type: declared_content_type.unwrap_or("unknown").to_string(),
+        };
+        let content_type = DocumentContentType::sniff(contents).ok_or_else(unsupported)?;
+        if declared_content_type.is_some_and(|declared| declared != content_type.mime()) {
+            return Err(unsupported());
+        }
+
+        Ok(Self {
+            id: DocumentId::generate(kind),
+            booking_id,
+            kind,
+            content_type,
+            size: contents.len(),
+        })
+    }
+
+    /// Describe a stored document from its decrypted `contents`.
+    pub fn from_stored(booking_id: u64, id: DocumentId, contents: &[u8]) -> Result<Self, DocumentError> {
+        let kind = id.as_str().split_once('-').and_then(|(kind, _)| kind.parse().ok());
+        let content_type = DocumentContentType::sniff(contents);
+        match (kind, content_type) {
+            (Some(kind), Some(content_type)) => Ok(Self {
+                id,
+                booking_id,
+                kind,
+                content_type,
+                size: contents.len(),
+            }),
+            _ => Err(DocumentError::Storage(anyhow::anyhow!("Stored document {id} is corrupt"))),
+        }
+    }
+
+    // Getter for id
+    pub fn id(&self) -> &DocumentId {
+        &self.id
+    }
+
+    // Getter for booking_id
+    pub fn booking_id(&self) -> u64 {
+        self.booking_id
+    }
+
+    // Getter for kind
+    pub fn kind(&self) -> DocumentKind {
+        self.kind
+    }
+
+    // Getter for content_type
+    pub fn content_type(&self) -> DocumentContentType {
+        self.content_type
+    }
+
+    // Getter for size
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// Encrypts documents at rest with the deployment's AES-256-GCM key.
+///
+/// Each document is sealed with a fresh nonce and its booking and document IDs as associated
+/// data, so a sealed document cannot be passed off as another one.
+#[derive(Clone)]
+pub struct DocumentCipher(Aes256Gcm);
+
+impl std::fmt::Debug for DocumentCipher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DocumentCipher(..)")
+    }
+}
+
+#[derive(Clone, Debug, Error)]
+#[error("Document encryption key must be 32 bytes encoded as base64")]
+pub struct DocumentKeyError;
+
+impl DocumentCipher {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self(Aes256Gcm::new(&Key::<Aes256Gcm>::from(key)))
+    }
+
+    /// Parse a base64 encoded 32 byte key, e.g. from `openssl rand -base64 32`.
+    pub fn from_base64(key: &str) -> Result<Self, DocumentKeyError> {
+        let key = general_purpose::STANDARD.decode(key.trim()).map_err(|_| DocumentKeyError)?;
+        Ok(Self::new(key.try_into().map_err(|_| DocumentKeyError)?))
+    }
+
+    /// Encrypt the `contents` of `document`, returning the nonce followed by the ciphertext.
+    pub fn seal(&self, document: &Document, contents: &[u8]) -> Result<Vec<u8>, DocumentError> {
+        let aad = Self::associated_data(document.booking_id, &document.id);
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .0
+            .encrypt(&nonce, Payload { msg: contents, aad: aad.as_bytes() })
+            .map_err(|_| DocumentError::Storage(anyhow::anyhow!("Failed to encrypt document {}", document.id)))?;
+        Ok([&nonce[..], &ciphertext].concat())
+    }
+
+    /// Decrypt a document sealed by [DocumentCipher::seal] for `booking_id` and `id`.
+    pub fn open(&self, booking_id: u64, id: &DocumentId, sealed: &[u8]) -> Result<Vec<u8>, DocumentError> {
+        let error = || DocumentError::Storage(anyhow::anyhow!("Failed to decrypt document {id}"));
+        if sealed.len() < NONCE_LEN {
+            return Err(error());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce: [u8; NONCE_LEN] = nonce.try_into().map_err(|_| error())?;
+        let aad = Self::associated_data(booking_id, id);
+        self.0
+            .decrypt(&Nonce::from(nonce), Payload { msg: ciphertext, aad: aad.as_bytes() })
+            .map_err(|_| error())
+    }
+
+    fn associated_data(booking_id: u64, id: &DocumentId) -> String {
+        format!("bookings/{booking_id}/documents/{id}")
+    }
+}
+
+/// Errors that may occur while uploading or retrieving a [Document].
+#[derive(Debug, Error)]
+pub enum DocumentError {
+    #[error("{kind} is not a document kind, expected driving_licence, aadhaar or pan")]
+    InvalidKind { kind: String },
+
+    #[error("Document type {content_type} is not supported, expected a JPEG, PNG or PDF")]
+    UnsupportedType { content_type: String },
+
+    #[error("Document is empty")]
+    Empty,
+
+    #[error("Document is {size} bytes, the maximum is {max}")]
+    TooLarge { size: usize, max: usize },
+
+    #[error("Booking {booking_id} not found")]
+    BookingNotFound { booking_id: u64 },
+
+    #[error("Document {document_id} not found")]
+    NotFound { document_id: String },
+
+    #[error(transparent)]
+    Storage(#[from] anyhow::Error),
+}
+
+impl ErrorCode for DocumentError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidKind { .. } => "invalid_document_kind",
+            Self::UnsupportedType { .. } => "unsupported_document_type",
+            Self::Empty => "empty_document",
+            Self::TooLarge { .. } => "document_too_large",
+            Self::BookingNotFound { .. } => "booking_not_found",
+            Self::NotFound { .. } => "document_not_found",
+            Self::Storage(_) => "internal_error",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn test_document_type_is_sniffed_from_contents() {
+        let document = Document::new(1, DocumentKind::Aadhaar, Some("image/png"), PNG).unwrap();
+        assert_eq!(document.content_type(), DocumentContentType::Png);
+        assert!(document.id().as_str().starts_with("aadhaar-"));
+        assert_eq!(DocumentId::parse(document.id().as_str()).unwrap(), *document.id());
+
+        let code = |declared, contents| Document::new(1, DocumentKind::Pan, declared, contents).unwrap_err().code();
+        assert_eq!(code(Some("image/jpeg"), PNG), "unsupported_document_type");
+        assert_eq!(code(None, b"MZ\x90\0"), "unsupported_document_type");
+        assert_eq!(code(None, b""), "empty_document");
+        let too_large = [b"%PDF-".as_slice(), &vec![0; MAX_DOCUMENT_SIZE]].concat();
+        assert_eq!(code(Some("application/pdf"), &too_large), "document_too_large");
+    }
+
+    #[test]
+    fn test_document_ids_cannot_escape_the_booking() {
+        assert!(DocumentId::parse("../../etc/passwd").is_err());
+        assert!(DocumentId::parse("aadhaar-../../etc/pass").is_err());
+        assert!(DocumentId::parse("passport-5f0c9e2a7b1d4c3e8a6f0b2d9c7e1a4f").is_err());
+        assert!(DocumentId::parse("pan-5F0C9E2A7B1D4C3E8A6F0B2D9C7E1A4F").is_err());
+        assert!(DocumentId::parse("pan-5f0c9e2a7b1d4c3e8a6f0b2d9c7e1a4f").is_ok());
+    }
+
+    #[test]
+    fn test_sealed_documents_only_open_for_their_own_id() {
+        let cipher = DocumentCipher::new([7; 32]);
+        let document = Document::new(1, DocumentKind::Aadhaar, None, PNG).unwrap();
+        let sealed = cipher.seal(&document, PNG).unwrap();
+        assert!(!sealed.windows(4).any(|window| window == b"IHDR"));
+
+        assert_eq!(cipher.open(1, document.id(), &sealed).unwrap(), PNG);
+        assert!(cipher.open(2, document.id(), &sealed).is_err());
+        let other = Document::new(1, DocumentKind::Aadhaar, None, PNG).unwrap();
+        assert!(cipher.open(1, other.id(), &sealed).is_err());
+        assert!(DocumentCipher::new([8; 32]).open(1, document.id(), &sealed).is_err());
+    }
+
+    #[test]
+    fn test_key_must_be_32_bytes_of_base64() {
+        assert!(DocumentCipher::from_base64(&general_purpose::STANDARD.encode([1; 32])).is_ok());
+        assert!(DocumentCipher::from_base64(&general_purpose::STANDARD.encode([1; 16])).is_err());
+        assert!(DocumentCipher::from_base64("not base64").is_err());
+    }
+}
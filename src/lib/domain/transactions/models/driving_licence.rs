@@ -0,0 +1,172 @@
+use std::fmt::{Display, Formatter};
+
+use regex::Regex;
+use thiserror::Error;
+
+use super::transaction::{ErrorCode, IST};
+use crate::utils::redaction::mask;
+
+/// Codes of the states and union territories that issue driving licences, including `OR` and
+/// `UA`, which Odisha and Uttarakhand used before their renaming in 2011 and 2007 and which
+/// licences issued then still carry.
+const STATE_CODES: &[&str] = &[
+    "AN", "AP", "AR", "AS", "BR", "CG", "CH", "DD", "DL", "DN", "GA", "GJ", "HP", "HR", "JH", "JK",
+    "KA", "KL", "LA", "LD", "MH", "ML", "MN", "MP", "MZ", "NL", "OD", "OR", "PB", "PY", "RJ", "SK",
+    "TN", "TR", "TS", "UA", "UK", "UP", "WB",
+];
+
+/// An Indian driving licence, valid until the end of its expiry date.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DrivingLicence {
+    number: String,
+    issuing_state: String,
+    expiry: time::Date,
+}
+
+#[derive(Clone, Debug, Error)]
+pub enum DrivingLicenceError {
+    #[error("{invalid_licence_number} is not a valid driving licence number")]
+    InvalidNumber { invalid_licence_number: String },
+
+    #[error("{invalid_issuing_state} is not a valid issuing state code")]
+    InvalidIssuingState { invalid_issuing_state: String },
+
+    #[error("Driving licence {number} was not issued in {issuing_state}")]
+    StateMismatch { number: String, issuing_state: String },
+
+    #[error("{invalid_expiry} is not a valid expiry date")]
+    InvalidExpiry { invalid_expiry: String },
+
+    #[error("Driving licence expires on {expiry}, before the booking ends")]
+    ExpiresBeforeEndTime { expiry: String, end_time: u64 },
+}
+
+impl DrivingLicence {
+    /// Validate a licence number such as `MH14 20110062821`: the issuing state, a two digit
+    /// RTO code, the year of issue and a seven digit serial. `issuing_state` is the state code,
+    /// e.g. `MH`, and `expiry` an ISO 8601 date.
+    pub fn new(number: &str, issuing_state: &str, expiry: &str) -> Result<Self, DrivingLicenceError> {
+        let compact: String = number
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-'))
+            .collect::<String>()
+            .to_uppercase();
+        if !Self::validate_number(&compact) {
+            return Err(DrivingLicenceError::InvalidNumber { invalid_licence_number: number.trim().to_string() });
+        }
+
+        let issuing_state = issuing_state.trim().to_uppercase();
+        if !STATE_CODES.contains(&issuing_state.as_str()) {
+            return Err(DrivingLicenceError::InvalidIssuingState { invalid_issuing_state: issuing_state });
+        }
+        if !compact.starts_with(&issuing_state) {
+            return Err(DrivingLicenceError::StateMismatch { number: compact, issuing_state });
+        }
+
+        let expiry = time::Date::parse(expiry.trim(), &time::format_description::well_known::Iso8601::DATE)
+            .map_err(|_| DrivingLicenceError::InvalidExpiry { invalid_expiry: expiry.trim().to_string() })?;
+
+        Ok(Self { number: compact, issuing_state, expiry })
+    }
+
+    fn validate_number(number: &str) -> bool {
+        let re = Regex::new(r"^([A-Z]{2})[0-9]{2}(19|20)[0-9]{2}[0-9]{7}$").unwrap();
+        re.captures(number)
+            .is_some_and(|captures| STATE_CODES.contains(&&captures[1]))
+    }
+
+    /// Reject a licence that expires before the unix timestamp `end_time`, in IST.
+    pub fn check_valid_until(&self, end_time: u64) -> Result<(), DrivingLicenceError> {
+        let error = || DrivingLicenceError::ExpiresBeforeEndTime { expiry: self.expiry_date(), end_time };
+        let end_date = i64::try_from(end_time)
+            .ok()
+            .and_then(|end_time| time::OffsetDateTime::from_unix_timestamp(end_time).ok())
+            .ok_or_else(error)?
+            .to_offset(IST)
+            .date();
+        if end_date > self.expiry {
+            Err(error())
+        } else {
+            Ok(())
+        }
+    }
+
+    // Getter for number
+    pub fn number(&self) -> &str {
+        &self.number
+    }
+
+    // Getter for issuing_state
+    pub fn issuing_state(&self) -> &str {
+        &self.issuing_state
+    }
+
+    /// The expiry date in ISO 8601 form, e.g. `2031-04-22`.
+    pub fn expiry_date(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.expiry.year(), self.expiry.month() as u8, self.expiry.day())
+    }
+}
+
+/// Masked to the issuing state and the last four digits; use [DrivingLicence::number] for the
+/// full number.
+impl Display for DrivingLicence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&mask(&self.number, 2, 4))
+    }
+}
+
+impl std::fmt::Debug for DrivingLicence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DrivingLicence")
+            .field("number", &self.to_string())
+            .field("issuing_state", &self.issuing_state)
+            .field("expiry", &self.expiry)
+            .finish()
+    }
+}
+
+impl ErrorCode for DrivingLicenceError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidNumber { .. } => "invalid_licence_number",
+            Self::InvalidIssuingState { .. } => "invalid_issuing_state",
+            Self::StateMismatch { .. } => "licence_state_mismatch",
+            Self::InvalidExpiry { .. } => "invalid_licence_expiry",
+            Self::ExpiresBeforeEndTime { .. } => "licence_expires_before_end_time",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_licence_number_is_validated_against_the_state() {
+        let licence = DrivingLicence::new("mh14 20110062821", "mh", "2031-04-22").unwrap();
+        assert_eq!(licence.number(), "MH1420110062821");
+        assert_eq!(licence.issuing_state(), "MH");
+        assert_eq!(licence.expiry_date(), "2031-04-22");
+        assert!(DrivingLicence::new("DL-0420150012345", "DL", "2035-01-01").is_ok());
+
+        let code = |number, state, expiry| DrivingLicence::new(number, state, expiry).unwrap_err().code();
+        assert_eq!(code("MH142011006282", "MH", "2031-04-22"), "invalid_licence_number");
+        assert_eq!(code("XX1420110062821", "MH", "2031-04-22"), "invalid_licence_number");
+        assert_eq!(code("MH1418110062821", "MH", "2031-04-22"), "invalid_licence_number");
+        assert_eq!(code("MH1420110062821", "ZZ", "2031-04-22"), "invalid_issuing_state");
+        assert_eq!(code("MH1420110062821", "KA", "2031-04-22"), "licence_state_mismatch");
+        assert_eq!(code("MH1420110062821", "MH", "22/04/2031"), "invalid_licence_expiry");
+    }
+
+    #[test]
+    fn test_licence_must_be_valid_until_the_booking_ends() {
+        let licence = DrivingLicence::new("MH1420110062821", "MH", "2031-04-22").unwrap();
+        // 2031-04-22T18:29:59Z is 23:59:59 on the expiry date in IST
+        assert!(licence.check_valid_until(1934648999).is_ok());
+        // 2031-04-22T18:30:00Z is midnight on the day after
+        assert_eq!(
+            licence.check_valid_until(1934649000).unwrap_err().code(),
+            "licence_expires_before_end_time"
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+/// Something that happened to a booking, recorded in the outbox together with the booking itself.
+///
+/// Events only carry identifiers and payment references; handlers load the [Booking] for
+/// customer details so that no PII is copied into the outbox.
+///
+/// [Booking]: super::booking::Booking
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum BookingEvent {
+    PaymentLinkCreated {
+        booking_id: u64,
+        car_id: u64,
+        payment_link: String,
+        amount: f64,
+    },
+    BookingReserved {
+        booking_id: u64,
+        car_id: u64,
+        payment_id: String,
+    },
+    BookingCancelled {
+        booking_id: u64,
+        car_id: u64,
+    },
+}
+
+impl BookingEvent {
+    pub fn booking_id(&self) -> u64 {
+        match self {
+            BookingEvent::PaymentLinkCreated { booking_id, .. }
+            | BookingEvent::BookingReserved { booking_id, .. }
+            | BookingEvent::BookingCancelled { booking_id, .. } => *booking_id,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BookingEvent::PaymentLinkCreated { .. } => "PaymentLinkCreated",
+            BookingEvent::BookingReserved { .. } => "BookingReserved",
+            BookingEvent::BookingCancelled { .. } => "BookingCancelled",
+        }
+    }
+}
+
+/// Something that happened while handling a request, published on the
+/// [EventBus](crate::domain::transactions::event_bus::EventBus) as soon as it happens.
+///
+/// Unlike [BookingEvent]s these are not persisted: subscribers that miss one, e.g. because the
+/// service stopped, never see it. Use them for metrics and logging, not for customer-facing work.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum TransactionEvent {
+    TransactionCreated {
+        booking_id: u64,
+    },
+    TransactionCreationFailed {
+        booking_id: u64,
+        reason: String,
+    },
+    PaymentLinkCreationFailed {
+        booking_id: u64,
+        reason: String,
+    },
+    DocumentUploaded {
+        booking_id: u64,
+        document_id: String,
+    },
+    /// A staff member downloaded a KYC document.
+    DocumentAccessed {
+        booking_id: u64,
+        document_id: String,
+    },
+    /// A call to the canister `method` failed transiently and was retried `retries` times.
+    CanisterCallRetried {
+        method: String,
+        retries: u32,
+        succeeded: bool,
+    },
+}
+
+impl TransactionEvent {
+    /// The booking the event is about, if any.
+    pub fn booking_id(&self) -> Option<u64> {
+        match self {
+            TransactionEvent::TransactionCreated { booking_id }
+            | TransactionEvent::TransactionCreationFailed { booking_id, .. }
+            | TransactionEvent::PaymentLinkCreationFailed { booking_id, .. }
+            | TransactionEvent::DocumentUploaded { booking_id, .. }
+            | TransactionEvent::DocumentAccessed { booking_id, .. } => Some(*booking_id),
+            TransactionEvent::CanisterCallRetried { .. } => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransactionEvent::TransactionCreated { .. } => "TransactionCreated",
+            TransactionEvent::TransactionCreationFailed { .. } => "TransactionCreationFailed",
+            TransactionEvent::PaymentLinkCreationFailed { .. } => "PaymentLinkCreationFailed",
+            TransactionEvent::DocumentUploaded { .. } => "DocumentUploaded",
+            TransactionEvent::DocumentAccessed { .. } => "DocumentAccessed",
+            TransactionEvent::CanisterCallRetried { .. } => "CanisterCallRetried",
+        }
+    }
+}
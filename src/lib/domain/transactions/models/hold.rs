@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use thiserror::Error;
+
+use super::booking::BookingRepositoryError;
+use super::transaction::EmailAddress;
+
+/// How long a slot stays held for a customer who has been sent a payment link, unless configured
+/// otherwise. Razorpay requires payment links to stay valid for at least 15 minutes.
+pub const DEFAULT_HOLD_DURATION: Duration = Duration::from_secs(20 * 60);
+
+/// A time-limited claim on a car between `start_time` and `end_time`, placed when a payment link
+/// is created so that nobody else can be sent a link for the same slot before it expires.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlotHold {
+    booking_id: u64,
+    car_id: u64,
+    holder: EmailAddress,
+    start_time: u64,
+    end_time: u64,
+    expires_at: u64,
+}
+
+impl SlotHold {
+    pub fn new(
+        booking_id: u64,
+        car_id: u64,
+        holder: EmailAddress,
+        start_time: u64,
+        end_time: u64,
+        expires_at: u64,
+    ) -> Self {
+        Self {
+            booking_id,
+            car_id,
+            holder,
+            start_time,
+            end_time,
+            expires_at,
+        }
+    }
+
+    // Getter for booking_id
+    pub fn booking_id(&self) -> u64 {
+        self.booking_id
+    }
+
+    // Getter for car_id
+    pub fn car_id(&self) -> u64 {
+        self.car_id
+    }
+
+    // Getter for holder
+    pub fn holder(&self) -> &EmailAddress {
+        &self.holder
+    }
+
+    // Getter for start_time
+    pub fn start_time(&self) -> u64 {
+        self.start_time
+    }
+
+    // Getter for end_time
+    pub fn end_time(&self) -> u64 {
+        self.end_time
+    }
+
+    // Getter for expires_at
+    pub fn expires_at(&self) -> u64 {
+        self.expires_at
+    }
+
+    /// Whether `other` holds the same car for an overlapping period on behalf of someone else
+    /// while both holds are live at `now`.
+    ///
+    /// A customer's own holds never conflict, so that they can ask for a new payment link.
+    pub fn conflicts_with(&self, other: &SlotHold, now: u64) -> bool {
+        self.car_id == other.car_id
+            && self.holder != other.holder
+            && self.start_time < other.end_time
+            && other.start_time < self.end_time
+            && self.expires_at > now
+            && other.expires_at > now
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SlotHoldError {
+    #[error("Car {car_id} is held for another customer until {held_until}")]
+    Held { car_id: u64, held_until: u64 },
+
+    #[error(transparent)]
+    Storage(#[from] BookingRepositoryError),
+}
+
+impl From<anyhow::Error> for SlotHoldError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Storage(e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hold(holder: &str, start_time: u64, end_time: u64, expires_at: u64) -> SlotHold {
+        SlotHold::new(1, 101, EmailAddress::new(holder).unwrap(), start_time, end_time, expires_at)
+    }
+
+    #[test]
+    fn test_overlapping_live_holds_of_other_customers_conflict() {
+        let held = hold("first@example.com", 100, 200, 50);
+        assert!(hold("second@example.com", 150, 250, 50).conflicts_with(&held, 10));
+        // Adjacent slots don't overlap
+        assert!(!hold("second@example.com", 200, 300, 50).conflicts_with(&held, 10));
+        // The same customer can hold again
+        assert!(!hold("first@example.com", 150, 250, 50).conflicts_with(&held, 10));
+        // Expired holds are ignored
+        assert!(!hold("second@example.com", 150, 250, 80).conflicts_with(&held, 60));
+    }
+}
@@ -1 +1,14 @@
 pub mod transaction;
+pub mod booking;
+pub mod event;
+pub mod outbox;
+
+pub mod availability;
+pub mod hold;
+pub mod age_policy;
+pub mod phone_number;
+pub mod driving_licence;
+pub mod document;
+pub mod verification;
+pub mod pii;
+pub mod data_request;
\ No newline at end of file
@@ -0,0 +1,183 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use super::booking::BookingRepositoryError;
+use super::event::BookingEvent;
+
+/// A [BookingEvent] waiting in, or delivered from, the transactional outbox.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct OutboxEvent {
+    id: u64,
+    event: BookingEvent,
+    status: OutboxStatus,
+    attempts: u32,
+    next_attempt_at: u64,
+    completed_handlers: Vec<String>,
+    last_error: Option<String>,
+    created_at: u64,
+}
+
+impl OutboxEvent {
+    // Constructor: used by repositories to rebuild a stored event
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        id: u64,
+        event: BookingEvent,
+        status: OutboxStatus,
+        attempts: u32,
+        next_attempt_at: u64,
+        completed_handlers: Vec<String>,
+        last_error: Option<String>,
+        created_at: u64,
+    ) -> Self {
+        Self {
+            id,
+            event,
+            status,
+            attempts,
+            next_attempt_at,
+            completed_handlers,
+            last_error,
+            created_at,
+        }
+    }
+
+    // Getter for id
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    // Getter for event
+    pub fn event(&self) -> &BookingEvent {
+        &self.event
+    }
+
+    // Getter for status
+    pub fn status(&self) -> OutboxStatus {
+        self.status
+    }
+
+    // Getter for attempts
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    // Getter for next_attempt_at
+    pub fn next_attempt_at(&self) -> u64 {
+        self.next_attempt_at
+    }
+
+    // Getter for completed_handlers
+    pub fn completed_handlers(&self) -> &[String] {
+        &self.completed_handlers
+    }
+
+    // Getter for last_error
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    // Getter for created_at
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    pub fn is_completed_by(&self, handler: &str) -> bool {
+        self.completed_handlers.iter().any(|h| h == handler)
+    }
+
+    pub fn complete_handler(&mut self, handler: &str) {
+        if !self.is_completed_by(handler) {
+            self.completed_handlers.push(handler.to_string());
+        }
+    }
+
+    pub fn delivered(mut self) -> Self {
+        self.status = OutboxStatus::Delivered;
+        self.last_error = None;
+        self
+    }
+
+    /// Record a failed delivery attempt, to be retried at `next_attempt_at`.
+    pub fn failed(mut self, error: String, next_attempt_at: u64) -> Self {
+        self.attempts += 1;
+        self.last_error = Some(error);
+        self.next_attempt_at = next_attempt_at;
+        self
+    }
+
+    pub fn dead_lettered(mut self) -> Self {
+        self.status = OutboxStatus::DeadLettered;
+        self
+    }
+
+    /// Put a dead-lettered event back in the queue with a fresh retry budget. Handlers that
+    /// already succeeded are not called again.
+    pub fn replayed(mut self, now: u64) -> Self {
+        self.status = OutboxStatus::Pending;
+        self.attempts = 0;
+        self.next_attempt_at = now;
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboxStatus {
+    Pending,
+    Delivered,
+    DeadLettered,
+}
+
+impl OutboxStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutboxStatus::Pending => "pending",
+            OutboxStatus::Delivered => "delivered",
+            OutboxStatus::DeadLettered => "dead_lettered",
+        }
+    }
+}
+
+impl Display for OutboxStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Clone, Debug, Error)]
+#[error("{invalid_status} is not a valid outbox status")]
+pub struct OutboxStatusError {
+    pub invalid_status: String,
+}
+
+impl FromStr for OutboxStatus {
+    type Err = OutboxStatusError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(OutboxStatus::Pending),
+            "delivered" => Ok(OutboxStatus::Delivered),
+            "dead_lettered" => Ok(OutboxStatus::DeadLettered),
+            _ => Err(OutboxStatusError {
+                invalid_status: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// Errors that may occur while inspecting or replaying the outbox.
+#[derive(Debug, Error)]
+pub enum OutboxError {
+    #[error("Outbox event not found with ID: {id}")]
+    NotFound { id: u64 },
+
+    #[error("Outbox event {id} is not dead-lettered")]
+    NotDeadLettered { id: u64 },
+
+    #[error(transparent)]
+    Storage(#[from] BookingRepositoryError),
+}
@@ -0,0 +1,205 @@
+use std::fmt::{Display, Formatter};
+
+use thiserror::Error;
+
+use super::transaction::ErrorCode;
+use crate::utils::redaction::mask;
+
+/// The longest number E.164 allows, country code included.
+const MAX_E164_DIGITS: usize = 15;
+
+/// The shortest national number accepted for countries without a [CountryRule].
+const MIN_NATIONAL_DIGITS: usize = 4;
+
+/// How mobile numbers look in a country: the digit counts allowed after the country code and
+/// the prefixes they start with.
+struct CountryRule {
+    country_code: u16,
+    lengths: &'static [usize],
+    prefixes: &'static [&'static str],
+}
+
+const COUNTRY_RULES: &[CountryRule] = &[
+    // India
+    CountryRule { country_code: 91, lengths: &[10], prefixes: &["6", "7", "8", "9"] },
+    // United States, Canada and the rest of the North American Numbering Plan
+    CountryRule { country_code: 1, lengths: &[10], prefixes: &["2", "3", "4", "5", "6", "7", "8", "9"] },
+    // United Kingdom
+    CountryRule { country_code: 44, lengths: &[10], prefixes: &["7"] },
+    // Germany
+    CountryRule { country_code: 49, lengths: &[10, 11], prefixes: &["15", "16", "17"] },
+    // France
+    CountryRule { country_code: 33, lengths: &[9], prefixes: &["6", "7"] },
+    // Australia
+    CountryRule { country_code: 61, lengths: &[9], prefixes: &["4"] },
+    // Singapore
+    CountryRule { country_code: 65, lengths: &[8], prefixes: &["8", "9"] },
+    // United Arab Emirates
+    CountryRule { country_code: 971, lengths: &[9], prefixes: &["5"] },
+    // Nepal
+    CountryRule { country_code: 977, lengths: &[10], prefixes: &["9"] },
+    // Bangladesh
+    CountryRule { country_code: 880, lengths: &[10], prefixes: &["1"] },
+    // Sri Lanka
+    CountryRule { country_code: 94, lengths: &[9], prefixes: &["7"] },
+];
+
+/// A mobile number: a country calling code and the national number that follows it.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PhoneNumber {
+    country_code: u16,
+    national_number: String,
+}
+
+#[derive(Clone, Debug, Error)]
+pub enum PhoneNumberError {
+    #[error("{country_code} is not a valid country code")]
+    InvalidCountryCode { country_code: u16 },
+
+    #[error("{invalid_mobile_number} is not a valid mobile number for country code +{country_code}")]
+    InvalidNumber { country_code: u16, invalid_mobile_number: String },
+}
+
+impl PhoneNumber {
+    /// Validate `number` against the rules of `country_code`. `number` is either the national
+    /// number, optionally with a trunk `0` and spaces or hyphens, or the full E.164 form.
+    pub fn new(country_code: u16, number: &str) -> Result<Self, PhoneNumberError> {
+        if country_code == 0 || country_code > 999 {
+            return Err(PhoneNumberError::InvalidCountryCode { country_code });
+        }
+        let invalid = || PhoneNumberError::InvalidNumber {
+            country_code,
+            invalid_mobile_number: number.to_string(),
+        };
+
+        let compact: String = number.chars().filter(|c| !matches!(c, ' ' | '-')).collect();
+        let national_number = match compact.strip_prefix('+') {
+            Some(e164) => e164.strip_prefix(country_code.to_string().as_str()).ok_or_else(invalid)?,
+            None => compact.strip_prefix('0').unwrap_or(&compact),
+        };
+        if national_number.is_empty() || !national_number.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+
+        let valid = match COUNTRY_RULES.iter().find(|rule| rule.country_code == country_code) {
+            Some(rule) => {
+                rule.lengths.contains(&national_number.len())
+                    && rule.prefixes.iter().any(|prefix| national_number.starts_with(prefix))
+            }
+            None => {
+                national_number.len() >= MIN_NATIONAL_DIGITS
+                    && country_code.to_string().len() + national_number.len() <= MAX_E164_DIGITS
+            }
+        };
+        if !valid {
+            return Err(invalid());
+        }
+
+        Ok(Self { country_code, national_number: national_number.to_string() })
+    }
+
+    /// A number read back from the booking store or the canister, either in E.164 form or as
+    /// the national number stored before it. It is not checked again, as it may predate the
+    /// current rules.
+    pub fn from_stored(country_code: u16, stored: &str) -> Self {
+        let prefix = format!("+{country_code}");
+        Self { country_code, national_number: stored.strip_prefix(prefix.as_str()).unwrap_or(stored).to_string() }
+    }
+
+    // Getter for country_code
+    pub fn country_code(&self) -> u16 {
+        self.country_code
+    }
+
+    // Getter for national_number
+    pub fn national_number(&self) -> &str {
+        &self.national_number
+    }
+
+    /// The number in E.164 form, e.g. `+919876543210`, as stored and sent to SMS providers.
+    pub fn e164(&self) -> String {
+        format!("+{}{}", self.country_code, self.national_number)
+    }
+
+    /// Stands in for the number of a customer whose details were erased. It is not a valid
+    /// number, so nothing can be sent to it.
+    pub fn erased() -> Self {
+        Self {
+            country_code: 0,
+            national_number: String::new(),
+        }
+    }
+}
+
+/// Masked to the country code and last four digits, e.g. `+91******3210`; use
+/// [PhoneNumber::e164] for the full number.
+impl Display for PhoneNumber {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "+{}{}", self.country_code, mask(&self.national_number, 0, 4))
+    }
+}
+
+impl std::fmt::Debug for PhoneNumber {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("PhoneNumber").field(&self.to_string()).finish()
+    }
+}
+
+impl ErrorCode for PhoneNumberError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidCountryCode { .. } => "invalid_country_code",
+            Self::InvalidNumber { .. } => "invalid_mobile_number",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbers_are_validated_per_country() {
+        assert_eq!(PhoneNumber::new(91, "9876543210").unwrap().e164(), "+919876543210");
+        assert_eq!(PhoneNumber::new(44, "07911 123456").unwrap().e164(), "+447911123456");
+        assert_eq!(PhoneNumber::new(1, "415-555-2671").unwrap().e164(), "+14155552671");
+        assert_eq!(PhoneNumber::new(65, "91234567").unwrap().e164(), "+6591234567");
+        assert_eq!(PhoneNumber::new(971, "501234567").unwrap().e164(), "+971501234567");
+
+        // Wrong length or prefix for the country
+        assert!(PhoneNumber::new(91, "5876543210").is_err());
+        assert!(PhoneNumber::new(91, "98765").is_err());
+        assert!(PhoneNumber::new(65, "9876543210").is_err());
+        assert!(PhoneNumber::new(44, "2079460000").is_err());
+        assert!(PhoneNumber::new(91, "98765x3210").is_err());
+    }
+
+    #[test]
+    fn test_e164_input_must_match_the_country_code() {
+        let number = PhoneNumber::new(91, "+919876543210").unwrap();
+        assert_eq!(number.country_code(), 91);
+        assert_eq!(number.national_number(), "9876543210");
+        assert_eq!(number.e164(), "+919876543210");
+        assert_eq!(number.to_string(), "+91******3210");
+
+        assert!(PhoneNumber::new(44, "+919876543210").is_err());
+    }
+
+    #[test]
+    fn test_countries_without_rules_fall_back_to_e164_limits() {
+        assert_eq!(PhoneNumber::new(353, "851234567").unwrap().e164(), "+353851234567");
+        assert!(PhoneNumber::new(353, "123").is_err());
+        assert!(PhoneNumber::new(353, "1234567890123").is_err());
+        assert!(matches!(
+            PhoneNumber::new(0, "9876543210"),
+            Err(PhoneNumberError::InvalidCountryCode { .. })
+        ));
+    }
+
+    #[test]
+    fn test_stored_numbers_are_read_back_unchecked() {
+        assert_eq!(PhoneNumber::from_stored(91, "+919876543210").national_number(), "9876543210");
+        // Stored as the national number, and not valid for India today
+        assert_eq!(PhoneNumber::from_stored(91, "5876543210").e164(), "+915876543210");
+    }
+}
@@ -0,0 +1,322 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, OsRng, Payload};
+use aes_gcm::{AeadCore, Aes256Gcm, Key, KeyInit, Nonce};
+use anyhow::anyhow;
+use base64::engine::general_purpose;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use thiserror::Error;
+
+use super::phone_number::PhoneNumber;
+use crate::identity::admin::AdminIdentity;
+use super::transaction::{Aadhar, EmailAddress, PAN};
+
+/// Prefix of every value sealed by [PiiCipher], naming the format.
+const FORMAT: &str = "v1";
+
+/// Length of the AES-GCM nonces in sealed values and wrapped data keys.
+const NONCE_LEN: usize = 12;
+
+/// A customer detail that is encrypted at rest.
+pub trait SensitiveField {
+    /// Names the field in the associated data, so that a sealed value can't be moved to another
+    /// column or booking.
+    const FIELD: &'static str;
+
+    /// The value as stored once decrypted.
+    fn plaintext(&self) -> Cow<'_, str>;
+
+    /// The value as looked up by [PiiCipher::blind_index], e.g. lowercased for email addresses.
+    fn index_term(&self) -> Cow<'_, str> {
+        self.plaintext()
+    }
+}
+
+impl SensitiveField for PAN {
+    const FIELD: &'static str = "pan";
+
+    fn plaintext(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self.expose())
+    }
+}
+
+impl SensitiveField for Aadhar {
+    const FIELD: &'static str = "aadhar";
+
+    fn plaintext(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self.expose())
+    }
+}
+
+impl SensitiveField for EmailAddress {
+    const FIELD: &'static str = "email";
+
+    fn plaintext(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn index_term(&self) -> Cow<'_, str> {
+        Cow::Owned(self.0.to_lowercase())
+    }
+}
+
+/// The admin key belongs to no booking, so it is sealed as booking 0.
+impl SensitiveField for AdminIdentity {
+    const FIELD: &'static str = "admin_key";
+
+    fn plaintext(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self.private_key_pem())
+    }
+}
+
+impl SensitiveField for PhoneNumber {
+    const FIELD: &'static str = "mobile_number";
+
+    fn plaintext(&self) -> Cow<'_, str> {
+        Cow::Owned(self.e164())
+    }
+}
+
+/// Envelope encryption for customer details at rest.
+///
+/// Every value is encrypted with AES-256-GCM under a fresh data key, which is itself wrapped by
+/// the active master key. Sealed values are `v1:<key id>:<wrapped data key>:<ciphertext>`, so
+/// master keys can be rotated: new values use the active key, and values sealed under an older
+/// key stay readable for as long as that key is configured.
+///
+/// Blind indexes are HMAC-SHA256 digests under a separate index key, so that bookings can be
+/// found by email address or mobile number without decrypting them. The index key can't be
+/// rotated without rebuilding every index.
+#[derive(Clone)]
+pub struct PiiCipher(Arc<Keyring>);
+
+struct Keyring {
+    active_key_id: String,
+    keys: HashMap<String, Aes256Gcm>,
+    index_key: [u8; 32],
+}
+
+impl std::fmt::Debug for PiiCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PiiCipher")
+            .field("active_key_id", &self.0.active_key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Clone, Debug, Error)]
+pub enum PiiKeyError {
+    #[error("Key {key_id} must be 32 bytes encoded as base64")]
+    InvalidKey { key_id: String },
+
+    #[error("Key ID {key_id} must be non-empty and must not contain ':'")]
+    InvalidKeyId { key_id: String },
+
+    #[error("The active key {key_id} is not one of the configured keys")]
+    UnknownActiveKey { key_id: String },
+}
+
+impl PiiCipher {
+    /// A cipher sealing new values with `keys[active_key_id]` and opening values sealed with any
+    /// of `keys`.
+    pub fn new(active_key_id: &str, keys: Vec<(String, [u8; 32])>, index_key: [u8; 32]) -> Result<Self, PiiKeyError> {
+        let mut keyring = HashMap::new();
+        for (key_id, key) in keys {
+            if key_id.is_empty() || key_id.contains(':') {
+                return Err(PiiKeyError::InvalidKeyId { key_id });
+            }
+            keyring.insert(key_id, Aes256Gcm::new(&Key::<Aes256Gcm>::from(key)));
+        }
+        if !keyring.contains_key(active_key_id) {
+            return Err(PiiKeyError::UnknownActiveKey { key_id: active_key_id.to_string() });
+        }
+
+        Ok(Self(Arc::new(Keyring {
+            active_key_id: active_key_id.to_string(),
+            keys: keyring,
+            index_key,
+        })))
+    }
+
+    /// Parse master keys given as `id=base64,...` and a base64 index key, each of 32 bytes.
+    pub fn from_base64(active_key_id: &str, keys: &str, index_key: &str) -> Result<Self, PiiKeyError> {
+        let decode = |key_id: &str, key: &str| -> Result<[u8; 32], PiiKeyError> {
+            let invalid = || PiiKeyError::InvalidKey { key_id: key_id.to_string() };
+            general_purpose::STANDARD.decode(key.trim()).map_err(|_| invalid())?.try_into().map_err(|_| invalid())
+        };
+        let keys = keys
+            .split(',')
+            .filter(|pair| !pair.trim().is_empty())
+            .map(|pair| {
+                let (key_id, key) = pair
+                    .split_once('=')
+                    .ok_or_else(|| PiiKeyError::InvalidKeyId { key_id: pair.trim().to_string() })?;
+                Ok((key_id.trim().to_string(), decode(key_id.trim(), key)?))
+            })
+            .collect::<Result<Vec<_>, PiiKeyError>>()?;
+        Self::new(active_key_id.trim(), keys, decode("index", index_key)?)
+    }
+
+    pub fn active_key_id(&self) -> &str {
+        &self.0.active_key_id
+    }
+
+    /// Encrypt `value` of `booking_id` under a new data key wrapped by the active master key.
+    pub fn seal<T: SensitiveField>(&self, booking_id: u64, value: &T) -> anyhow::Result<String> {
+        let error = || anyhow!("Failed to encrypt {} of booking {booking_id}", T::FIELD);
+        let key_id = &self.0.active_key_id;
+        let master_key = &self.0.keys[key_id];
+
+        let mut data_key = [0u8; 32];
+        OsRng.fill_bytes(&mut data_key);
+        let wrapped_key = encrypt(master_key, &data_key, Self::key_aad(key_id).as_bytes()).map_err(|_| error())?;
+
+        let cipher = Aes256Gcm::new(&Key::<Aes256Gcm>::from(data_key));
+        let aad = Self::value_aad(booking_id, T::FIELD);
+        let ciphertext = encrypt(&cipher, value.plaintext().as_bytes(), aad.as_bytes()).map_err(|_| error())?;
+
+        let encode = |bytes: &[u8]| general_purpose::STANDARD_NO_PAD.encode(bytes);
+        Ok(format!("{FORMAT}:{key_id}:{}:{}", encode(&wrapped_key), encode(&ciphertext)))
+    }
+
+    /// Decrypt the `T` of `booking_id` sealed by [PiiCipher::seal].
+    ///
+    /// Values stored before encryption was introduced are returned as they are, until
+    /// re-encrypted.
+    pub fn open<T: SensitiveField>(&self, booking_id: u64, sealed: &str) -> anyhow::Result<String> {
+        let Some(envelope) = sealed.strip_prefix(FORMAT).and_then(|rest| rest.strip_prefix(':')) else {
+            return Ok(sealed.to_string());
+        };
+
+        let error = || anyhow!("Failed to decrypt {} of booking {booking_id}", T::FIELD);
+        let mut parts = envelope.split(':');
+        let (Some(key_id), Some(wrapped_key), Some(ciphertext), None) = (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(error());
+        };
+        let master_key = self
+            .0
+            .keys
+            .get(key_id)
+            .ok_or_else(|| anyhow!("{} of booking {booking_id} was sealed with unknown key {key_id}", T::FIELD))?;
+        let decode = |text: &str| general_purpose::STANDARD_NO_PAD.decode(text).map_err(|_| error());
+
+        let data_key = decrypt(master_key, &decode(wrapped_key)?, Self::key_aad(key_id).as_bytes()).map_err(|_| error())?;
+        let data_key: [u8; 32] = data_key.try_into().map_err(|_| error())?;
+        let cipher = Aes256Gcm::new(&Key::<Aes256Gcm>::from(data_key));
+        let aad = Self::value_aad(booking_id, T::FIELD);
+        let plaintext = decrypt(&cipher, &decode(ciphertext)?, aad.as_bytes()).map_err(|_| error())?;
+        String::from_utf8(plaintext).map_err(|_| error())
+    }
+
+    /// Whether `sealed` is encrypted under the active master key, rather than an older key or
+    /// not at all.
+    pub fn is_current(&self, sealed: &str) -> bool {
+        sealed
+            .strip_prefix(FORMAT)
+            .and_then(|rest| rest.strip_prefix(':'))
+            .and_then(|envelope| envelope.split(':').next())
+            .is_some_and(|key_id| key_id == self.0.active_key_id)
+    }
+
+    /// A deterministic digest of `value` to look it up by, which reveals nothing else about it.
+    pub fn blind_index<T: SensitiveField>(&self, value: &T) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.0.index_key).expect("HMAC accepts keys of any length");
+        mac.update(T::FIELD.as_bytes());
+        mac.update(b":");
+        mac.update(value.index_term().as_bytes());
+        general_purpose::STANDARD_NO_PAD.encode(mac.finalize().into_bytes())
+    }
+
+    fn key_aad(key_id: &str) -> String {
+        format!("pii/keys/{key_id}")
+    }
+
+    fn value_aad(booking_id: u64, field: &str) -> String {
+        format!("bookings/{booking_id}/{field}")
+    }
+}
+
+/// Encrypt `plaintext` under `cipher`, returning the nonce followed by the ciphertext.
+fn encrypt(cipher: &Aes256Gcm, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, aes_gcm::Error> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, Payload { msg: plaintext, aad })?;
+    Ok([&nonce[..], &ciphertext].concat())
+}
+
+fn decrypt(cipher: &Aes256Gcm, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, aes_gcm::Error> {
+    if sealed.len() < NONCE_LEN {
+        return Err(aes_gcm::Error);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce: [u8; NONCE_LEN] = nonce.try_into().map_err(|_| aes_gcm::Error)?;
+    cipher.decrypt(&Nonce::from(nonce), Payload { msg: ciphertext, aad })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher(active_key_id: &str) -> PiiCipher {
+        let keys = vec![("2024".to_string(), [1; 32]), ("2025".to_string(), [2; 32])];
+        PiiCipher::new(active_key_id, keys, [3; 32]).unwrap()
+    }
+
+    #[test]
+    fn test_sealed_values_open_only_for_their_booking_and_field() {
+        let cipher = cipher("2025");
+        let pan = PAN::new("ABCPU1234F").unwrap();
+        let sealed = cipher.seal(7, &pan).unwrap();
+
+        assert!(sealed.starts_with("v1:2025:"));
+        assert!(!sealed.contains("ABCPU1234F"));
+        assert_ne!(sealed, cipher.seal(7, &pan).unwrap(), "every value gets its own data key");
+        assert_eq!(cipher.open::<PAN>(7, &sealed).unwrap(), "ABCPU1234F");
+        assert!(cipher.open::<PAN>(8, &sealed).is_err());
+        assert!(cipher.open::<Aadhar>(7, &sealed).is_err());
+    }
+
+    #[test]
+    fn test_values_sealed_with_an_old_key_open_after_rotation() {
+        let aadhar = Aadhar::new("234567890124").unwrap();
+        let sealed = cipher("2024").seal(1, &aadhar).unwrap();
+
+        let rotated = cipher("2025");
+        assert!(!rotated.is_current(&sealed));
+        assert_eq!(rotated.open::<Aadhar>(1, &sealed).unwrap(), "234567890124");
+        assert!(rotated.is_current(&rotated.seal(1, &aadhar).unwrap()));
+
+        let retired = PiiCipher::new("2025", vec![("2025".to_string(), [2; 32])], [3; 32]).unwrap();
+        assert!(retired.open::<Aadhar>(1, &sealed).is_err());
+        // Stored before encryption was introduced.
+        assert_eq!(rotated.open::<Aadhar>(1, "234567890124").unwrap(), "234567890124");
+    }
+
+    #[test]
+    fn test_blind_index_is_deterministic_per_field() {
+        let current = cipher("2025");
+        let email = EmailAddress::new("Test@Example.com").unwrap();
+        let same = EmailAddress::new("test@example.com").unwrap();
+        let phone = PhoneNumber::new(91, "9876543210").unwrap();
+
+        assert_eq!(current.blind_index(&email), current.blind_index(&same));
+        assert_eq!(cipher("2024").blind_index(&phone), current.blind_index(&phone));
+        assert_ne!(current.blind_index(&email), current.blind_index(&phone));
+        assert!(!current.blind_index(&phone).contains("9876543210"));
+    }
+
+    #[test]
+    fn test_keys_are_parsed_from_base64() {
+        let key = general_purpose::STANDARD.encode([1; 32]);
+        let cipher = PiiCipher::from_base64("a", &format!("a={key}, b={key}"), &key).unwrap();
+        assert_eq!(cipher.active_key_id(), "a");
+
+        assert!(matches!(PiiCipher::from_base64("c", &format!("a={key}"), &key), Err(PiiKeyError::UnknownActiveKey { .. })));
+        assert!(matches!(PiiCipher::from_base64("a", "a=c2hvcnQ=", &key), Err(PiiKeyError::InvalidKey { .. })));
+    }
+}
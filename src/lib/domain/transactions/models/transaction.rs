@@ -1,9 +1,10 @@
 use std::fmt::{Display, Formatter};
 use candid::Principal;
-use derive_more::From;
 use regex::Regex;
 use thiserror::Error;
 
+use crate::utils::redaction::mask;
+
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Transaction {
     booking_id: u64, 
@@ -11,8 +12,7 @@ pub struct Transaction {
     name: UserName,
     email: EmailAddress,
     age: Age, 
-    country_code: u16,
-    mobile_number: MobileNumber,
+    phone_number: PhoneNumber,
     pan: PAN,
     aadhar: Aadhar,
     start_time: u64, 
@@ -28,8 +28,7 @@ impl Transaction {
         name: UserName,
         email: EmailAddress,
         age: Age,
-        country_code: u16,
-        mobile_number: MobileNumber,
+        phone_number: PhoneNumber,
         pan: PAN,
         aadhar: Aadhar,
         start_time: u64,
@@ -41,8 +40,7 @@ impl Transaction {
             name,
             email,
             age,
-            country_code,
-            mobile_number,
+            phone_number,
             pan,
             aadhar,
             start_time,
@@ -50,6 +48,23 @@ impl Transaction {
         }
     }
 
+    /// A transaction whose customer details were erased at the customer's request, keeping only
+    /// what the financial record of the booking needs.
+    pub fn erased(booking_id: u64, car_id: u64, age: Age, start_time: u64, end_time: u64) -> Self {
+        Self {
+            booking_id,
+            car_id,
+            name: UserName(ERASED.to_string()),
+            email: EmailAddress(format!("erased-{booking_id}@erased.invalid")),
+            age,
+            phone_number: PhoneNumber::erased(),
+            pan: PAN(String::new()),
+            aadhar: Aadhar(String::new()),
+            start_time,
+            end_time,
+        }
+    }
+
     // Getter for booking_id
     pub fn booking_id(&self) -> u64 {
         self.booking_id
@@ -75,10 +90,6 @@ impl Transaction {
         &self.age
     }
 
-    // Getter for country_code
-    pub fn country_code(&self) -> u16 {
-        self.country_code
-    }
     // Getter for country_code
     pub fn start_time(&self) -> u64 {
         self.start_time
@@ -88,9 +99,9 @@ impl Transaction {
         self.end_time
     }
 
-    // Getter for mobile_number
-    pub fn mobile_number(&self) -> &MobileNumber {
-        &self.mobile_number
+    // Getter for phone_number
+    pub fn phone_number(&self) -> &PhoneNumber {
+        &self.phone_number
     }
 
     // Getter for PAN
@@ -104,8 +115,30 @@ impl Transaction {
     }
 }
 
+#[cfg(test)]
+impl Transaction {
+    /// A valid transaction of a 25 year old customer, for tests.
+    pub(crate) fn test_fixture(booking_id: u64, car_id: u64) -> Self {
+        Self::new(
+            booking_id,
+            car_id,
+            UserName::new("Test User").unwrap(),
+            EmailAddress::new("test@example.com").unwrap(),
+            Age::new(25).unwrap(),
+            PhoneNumber::new(91, "9876543210").unwrap(),
+            PAN::new("ABCPU1234F").unwrap(),
+            Aadhar::new("234567890124").unwrap(),
+            1734556800,
+            1734564000,
+        )
+    }
+}
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+
+/// Replaces the name of a customer whose details were erased.
+pub const ERASED: &str = "[erased]";
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct UserName(pub String);
 
 #[derive(Clone, Debug, Error)]
@@ -121,6 +154,12 @@ impl UserName {
             Ok(Self(trimmed.to_string()))
         }
     }
+
+    /// A name read back from the booking store or the canister, which was validated when it was
+    /// accepted and is not checked again.
+    pub fn from_stored(stored: &str) -> Self {
+        Self(stored.to_string())
+    }
 }
 
 impl Display for UserName {
@@ -129,7 +168,13 @@ impl Display for UserName {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+impl std::fmt::Debug for UserName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("UserName").field(&mask(&self.0, 1, 0)).finish()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct EmailAddress(pub String);
 
 #[derive(Clone, Debug, Error)]
@@ -145,6 +190,12 @@ impl EmailAddress {
         Ok(Self(trimmed.to_string()))
     }
 
+    /// An address read back from the booking store or the canister, which was validated when it
+    /// was accepted and is not checked again.
+    pub fn from_stored(stored: &str) -> Self {
+        Self(stored.to_string())
+    }
+
     fn validate_email_address(email: &str) -> Result<(), EmailAddressError> {
         let email_regex = Regex::new(r"^[a-zA-Z0-9_.+-]+@[a-zA-Z0-9-]+\.[a-zA-Z0-9-.]+$").unwrap();
         if email_regex.is_match(email) {
@@ -157,9 +208,27 @@ impl EmailAddress {
     }
 }
 
+impl EmailAddress {
+    /// The address with all but the first character of its local part masked, e.g.
+    /// `t***@example.com`.
+    fn masked(&self) -> String {
+        match self.0.split_once('@') {
+            Some((local, domain)) => format!("{}@{domain}", mask(local, 1, 0)),
+            None => mask(&self.0, 0, 0),
+        }
+    }
+}
+
+/// Masked, so that addresses don't end up in logs; use the inner value to send email.
 impl Display for EmailAddress {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.0)
+        f.write_str(&self.masked())
+    }
+}
+
+impl std::fmt::Debug for EmailAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("EmailAddress").field(&self.masked()).finish()
     }
 }
 
@@ -180,6 +249,16 @@ impl Age {
             Ok(Self(age))
         }
     }
+
+    /// An age read back from the booking store or the canister, which was validated when it was
+    /// accepted and is not checked again.
+    pub fn from_stored(age: u8) -> Self {
+        Self(age)
+    }
+
+    pub fn value(&self) -> u8 {
+        self.0
+    }
 }
 
 impl Display for Age {
@@ -188,33 +267,56 @@ impl Display for Age {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct MobileNumber(u64);
+/// The customer's date of birth, from which their [Age] on the day of a booking is computed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateOfBirth(time::Date);
 
 #[derive(Clone, Debug, Error)]
-#[error("{invalid_mobile_number} is not a valid mobile number")]
-pub struct MobileNumberError {
-    pub invalid_mobile_number: String,
+#[error("{invalid_date_of_birth} is not a valid date of birth, expected YYYY-MM-DD in the past")]
+pub struct DateOfBirthError {
+    pub invalid_date_of_birth: String,
 }
 
-impl MobileNumber {
-    pub fn new(mobile_number: u64) -> Result<Self, MobileNumberError> {
-        if mobile_number.to_string().len() != 10 {
-            Err(MobileNumberError { invalid_mobile_number: mobile_number.to_string() })
+/// Bookings are in India, so ages are counted in IST.
+pub(crate) const IST: time::UtcOffset = match time::UtcOffset::from_hms(5, 30, 0) {
+    Ok(offset) => offset,
+    Err(_) => panic!("invalid IST offset"),
+};
+
+impl DateOfBirth {
+    /// Parse an ISO 8601 date such as `1990-04-23`.
+    pub fn new(raw: &str) -> Result<Self, DateOfBirthError> {
+        let trimmed = raw.trim();
+        let error = || DateOfBirthError {
+            invalid_date_of_birth: trimmed.to_string(),
+        };
+        let date = time::Date::parse(trimmed, &time::format_description::well_known::Iso8601::DATE)
+            .map_err(|_| error())?;
+        let today = time::OffsetDateTime::now_utc().to_offset(IST).date();
+        if date > today || date.year() < 1900 {
+            Err(error())
         } else {
-            Ok(Self(mobile_number))
+            Ok(Self(date))
         }
     }
+
+    /// Age in completed years at the unix timestamp `at`, or `None` if `at` is not a valid time.
+    pub fn age_at(&self, at: u64) -> Option<u8> {
+        let date = time::OffsetDateTime::from_unix_timestamp(i64::try_from(at).ok()?).ok()?.to_offset(IST).date();
+        let birthday_passed = (date.month(), date.day()) >= (self.0.month(), self.0.day());
+        let years = date.year() - self.0.year() - i32::from(!birthday_passed);
+        u8::try_from(years).ok()
+    }
 }
 
-impl Display for MobileNumber {
+impl Display for DateOfBirth {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.0.to_string())
+        write!(f, "{:04}-{:02}-{:02}", self.0.year(), self.0.month() as u8, self.0.day())
     }
 }
 
 // PAN field and validation
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct PAN(String);
 
 #[derive(Clone, Debug, Error)]
@@ -235,20 +337,61 @@ impl PAN {
         }
     }
 
+    /// A PAN read back from the booking store or the canister. It is not checked again, as it
+    /// may predate the current rules, e.g. the holder type check.
+    pub fn from_stored(stored: &str) -> Self {
+        Self(stored.to_string())
+    }
+
+    /// The unmasked PAN, for storage and the canister. Never log it.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Five letters, four digits and a letter, where the fourth character is the holder type.
+    /// Only individuals (`P`) can rent a car.
     fn validate_pan(pan: &str) -> bool {
-        let pan_regex = regex::Regex::new(r"^[A-Z]{5}[0-9]{4}[A-Z]{1}$").unwrap(); // PAN format
+        let pan_regex = regex::Regex::new(r"^[A-Z]{3}P[A-Z][0-9]{4}[A-Z]$").unwrap(); // PAN format
         pan_regex.is_match(pan)
     }
+
+    /// Check that the fifth character of an individual's PAN, the initial of their surname,
+    /// matches the last word of `name`.
+    pub fn check_surname(&self, name: &UserName) -> Result<(), PANNameMismatchError> {
+        let surname_initial = name
+            .0
+            .split_whitespace()
+            .last()
+            .and_then(|surname| surname.chars().next())
+            .map(|initial| initial.to_ascii_uppercase());
+        let pan_initial = self.0.chars().nth(4);
+        if surname_initial.is_some() && surname_initial == pan_initial {
+            Ok(())
+        } else {
+            Err(PANNameMismatchError)
+        }
+    }
 }
 
+#[derive(Clone, Debug, Error)]
+#[error("The fifth character of the PAN must be the initial of the holder's surname")]
+pub struct PANNameMismatchError;
+
+/// Masked to the last four characters, e.g. `******234F`; use [PAN::expose] for the full PAN.
 impl Display for PAN {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.0)
+        f.write_str(&mask(&self.0, 0, 4))
+    }
+}
+
+impl std::fmt::Debug for PAN {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("PAN").field(&mask(&self.0, 0, 4)).finish()
     }
 }
 
 // Aadhar field and validation
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Aadhar(String);
 
 #[derive(Clone, Debug, Error)]
@@ -269,32 +412,107 @@ impl Aadhar {
         }
     }
 
+    /// An Aadhaar number read back from the booking store or the canister. It is not checked
+    /// again, as it may predate the current rules, e.g. the Verhoeff checksum.
+    pub fn from_stored(stored: &str) -> Self {
+        Self(stored.to_string())
+    }
+
+    /// The unmasked Aadhaar number, for storage and the canister. Never log it.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Twelve digits, not starting with 0 or 1, ending in a Verhoeff check digit.
     fn validate_aadhar(aadhar: &str) -> bool {
-        let aadhar_regex = regex::Regex::new(r"^\d{12}$").unwrap(); // Aadhar must be a 12-digit number
-        aadhar_regex.is_match(aadhar)
+        let aadhar_regex = regex::Regex::new(r"^[2-9]\d{11}$").unwrap(); // Aadhar must be a 12-digit number
+        aadhar_regex.is_match(aadhar) && verhoeff::is_valid(aadhar)
+    }
+}
+
+/// The Verhoeff checksum used for Aadhaar numbers, which catches every single-digit error and
+/// every transposition of adjacent digits.
+mod verhoeff {
+    const MULTIPLICATION: [[u8; 10]; 10] = [
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+        [1, 2, 3, 4, 0, 6, 7, 8, 9, 5],
+        [2, 3, 4, 0, 1, 7, 8, 9, 5, 6],
+        [3, 4, 0, 1, 2, 8, 9, 5, 6, 7],
+        [4, 0, 1, 2, 3, 9, 5, 6, 7, 8],
+        [5, 9, 8, 7, 6, 0, 4, 3, 2, 1],
+        [6, 5, 9, 8, 7, 1, 0, 4, 3, 2],
+        [7, 6, 5, 9, 8, 2, 1, 0, 4, 3],
+        [8, 7, 6, 5, 9, 3, 2, 1, 0, 4],
+        [9, 8, 7, 6, 5, 4, 3, 2, 1, 0],
+    ];
+
+    const PERMUTATION: [[u8; 10]; 8] = [
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+        [1, 5, 7, 6, 2, 8, 3, 0, 9, 4],
+        [5, 8, 0, 3, 7, 9, 6, 1, 4, 2],
+        [8, 9, 1, 6, 0, 4, 3, 5, 2, 7],
+        [9, 4, 5, 3, 1, 2, 6, 8, 7, 0],
+        [4, 2, 8, 6, 5, 7, 3, 9, 0, 1],
+        [2, 7, 9, 3, 8, 0, 6, 4, 1, 5],
+        [7, 0, 4, 6, 9, 1, 3, 2, 5, 8],
+    ];
+
+    #[cfg(test)]
+    const INVERSE: [u8; 10] = [0, 4, 3, 2, 1, 5, 6, 7, 8, 9];
+
+    /// Run the checksum over `digits`, treating the last one as being at `offset` from the end.
+    fn checksum(digits: &str, offset: usize) -> Option<u8> {
+        digits.chars().rev().enumerate().try_fold(0u8, |check, (i, c)| {
+            let digit = c.to_digit(10)? as usize;
+            Some(MULTIPLICATION[check as usize][PERMUTATION[(i + offset) % 8][digit] as usize])
+        })
+    }
+
+    /// Whether the last digit of `digits` is its Verhoeff check digit.
+    pub(super) fn is_valid(digits: &str) -> bool {
+        checksum(digits, 0) == Some(0)
+    }
+
+    /// The Verhoeff check digit to append to `digits`.
+    #[cfg(test)]
+    pub(super) fn check_digit(digits: &str) -> Option<u8> {
+        checksum(digits, 1).map(|check| INVERSE[check as usize])
     }
 }
 
+/// Masked to the last four digits, as on UIDAI's masked Aadhaar; use [Aadhar::expose] for the
+/// full number.
 impl Display for Aadhar {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.0)
+        f.write_str(&mask(&self.0, 0, 4))
+    }
+}
+
+impl std::fmt::Debug for Aadhar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Aadhar").field(&mask(&self.0, 0, 4)).finish()
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, From,)]
+#[derive(Clone)]
 pub struct CreateTransactionRequest {
     name: UserName,
     email: EmailAddress,
-    age: Age,
+    age: Age,               // Age on the start date
+    date_of_birth: DateOfBirth,
     pan: PAN,
     aadhar: Aadhar,
-    mobile_number: MobileNumber,
-    country_code: u16,
+    phone_number: PhoneNumber,
+    driving_licence: DrivingLicence,
     // principal: Principal,   // Principal for ICP
     car_id: u64,            // Car ID for the transaction
     start_time: StartTime,   // Start time (validated)
     end_time: EndTime,
-    principal_jwk: String 
+    /// The customer's delegation to a session key, which the server calls the canister with.
+    delegated_identity: DelegatedIdentityWire,
+    /// The principal `delegated_identity` was verified to act for.
+    caller: Principal,
+    verification_tokens: Vec<VerificationToken>,
 }
 
 impl CreateTransactionRequest {
@@ -303,32 +521,43 @@ impl CreateTransactionRequest {
         name: UserName,
         email: EmailAddress,
         age: Age,
+        date_of_birth: DateOfBirth,
         pan: PAN,
         aadhar: Aadhar,
-        mobile_number: MobileNumber,
-        country_code: u16,
+        phone_number: PhoneNumber,
+        driving_licence: DrivingLicence,
         // principal: Principal,
         car_id: u64,
         start_time: StartTime,
         end_time: EndTime,
-        principal_jwk: String,
+        delegated_identity: DelegatedIdentityWire,
+        caller: Principal,
     ) -> Self {
         Self {
             name,
             email,
             age,
+            date_of_birth,
             pan,
             aadhar,
-            mobile_number,
-            country_code,
+            phone_number,
+            driving_licence,
             // principal,
             car_id,
             start_time,
             end_time,
-            principal_jwk,
+            delegated_identity,
+            caller,
+            verification_tokens: Vec::new(),
         }
     }
 
+    /// The tokens proving that the customer confirmed their email address and mobile number.
+    pub fn with_verification_tokens(mut self, verification_tokens: Vec<VerificationToken>) -> Self {
+        self.verification_tokens = verification_tokens;
+        self
+    }
+
     // Getter for name
     pub fn name(&self) -> &UserName {
         &self.name
@@ -344,6 +573,11 @@ impl CreateTransactionRequest {
         &self.age
     }
 
+    // Getter for date_of_birth
+    pub fn date_of_birth(&self) -> &DateOfBirth {
+        &self.date_of_birth
+    }
+
     // Getter for PAN
     pub fn pan(&self) -> &PAN {
         &self.pan
@@ -354,14 +588,14 @@ impl CreateTransactionRequest {
         &self.aadhar
     }
 
-    // Getter for mobile_number
-    pub fn mobile_number(&self) -> &MobileNumber {
-        &self.mobile_number
+    // Getter for phone_number
+    pub fn phone_number(&self) -> &PhoneNumber {
+        &self.phone_number
     }
 
-    // Getter for country_code
-    pub fn country_code(&self) -> u16 {
-        self.country_code
+    // Getter for driving_licence
+    pub fn driving_licence(&self) -> &DrivingLicence {
+        &self.driving_licence
     }
 
     // Getter for Principal
@@ -384,20 +618,79 @@ impl CreateTransactionRequest {
         self.end_time.0
     }
 
-    pub fn secret(&self) -> k256::SecretKey {
-        println!("{:?}", &self.principal_jwk);
-        k256::SecretKey::from_jwk_str(&self.principal_jwk).unwrap()
+    // Getter for verification_tokens
+    pub fn verification_tokens(&self) -> &[VerificationToken] {
+        &self.verification_tokens
     }
 
-    pub fn customer(&self, caller: Principal) -> Customer {
-        Customer { age: self.age.clone().0, pan: self.pan.clone().0, mobile_number: self.mobile_number.0.to_string(), name: self.name.0.clone(), email: self.email.0.clone(), country_code: self.country_code.to_string(), aadhar: self.aadhar.0.to_string(), caller: caller }
+    // Getter for delegated_identity
+    pub fn delegated_identity(&self) -> &DelegatedIdentityWire {
+        &self.delegated_identity
+    }
+
+    // Getter for caller
+    pub fn caller(&self) -> Principal {
+        self.caller
+    }
+
+    /// The [Transaction] this request describes, once the canister has assigned it `booking_id`.
+    pub fn transaction(&self, booking_id: u64) -> Transaction {
+        Transaction::new(
+            booking_id,
+            self.car_id,
+            self.name.clone(),
+            self.email.clone(),
+            self.age.clone(),
+            self.phone_number.clone(),
+            self.pan.clone(),
+            self.aadhar.clone(),
+            self.start_time.0,
+            self.end_time.0,
+        )
+    }
+
+    pub fn customer(&self) -> Customer {
+        let driving_licence = CustomerDrivingLicence {
+            number: self.driving_licence.number().to_string(),
+            issuing_state: self.driving_licence.issuing_state().to_string(),
+            expiry: self.driving_licence.expiry_date(),
+        };
+        Customer { age: self.age.clone().0, pan: self.pan.clone().0, mobile_number: self.phone_number.e164(), name: self.name.0.clone(), email: self.email.0.clone(), country_code: self.phone_number.country_code().to_string(), aadhar: self.aadhar.0.to_string(), caller: self.caller, driving_licence: Some(driving_licence) }
     }
 
 }
 
+/// Leaves out the customer's private key and verification tokens.
+impl std::fmt::Debug for CreateTransactionRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CreateTransactionRequest")
+            .field("name", &self.name)
+            .field("email", &self.email)
+            .field("age", &self.age)
+            .field("date_of_birth", &self.date_of_birth)
+            .field("pan", &self.pan)
+            .field("aadhar", &self.aadhar)
+            .field("phone_number", &self.phone_number)
+            .field("driving_licence", &self.driving_licence)
+            .field("car_id", &self.car_id)
+            .field("start_time", &self.start_time)
+            .field("end_time", &self.end_time)
+            .field("caller", &self.caller)
+            .finish_non_exhaustive()
+    }
+}
+
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::canister::backend::Customer;
+use crate::canister::backend::{Customer, DrivingLicence as CustomerDrivingLicence};
+use crate::identity::delegated_identity::DelegatedIdentityWire;
+
+use super::booking::BookingRepositoryError;
+use super::age_policy::AgeRestrictionError;
+use super::hold::SlotHoldError;
+use super::driving_licence::{DrivingLicence, DrivingLicenceError};
+use super::phone_number::{PhoneNumber, PhoneNumberError};
+use super::verification::{VerificationError, VerificationToken};
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct StartTime(u64);
@@ -470,7 +763,10 @@ pub enum CreateTransactionError {
     InvalidEmail(#[from] EmailAddressError), // For invalid email address
 
     #[error(transparent)]
-    InvalidMobile(#[from] MobileNumberError), // For invalid mobile
+    InvalidMobile(#[from] PhoneNumberError), // For invalid mobile
+
+    #[error(transparent)]
+    InvalidDrivingLicence(#[from] DrivingLicenceError),
 
     #[error("Start time must be greater than the current time")]
     StartTimeError, // When the start time is not valid
@@ -488,9 +784,210 @@ pub enum CreateTransactionError {
     CanisterCommunicationError(String), // Errors related to network or canister communication.
 
     #[error("Canister Response: {0}")]
-    CanisterRejectedError(String), // For any other unknown errors.
+    CanisterRejectedError(String), // The canister's reason for refusing, which its interface gives as free text.
+
+    #[error(transparent)]
+    Storage(#[from] BookingRepositoryError), // Errors from the offchain booking store.
+
+    #[error(transparent)]
+    AgeRestricted(#[from] AgeRestrictionError), // The driver's age is outside the car category's limits.
+
+    #[error("Slot held: car {car_id} is reserved for another customer's payment until {held_until}")]
+    SlotHeld { car_id: u64, held_until: u64 }, // Another customer holds an overlapping slot.
+
+    #[error(transparent)]
+    Unverified(#[from] VerificationError), // The email address or mobile number was not verified.
 
 
     #[error(transparent)]
     Unknown(#[from] anyhow::Error), // For any other unknown errors.
 }
+
+impl From<SlotHoldError> for CreateTransactionError {
+    fn from(e: SlotHoldError) -> Self {
+        match e {
+            SlotHoldError::Held { car_id, held_until } => Self::SlotHeld { car_id, held_until },
+            SlotHoldError::Storage(err) => Self::Storage(err),
+        }
+    }
+}
+
+/// A stable, machine-readable code identifying an error, e.g. `invalid_pan`.
+///
+/// Codes are part of the public API contract: clients match on them, so an existing code must
+/// never be changed or reused for a different error. Human-readable messages may change freely.
+pub trait ErrorCode {
+    fn code(&self) -> &'static str;
+}
+
+impl ErrorCode for UserNameEmptyError {
+    fn code(&self) -> &'static str {
+        "name_empty"
+    }
+}
+
+impl ErrorCode for EmailAddressError {
+    fn code(&self) -> &'static str {
+        "invalid_email"
+    }
+}
+
+impl ErrorCode for AgeError {
+    fn code(&self) -> &'static str {
+        "invalid_age"
+    }
+}
+
+impl ErrorCode for DateOfBirthError {
+    fn code(&self) -> &'static str {
+        "invalid_date_of_birth"
+    }
+}
+
+impl ErrorCode for PANError {
+    fn code(&self) -> &'static str {
+        "invalid_pan"
+    }
+}
+
+impl ErrorCode for PANNameMismatchError {
+    fn code(&self) -> &'static str {
+        "pan_name_mismatch"
+    }
+}
+
+impl ErrorCode for AadharError {
+    fn code(&self) -> &'static str {
+        "invalid_aadhar"
+    }
+}
+
+impl ErrorCode for StartTimeError {
+    fn code(&self) -> &'static str {
+        "start_time_not_in_future"
+    }
+}
+
+impl ErrorCode for EndTimeError {
+    fn code(&self) -> &'static str {
+        "end_time_not_after_start"
+    }
+}
+
+impl ErrorCode for CreateTransactionError {
+    fn code(&self) -> &'static str {
+        match self {
+            CreateTransactionError::InvalidAge(e) => e.code(),
+            CreateTransactionError::InvalidPAN(e) => e.code(),
+            CreateTransactionError::InvalidAadhar(e) => e.code(),
+            CreateTransactionError::UserNameEmpty(e) => e.code(),
+            CreateTransactionError::InvalidEmail(e) => e.code(),
+            CreateTransactionError::InvalidMobile(e) => e.code(),
+            CreateTransactionError::InvalidDrivingLicence(e) => e.code(),
+            CreateTransactionError::StartTimeError => "start_time_not_in_future",
+            CreateTransactionError::EndTimeError => "end_time_not_after_start",
+            CreateTransactionError::TransactionExists { .. } => "transaction_exists",
+            CreateTransactionError::InsufficientFunds => "insufficient_funds",
+            CreateTransactionError::CanisterCommunicationError(_) => "canister_unavailable",
+            CreateTransactionError::CanisterRejectedError(_) => "canister_rejected",
+            CreateTransactionError::Storage(_) => "storage_error",
+            CreateTransactionError::SlotHeld { .. } => "slot_held",
+            CreateTransactionError::AgeRestricted(e) => e.code(),
+            CreateTransactionError::Unverified(e) => e.code(),
+            CreateTransactionError::Unknown(_) => "unknown",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// Eleven digits that may start an Aadhaar number.
+    fn aadhar_prefix() -> impl Strategy<Value = String> {
+        "[2-9][0-9]{10}"
+    }
+
+    fn with_check_digit(prefix: &str) -> String {
+        format!("{prefix}{}", verhoeff::check_digit(prefix).unwrap())
+    }
+
+    #[test]
+    fn test_stored_values_predating_the_rules_are_read_back() {
+        // A company PAN and an Aadhaar number with a wrong check digit
+        assert_eq!(PAN::from_stored("ABCCU1234F").expose(), "ABCCU1234F");
+        assert_eq!(Aadhar::from_stored("234567890125").expose(), "234567890125");
+    }
+
+    #[test]
+    fn test_known_aadhar_numbers() {
+        assert!(Aadhar::new("234567890124").is_ok());
+        assert!(Aadhar::new("234567890125").is_err());
+        assert!(Aadhar::new("123456789012").is_err());
+    }
+
+    #[test]
+    fn test_age_is_counted_in_completed_years_in_ist() {
+        let date_of_birth = DateOfBirth::new("2000-06-15").unwrap();
+        // 2018-06-14T18:29:59Z is 23:59:59 on 14 June in IST
+        assert_eq!(date_of_birth.age_at(1529000999), Some(17));
+        // 2018-06-14T18:30:00Z is midnight on 15 June in IST
+        assert_eq!(date_of_birth.age_at(1529001000), Some(18));
+        assert!(DateOfBirth::new("2999-01-01").is_err());
+        assert!(DateOfBirth::new("15/06/2000").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_aadhar_with_check_digit_is_valid(prefix in aadhar_prefix()) {
+            prop_assert!(Aadhar::new(&with_check_digit(&prefix)).is_ok());
+        }
+
+        #[test]
+        fn prop_aadhar_starting_with_0_or_1_is_invalid(first in 0u8..2, rest in "[0-9]{10}") {
+            let prefix = format!("{first}{rest}");
+            prop_assert!(Aadhar::new(&with_check_digit(&prefix)).is_err());
+        }
+
+        #[test]
+        fn prop_aadhar_single_digit_error_is_detected(prefix in aadhar_prefix(), position in 1usize..12, delta in 1u32..10) {
+            let mut digits: Vec<u32> = with_check_digit(&prefix).chars().map(|c| c.to_digit(10).unwrap()).collect();
+            digits[position] = (digits[position] + delta) % 10;
+            let aadhar: String = digits.iter().map(|d| char::from_digit(*d, 10).unwrap()).collect();
+            prop_assert!(Aadhar::new(&aadhar).is_err());
+        }
+
+        #[test]
+        fn prop_aadhar_adjacent_transposition_is_detected(prefix in aadhar_prefix(), position in 1usize..11) {
+            let mut digits: Vec<char> = with_check_digit(&prefix).chars().collect();
+            prop_assume!(digits[position] != digits[position + 1]);
+            digits.swap(position, position + 1);
+            let aadhar: String = digits.into_iter().collect();
+            prop_assert!(Aadhar::new(&aadhar).is_err());
+        }
+
+        #[test]
+        fn prop_individual_pan_is_valid(pan in "[A-Z]{3}P[A-Z][0-9]{4}[A-Z]") {
+            prop_assert!(PAN::new(&pan).is_ok());
+        }
+
+        #[test]
+        fn prop_non_individual_pan_is_invalid(pan in "[A-Z]{3}[A-OQ-Z][A-Z][0-9]{4}[A-Z]") {
+            prop_assert!(PAN::new(&pan).is_err());
+        }
+
+        #[test]
+        fn prop_pan_must_match_surname_initial(
+            given in "[A-Z][a-z]{1,10}",
+            surname in "[A-Za-z][a-z]{1,10}",
+            initial in "[A-Z]",
+        ) {
+            let name = UserName::new(&format!("{given} {surname}")).unwrap();
+            let pan = PAN::new(&format!("ABCP{initial}1234F")).unwrap();
+            let matches = surname.to_ascii_uppercase().starts_with(initial.as_str());
+            prop_assert_eq!(pan.check_surname(&name).is_ok(), matches);
+        }
+    }
+}
@@ -0,0 +1,459 @@
+use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
+use std::time::Duration;
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use thiserror::Error;
+
+use super::booking::BookingRepositoryError;
+use super::phone_number::PhoneNumber;
+use super::transaction::{EmailAddress, ErrorCode};
+use crate::utils::constant_time_eq;
+
+/// How long a one-time password can be confirmed after it was sent.
+pub const OTP_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Wrong codes allowed per one-time password before it is locked.
+pub const MAX_OTP_ATTEMPTS: u32 = 5;
+
+/// How long a verification token is accepted by `create_payment_link`.
+pub const VERIFICATION_TOKEN_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// An email address or mobile number a one-time password is sent to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Contact {
+    Email(EmailAddress),
+    Phone(PhoneNumber),
+}
+
+impl Contact {
+    /// Identifies the contact in storage and in verification tokens, e.g. `phone:+919876543210`.
+    /// Email addresses are compared case-insensitively.
+    pub fn key(&self) -> String {
+        match self {
+            Contact::Email(email) => format!("email:{}", email.0.to_lowercase()),
+            Contact::Phone(phone) => format!("phone:{}", phone.e164()),
+        }
+    }
+
+    pub fn channel(&self) -> &'static str {
+        match self {
+            Contact::Email(_) => "email",
+            Contact::Phone(_) => "sms",
+        }
+    }
+}
+
+impl Display for Contact {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Contact::Email(email) => write!(f, "{email}"),
+            Contact::Phone(phone) => write!(f, "{phone}"),
+        }
+    }
+}
+
+/// How often one-time passwords may be sent to the same contact.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OtpRateLimit {
+    /// The shortest time between two codes.
+    pub min_interval: Duration,
+    /// The most codes sent within `window`.
+    pub max_sends: u32,
+    pub window: Duration,
+}
+
+impl Default for OtpRateLimit {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::from_secs(60),
+            max_sends: 5,
+            window: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl OtpRateLimit {
+    /// The default limit on codes sent at the request of one client, to any contacts: enough for
+    /// a few customers behind one address, too few to make pumping SMS to many numbers pay.
+    pub const PER_CLIENT: OtpRateLimit = OtpRateLimit {
+        min_interval: Duration::ZERO,
+        max_sends: 20,
+        window: Duration::from_secs(60 * 60),
+    };
+}
+
+/// Identifies the client at `ip` in storage, whose sends are counted in a challenge of its own
+/// that carries no code, e.g. `client:203.0.113.7`.
+pub fn client_key(ip: IpAddr) -> String {
+    format!("client:{ip}")
+}
+
+/// The one-time password most recently sent to a contact, and how many were sent before it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OtpChallenge {
+    contact: String,
+    code_hash: String,
+    expires_at: u64,
+    attempts: u32,
+    sends_in_window: u32,
+    window_started_at: u64,
+    last_sent_at: u64,
+}
+
+impl OtpChallenge {
+    pub fn new(
+        contact: String,
+        code_hash: String,
+        expires_at: u64,
+        attempts: u32,
+        sends_in_window: u32,
+        window_started_at: u64,
+        last_sent_at: u64,
+    ) -> Self {
+        Self {
+            contact,
+            code_hash,
+            expires_at,
+            attempts,
+            sends_in_window,
+            window_started_at,
+            last_sent_at,
+        }
+    }
+
+    /// A challenge for a new code replacing `previous`, if `limits` allow sending one at `now`.
+    ///
+    /// # Errors
+    ///
+    /// - [VerificationError::RateLimited] with the time until a code may be sent again.
+    pub fn issue(
+        previous: Option<&OtpChallenge>,
+        contact: String,
+        code_hash: String,
+        now: u64,
+        limits: &OtpRateLimit,
+    ) -> Result<Self, VerificationError> {
+        let (sends_in_window, window_started_at) = match previous {
+            Some(previous) if now < previous.window_started_at + limits.window.as_secs() => {
+                let next_allowed = previous.last_sent_at + limits.min_interval.as_secs();
+                if now < next_allowed {
+                    return Err(VerificationError::RateLimited { retry_after: next_allowed - now });
+                }
+                if previous.sends_in_window >= limits.max_sends {
+                    let window_ends = previous.window_started_at + limits.window.as_secs();
+                    return Err(VerificationError::RateLimited { retry_after: window_ends - now });
+                }
+                (previous.sends_in_window + 1, previous.window_started_at)
+            }
+            _ => (1, now),
+        };
+
+        Ok(Self {
+            contact,
+            code_hash,
+            expires_at: now + OTP_TTL.as_secs(),
+            attempts: 0,
+            sends_in_window,
+            window_started_at,
+            last_sent_at: now,
+        })
+    }
+
+    /// The same challenge with one more attempt at its code.
+    pub fn attempted(self) -> Self {
+        Self {
+            attempts: self.attempts + 1,
+            ..self
+        }
+    }
+
+    /// The same challenge once its code was confirmed, so that it can't be used again. The send
+    /// counters are kept for rate limiting.
+    pub fn consumed(self) -> Self {
+        Self {
+            code_hash: String::new(),
+            expires_at: 0,
+            ..self
+        }
+    }
+
+    // Getter for contact
+    pub fn contact(&self) -> &str {
+        &self.contact
+    }
+
+    // Getter for code_hash
+    pub fn code_hash(&self) -> &str {
+        &self.code_hash
+    }
+
+    // Getter for expires_at
+    pub fn expires_at(&self) -> u64 {
+        self.expires_at
+    }
+
+    // Getter for attempts
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    // Getter for sends_in_window
+    pub fn sends_in_window(&self) -> u32 {
+        self.sends_in_window
+    }
+
+    // Getter for window_started_at
+    pub fn window_started_at(&self) -> u64 {
+        self.window_started_at
+    }
+
+    // Getter for last_sent_at
+    pub fn last_sent_at(&self) -> u64 {
+        self.last_sent_at
+    }
+}
+
+/// Proof that a contact confirmed a one-time password, presented to `create_payment_link`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VerificationToken(String);
+
+impl VerificationToken {
+    pub fn new(token: &str) -> Self {
+        Self(token.trim().to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Hashes one-time passwords and signs verification tokens with the deployment's secret.
+///
+/// Tokens are `<expires_at>.<signature>`, where the signature covers the contact's
+/// [Contact::key] and the expiry, so every instance sharing the secret accepts them without a
+/// lookup.
+#[derive(Clone)]
+pub struct Verifier {
+    secret: Vec<u8>,
+    limits: OtpRateLimit,
+    client_limits: OtpRateLimit,
+}
+
+impl std::fmt::Debug for Verifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Verifier")
+            .field("limits", &self.limits)
+            .field("client_limits", &self.client_limits)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Verifier {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            secret: secret.to_vec(),
+            limits: OtpRateLimit::default(),
+            client_limits: OtpRateLimit::PER_CLIENT,
+        }
+    }
+
+    /// A verifier with a random secret, whose tokens are only accepted until the process exits.
+    pub fn random() -> Self {
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        Self::new(&secret)
+    }
+
+    pub fn with_limits(mut self, limits: OtpRateLimit) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_client_limits(mut self, client_limits: OtpRateLimit) -> Self {
+        self.client_limits = client_limits;
+        self
+    }
+
+    // Getter for limits
+    pub fn limits(&self) -> &OtpRateLimit {
+        &self.limits
+    }
+
+    // Getter for client_limits
+    pub fn client_limits(&self) -> &OtpRateLimit {
+        &self.client_limits
+    }
+
+    /// A random six digit code.
+    pub fn generate_code(&self) -> String {
+        // Reject the top of the range so that every code is equally likely.
+        const LIMIT: u32 = u32::MAX - u32::MAX % 1_000_000;
+        loop {
+            let n = OsRng.next_u32();
+            if n < LIMIT {
+                return format!("{:06}", n % 1_000_000);
+            }
+        }
+    }
+
+    pub fn hash_code(&self, contact: &Contact, code: &str) -> String {
+        self.sign(&format!("otp|{}|{}", contact.key(), code.trim()))
+    }
+
+    /// Check `code` against `challenge`, whose attempt counter already includes this attempt.
+    pub fn check_code(&self, challenge: &OtpChallenge, contact: &Contact, code: &str, now: u64) -> Result<(), VerificationError> {
+        if challenge.code_hash.is_empty() || now >= challenge.expires_at {
+            return Err(VerificationError::Expired);
+        }
+        if challenge.attempts > MAX_OTP_ATTEMPTS {
+            return Err(VerificationError::TooManyAttempts);
+        }
+        if constant_time_eq(self.hash_code(contact, code).as_bytes(), challenge.code_hash.as_bytes()) {
+            Ok(())
+        } else {
+            Err(VerificationError::InvalidCode)
+        }
+    }
+
+    pub fn issue_token(&self, contact: &Contact, now: u64) -> (VerificationToken, u64) {
+        let expires_at = now + VERIFICATION_TOKEN_TTL.as_secs();
+        let signature = self.sign(&format!("token|{}|{}", contact.key(), expires_at));
+        (VerificationToken(format!("{expires_at}.{signature}")), expires_at)
+    }
+
+    /// Whether any of `tokens` proves that `contact` was verified and has not expired at `now`.
+    pub fn is_verified(&self, tokens: &[VerificationToken], contact: &Contact, now: u64) -> bool {
+        tokens.iter().any(|token| {
+            token.0.split_once('.').is_some_and(|(expires_at, signature)| {
+                let expected = self.sign(&format!("token|{}|{}", contact.key(), expires_at));
+                expires_at.parse::<u64>().is_ok_and(|expires_at| now < expires_at)
+                    && constant_time_eq(signature.as_bytes(), expected.as_bytes())
+            })
+        })
+    }
+
+    fn sign(&self, message: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(message.as_bytes());
+        mac.finalize().into_bytes().iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+}
+
+/// Errors that may occur while sending or confirming a one-time password.
+#[derive(Debug, Error)]
+pub enum VerificationError {
+    #[error("Too many codes requested, try again in {retry_after} seconds")]
+    RateLimited { retry_after: u64 },
+
+    #[error("The code is wrong")]
+    InvalidCode,
+
+    #[error("The code has expired, request a new one")]
+    Expired,
+
+    #[error("Too many wrong codes, request a new one")]
+    TooManyAttempts,
+
+    #[error("{contact} has not been verified")]
+    NotVerified { contact: Contact },
+
+    #[error("Failed to send the code: {0}")]
+    Send(String),
+
+    #[error(transparent)]
+    Storage(#[from] anyhow::Error),
+}
+
+impl From<BookingRepositoryError> for VerificationError {
+    fn from(e: BookingRepositoryError) -> Self {
+        match e {
+            BookingRepositoryError::Unknown(cause) => Self::Storage(cause),
+        }
+    }
+}
+
+impl ErrorCode for VerificationError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::RateLimited { .. } => "otp_rate_limited",
+            Self::InvalidCode => "invalid_otp",
+            Self::Expired => "otp_expired",
+            Self::TooManyAttempts => "otp_attempts_exceeded",
+            Self::NotVerified { contact: Contact::Email(_) } => "email_not_verified",
+            Self::NotVerified { contact: Contact::Phone(_) } => "mobile_number_not_verified",
+            Self::Send(_) => "otp_not_sent",
+            Self::Storage(_) => "internal_error",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email() -> Contact {
+        Contact::Email(EmailAddress::new("Test@Example.com").unwrap())
+    }
+
+    #[test]
+    fn test_sends_are_rate_limited_per_window() {
+        let limits = OtpRateLimit::default();
+        let issue = |previous: Option<&OtpChallenge>, now| OtpChallenge::issue(previous, email().key(), "hash".to_string(), now, &limits);
+
+        let first = issue(None, 1000).unwrap();
+        assert!(matches!(issue(Some(&first), 1030), Err(VerificationError::RateLimited { retry_after: 30 })));
+
+        let mut previous = first;
+        for n in 1..limits.max_sends {
+            previous = issue(Some(&previous), 1000 + 60 * u64::from(n)).unwrap();
+        }
+        assert_eq!(previous.sends_in_window(), limits.max_sends);
+        assert!(matches!(issue(Some(&previous), 2000), Err(VerificationError::RateLimited { retry_after: 2600 })));
+
+        // A new window starts an hour after the first code.
+        let next = issue(Some(&previous), 4600).unwrap();
+        assert_eq!((next.sends_in_window(), next.window_started_at()), (1, 4600));
+    }
+
+    #[test]
+    fn test_code_is_checked_once_within_its_lifetime() {
+        let verifier = Verifier::new(b"secret");
+        let code = verifier.generate_code();
+        assert_eq!(code.len(), 6);
+        let hash = verifier.hash_code(&email(), &code);
+        let challenge = OtpChallenge::issue(None, email().key(), hash, 1000, verifier.limits()).unwrap();
+
+        let wrong = if code == "000000" { "111111" } else { "000000" };
+        assert!(matches!(verifier.check_code(&challenge, &email(), wrong, 1001), Err(VerificationError::InvalidCode)));
+        assert!(verifier.check_code(&challenge, &email(), &code, 1001).is_ok());
+        assert!(matches!(
+            verifier.check_code(&challenge, &email(), &code, 1000 + OTP_TTL.as_secs()),
+            Err(VerificationError::Expired)
+        ));
+        assert!(matches!(
+            verifier.check_code(&challenge.clone().consumed(), &email(), &code, 1001),
+            Err(VerificationError::Expired)
+        ));
+
+        let locked = OtpChallenge { attempts: MAX_OTP_ATTEMPTS + 1, ..challenge };
+        assert!(matches!(verifier.check_code(&locked, &email(), &code, 1001), Err(VerificationError::TooManyAttempts)));
+    }
+
+    #[test]
+    fn test_token_only_verifies_its_own_contact() {
+        let verifier = Verifier::new(b"secret");
+        let (token, expires_at) = verifier.issue_token(&email(), 1000);
+        let tokens = [token];
+
+        let same = Contact::Email(EmailAddress::new("test@example.com").unwrap());
+        assert!(verifier.is_verified(&tokens, &same, 1001));
+        assert!(!verifier.is_verified(&tokens, &same, expires_at));
+        let other = Contact::Phone(PhoneNumber::new(91, "9876543210").unwrap());
+        assert!(!verifier.is_verified(&tokens, &other, 1001));
+        assert!(!Verifier::new(b"other secret").is_verified(&tokens, &same, 1001));
+    }
+}
@@ -0,0 +1,281 @@
+/*!
+   Module `outbox` delivers the booking events recorded by the booking store to the
+   [BookingEventHandler](crate::domain::transactions::ports::BookingEventHandler)s subscribed on
+   the [EventBus], retrying failed deliveries with exponential backoff and moving events
+   that keep failing to a dead-letter list.
+*/
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::domain::transactions::event_bus::EventBus;
+use crate::domain::transactions::models::outbox::OutboxEvent;
+use crate::domain::transactions::ports::{BookingRepository, OutboxRepository};
+
+/// Tuning for the [OutboxDispatcher].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboxConfig {
+    /// How often the outbox is polled for due events.
+    pub poll_interval: Duration,
+    /// Maximum number of events delivered per poll.
+    pub batch_size: u32,
+    /// Failed attempts after which an event is dead-lettered.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for every further failure.
+    pub base_backoff: Duration,
+    /// Upper bound for the retry delay.
+    pub max_backoff: Duration,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            batch_size: 50,
+            max_attempts: 8,
+            base_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl OutboxConfig {
+    /// The delay before retrying an event that has failed `attempts` times.
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.base_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Background worker delivering outbox events to the booking handlers of an [EventBus].
+pub struct OutboxDispatcher<S>
+where
+    S: BookingRepository + OutboxRepository,
+{
+    store: S,
+    bus: EventBus,
+    config: OutboxConfig,
+}
+
+impl<S> OutboxDispatcher<S>
+where
+    S: BookingRepository + OutboxRepository,
+{
+    pub fn new(store: S, bus: EventBus, config: OutboxConfig) -> Self {
+        Self { store, bus, config }
+    }
+
+    /// Poll the outbox forever. Only one instance of the service dispatches at a time.
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(self.config.poll_interval);
+        loop {
+            interval.tick().await;
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("Time went backwards")
+                .as_secs();
+            if let Err(e) = self.store.run_dispatch_exclusive(self.dispatch_due(now)).await {
+                tracing::error!("Failed to dispatch outbox: {:?}", e);
+            }
+        }
+    }
+
+    /// Deliver every event due at `now`, returning the number of events attempted.
+    pub async fn dispatch_due(&self, now: u64) -> usize {
+        let events = match self.store.due_outbox_events(now, self.config.batch_size).await {
+            Ok(events) => events,
+            Err(e) => {
+                tracing::error!("Failed to load due outbox events: {:?}", e);
+                return 0;
+            }
+        };
+
+        let count = events.len();
+        for event in events {
+            let id = event.id();
+            let event = self.deliver(event, now).await;
+            if let Err(e) = self.store.update_outbox_event(&event).await {
+                tracing::error!("Failed to record delivery of outbox event {}: {:?}", id, e);
+            }
+        }
+        count
+    }
+
+    async fn deliver(&self, mut event: OutboxEvent, now: u64) -> OutboxEvent {
+        let errors = match self.store.find_booking(event.event().booking_id()).await {
+            Ok(Some(booking)) => {
+                let mut errors = Vec::new();
+                for handler in self.bus.booking_handlers() {
+                    if event.is_completed_by(handler.name()) {
+                        continue;
+                    }
+                    match handler.handle(event.event(), &booking).await {
+                        Ok(()) => event.complete_handler(handler.name()),
+                        Err(e) => errors.push(format!("{}: {}", handler.name(), e)),
+                    }
+                }
+                errors
+            }
+            Ok(None) => vec![format!("booking {} not found", event.event().booking_id())],
+            Err(e) => vec![format!("failed to load booking: {e}")],
+        };
+
+        if errors.is_empty() {
+            return event.delivered();
+        }
+
+        let attempts = event.attempts() + 1;
+        let error = errors.join("; ");
+        let event = event.failed(error, now + self.config.backoff(attempts).as_secs());
+        if attempts >= self.config.max_attempts {
+            tracing::error!(
+                "Dead-lettering outbox event {} ({}) after {} attempts: {}",
+                event.id(),
+                event.event().name(),
+                attempts,
+                event.last_error().unwrap_or_default()
+            );
+            event.dead_lettered()
+        } else {
+            tracing::warn!(
+                "Delivery of outbox event {} ({}) failed, retrying at {}: {}",
+                event.id(),
+                event.event().name(),
+                event.next_attempt_at(),
+                event.last_error().unwrap_or_default()
+            );
+            event
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::domain::transactions::models::booking::Booking;
+    use crate::domain::transactions::models::event::BookingEvent;
+    use crate::domain::transactions::models::outbox::OutboxStatus;
+    use crate::domain::transactions::models::transaction::*;
+    use crate::domain::transactions::ports::{BookingEventHandler, HandlerFuture};
+    use crate::outbound::in_memory::InMemoryBookingRepository;
+
+    /// Counts calls and fails the first `failures` of them.
+    #[derive(Clone)]
+    struct FlakyHandler {
+        name: &'static str,
+        failures: usize,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl BookingEventHandler for FlakyHandler {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn handle<'a>(&'a self, _: &'a BookingEvent, _: &'a Booking) -> HandlerFuture<'a> {
+            Box::pin(async move {
+                let call = self.calls.fetch_add(1, Ordering::SeqCst);
+                if call < self.failures {
+                    Err("unavailable".to_string())
+                } else {
+                    Ok(())
+                }
+            })
+        }
+    }
+
+    fn handler(name: &'static str, failures: usize) -> (FlakyHandler, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler = FlakyHandler {
+            name,
+            failures,
+            calls: calls.clone(),
+        };
+        (handler, calls)
+    }
+
+    async fn store_with_reservation() -> InMemoryBookingRepository {
+        let store = InMemoryBookingRepository::new();
+        let transaction = Transaction::test_fixture(1, 101);
+        let event = BookingEvent::BookingReserved {
+            booking_id: 1,
+            car_id: 101,
+            payment_id: "pay_123".to_string(),
+        };
+        store
+            .save_booking(&Booking::awaiting_payment(transaction, 2500.0), &[event])
+            .await
+            .unwrap();
+        store
+    }
+
+    #[tokio::test]
+    async fn test_failed_handler_is_retried_alone_with_backoff() {
+        let store = store_with_reservation().await;
+        let (email, email_calls) = handler("email", 0);
+        let (webhook, webhook_calls) = handler("webhook", 1);
+        let config = OutboxConfig::default();
+        let bus = EventBus::new().on_booking_event(email).on_booking_event(webhook);
+        let dispatcher = OutboxDispatcher::new(store.clone(), bus, config.clone());
+
+        let now = store.find_outbox_event(1).await.unwrap().unwrap().next_attempt_at();
+        assert_eq!(dispatcher.dispatch_due(now).await, 1);
+        let event = store.find_outbox_event(1).await.unwrap().unwrap();
+        assert_eq!(event.status(), OutboxStatus::Pending);
+        assert_eq!(event.attempts(), 1);
+        assert_eq!(event.next_attempt_at(), now + config.base_backoff.as_secs());
+        assert_eq!(event.completed_handlers(), ["email".to_string()]);
+
+        // Not due yet
+        assert_eq!(dispatcher.dispatch_due(now + 1).await, 0);
+
+        assert_eq!(dispatcher.dispatch_due(event.next_attempt_at()).await, 1);
+        let event = store.find_outbox_event(1).await.unwrap().unwrap();
+        assert_eq!(event.status(), OutboxStatus::Delivered);
+        assert_eq!(email_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(webhook_calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_event_is_dead_lettered_and_replayed() {
+        let store = store_with_reservation().await;
+        let (webhook, webhook_calls) = handler("webhook", 3);
+        let config = OutboxConfig {
+            max_attempts: 3,
+            ..OutboxConfig::default()
+        };
+        let dispatcher = OutboxDispatcher::new(store.clone(), EventBus::new().on_booking_event(webhook), config);
+
+        let mut now = 0;
+        for _ in 0..3 {
+            now = store.find_outbox_event(1).await.unwrap().unwrap().next_attempt_at();
+            dispatcher.dispatch_due(now).await;
+        }
+        let dead_letters = store.dead_lettered_outbox_events().await.unwrap();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].attempts(), 3);
+        assert_eq!(dispatcher.dispatch_due(u64::MAX).await, 0);
+
+        store
+            .update_outbox_event(&dead_letters[0].clone().replayed(now))
+            .await
+            .unwrap();
+        assert_eq!(dispatcher.dispatch_due(now).await, 1);
+        let event = store.find_outbox_event(1).await.unwrap().unwrap();
+        assert_eq!(event.status(), OutboxStatus::Delivered);
+        assert_eq!(webhook_calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_maximum() {
+        let config = OutboxConfig::default();
+        assert_eq!(config.backoff(1), Duration::from_secs(30));
+        assert_eq!(config.backoff(2), Duration::from_secs(60));
+        assert_eq!(config.backoff(4), Duration::from_secs(240));
+        assert_eq!(config.backoff(20), config.max_backoff);
+    }
+}
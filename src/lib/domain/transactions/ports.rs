@@ -9,10 +9,24 @@
 */
 
 use std::future::Future;
+use std::net::IpAddr;
+use std::pin::Pin;
 
 use crate::{canister::backend::{RazorpayPayment, RentalTransaction}, domain::transactions::models::transaction::{
-    CreateTransactionError, CreateTransactionRequest, Transaction
+    CreateTransactionError, CreateTransactionRequest, EmailAddress, Transaction
 }};
+use crate::domain::transactions::models::availability::{Availability, AvailabilityError, AvailabilityRange, Interval};
+use crate::domain::transactions::models::booking::{Booking, BookingRepositoryError, CancelBookingError};
+use crate::domain::transactions::models::data_request::{
+    CustomerDataExport, DataRequestError, DataRequestKind, DataRequestRecord, DataSubject, ErasureReport,
+};
+use crate::domain::transactions::models::document::{Document, DocumentError, DocumentId, DocumentKind};
+use crate::domain::transactions::models::hold::{SlotHold, SlotHoldError};
+use crate::domain::transactions::models::event::{BookingEvent, TransactionEvent};
+use crate::domain::transactions::models::outbox::{OutboxError, OutboxEvent};
+use crate::domain::transactions::models::phone_number::PhoneNumber;
+use crate::domain::transactions::models::verification::{Contact, OtpChallenge, VerificationError, VerificationToken};
+use crate::identity::admin::{AdminIdentity, AdminKeyInfo, AdminKeyRotationError};
 
 
 /// `TransactionService` is the public API for the transaction domain.
@@ -20,7 +34,8 @@ use crate::{canister::backend::{RazorpayPayment, RentalTransaction}, domain::tra
 /// External modules must conform to this contract – the domain is not concerned with the
 /// implementation details or underlying technology of any external code.
 pub trait PaymentService: Clone + Send + Sync + 'static {
-    /// Asynchronously create a new payment link [Payment].
+    /// Asynchronously create a new payment link [Payment] that can no longer be paid after the
+    /// unix timestamp `expire_by`.
     ///
     /// # Errors
     ///
@@ -29,6 +44,7 @@ pub trait PaymentService: Clone + Send + Sync + 'static {
         &self,
         amount: f64,
         booking_id: u64,
+        expire_by: u64,
     ) -> impl Future<Output = Result<String, String>> + Send;
 }
 
@@ -53,6 +69,138 @@ pub trait TransactionService: Clone + Send + Sync + 'static {
     ) -> impl Future<Output = Result<String, CreateTransactionError>> + Send;
 
     fn get_principal(&self) -> impl Future<Output = Result<String, CreateTransactionError>> + Send;
+
+    /// The booking with `booking_id`, if there is one.
+    fn find_booking(&self, booking_id: u64) -> impl Future<Output = Result<Option<Booking>, BookingRepositoryError>> + Send;
+
+    /// Cancel `booking_id` before it is paid for, releasing its slot. Cancelling a cancelled
+    /// booking succeeds without changing it.
+    ///
+    /// The payment link stays payable: a payment made after cancelling still reserves the car if
+    /// it is free.
+    ///
+    /// # Errors
+    ///
+    /// - [CancelBookingError::NotFound] if there is no booking with `booking_id`.
+    /// - [CancelBookingError::AlreadyReserved] if the booking was paid for; the canister has no
+    ///   way to cancel a reservation.
+    fn cancel_booking(&self, booking_id: u64) -> impl Future<Output = Result<Booking, CancelBookingError>> + Send;
+
+    /// The booked, held and free intervals of `car_id` between `from` and `to`.
+    ///
+    /// Bookings may be a few seconds stale; the canister still rejects a conflicting booking.
+    ///
+    /// # Errors
+    ///
+    /// - [AvailabilityError::InvalidRange] or [AvailabilityError::RangeTooLong] for a bad range.
+    fn car_availability(
+        &self,
+        car_id: u64,
+        from: u64,
+        to: u64,
+    ) -> impl Future<Output = Result<Availability, AvailabilityError>> + Send;
+
+    /// List the outbox events that exhausted their delivery attempts.
+    fn dead_letters(&self) -> impl Future<Output = Result<Vec<OutboxEvent>, OutboxError>> + Send;
+
+    /// Queue a dead-lettered outbox event for delivery again.
+    ///
+    /// # Errors
+    ///
+    /// - [OutboxError::NotFound] if there is no event with `id`.
+    /// - [OutboxError::NotDeadLettered] if the event is not in the dead-letter list.
+    fn replay_dead_letter(&self, id: u64) -> impl Future<Output = Result<OutboxEvent, OutboxError>> + Send;
+
+    /// Encrypt and store a KYC document for `booking_id`.
+    ///
+    /// # Errors
+    ///
+    /// - [DocumentError::BookingNotFound] if there is no booking with `booking_id`.
+    /// - [DocumentError::UnsupportedType], [DocumentError::Empty] or [DocumentError::TooLarge]
+    ///   if `contents` is not an acceptable document.
+    fn upload_document(
+        &self,
+        booking_id: u64,
+        kind: DocumentKind,
+        declared_content_type: Option<String>,
+        contents: Vec<u8>,
+    ) -> impl Future<Output = Result<Document, DocumentError>> + Send;
+
+    /// Load and decrypt a document of `booking_id`.
+    ///
+    /// # Errors
+    ///
+    /// - [DocumentError::NotFound] if the booking has no document with `document_id`.
+    fn get_document(
+        &self,
+        booking_id: u64,
+        document_id: &str,
+    ) -> impl Future<Output = Result<(Document, Vec<u8>), DocumentError>> + Send;
+
+    /// Send a one-time password to `contact`, at the request of the client at `client`.
+    ///
+    /// # Errors
+    ///
+    /// - [VerificationError::RateLimited] if too many codes were sent to `contact`, or at the
+    ///   request of `client`, recently.
+    /// - [VerificationError::Send] if the code could not be delivered.
+    fn send_verification(
+        &self,
+        contact: &Contact,
+        client: IpAddr,
+    ) -> impl Future<Output = Result<(), VerificationError>> + Send;
+
+    /// Confirm the one-time password sent to `contact`, returning a token that proves it was
+    /// verified and the unix timestamp at which the token expires.
+    ///
+    /// # Errors
+    ///
+    /// - [VerificationError::InvalidCode], [VerificationError::Expired] or
+    ///   [VerificationError::TooManyAttempts] if `code` is not accepted.
+    fn confirm_verification(
+        &self,
+        contact: &Contact,
+        code: &str,
+    ) -> impl Future<Output = Result<(VerificationToken, u64), VerificationError>> + Send;
+
+    /// Everything stored about `subject`: their bookings, documents, notifications and payments.
+    /// Every request is written to the `audit` log target with its outcome.
+    fn export_customer_data(
+        &self,
+        subject: &DataSubject,
+    ) -> impl Future<Output = Result<CustomerDataExport, DataRequestError>> + Send;
+
+    /// Erase the personal data of `subject`: anonymise their bookings, keeping the financial
+    /// records, and delete their documents and verification codes. Every request is written to
+    /// the `audit` log target with its outcome.
+    ///
+    /// The canister keeps its own copy of each reservation, which this service cannot erase.
+    fn erase_customer_data(
+        &self,
+        subject: &DataSubject,
+    ) -> impl Future<Output = Result<ErasureReport, DataRequestError>> + Send;
+
+    /// Record a data subject request that was refused before it could be made, e.g. for a wrong
+    /// admin token or an invalid subject, in the `audit` log and the record of requests.
+    fn refuse_data_request(&self, request: DataRequestKind, reason: &str) -> impl Future<Output = ()> + Send;
+
+    /// The keys held for calling the backend canister as its controller.
+    fn admin_keys(&self) -> impl Future<Output = Vec<AdminKeyInfo>> + Send;
+
+    /// Load the admin key in `pem`, decrypting it with `passphrase` if it is encrypted, and make
+    /// it the active key once it has proven to control the backend canister. Every request is
+    /// written to the `audit` log target with its outcome.
+    ///
+    /// # Errors
+    ///
+    /// - [AdminKeyRotationError::Key] if `pem` is not a usable key.
+    /// - [AdminKeyRotationError::NotAuthorized] if the key does not control the canister; the
+    ///   active key is left as it was.
+    fn rotate_admin_key(
+        &self,
+        pem: &str,
+        passphrase: Option<&str>,
+    ) -> impl Future<Output = Result<AdminKeyInfo, AdminKeyRotationError>> + Send;
 }
 
 /// `TransactionRepository` represents a store of transaction data.
@@ -78,28 +226,248 @@ pub trait TransactionRepository: Send + Sync + Clone + 'static {
     ) -> impl Future<Output = Result<RentalTransaction, CreateTransactionError>> + Send;
 
     fn get_principal(&self) -> impl Future<Output = Result<String, CreateTransactionError>> + Send;
+
+    /// The intervals in which `car_id` is booked that overlap `range`.
+    fn booked_intervals(
+        &self,
+        car_id: u64,
+        range: &AvailabilityRange,
+    ) -> impl Future<Output = Result<Vec<Interval>, AvailabilityError>> + Send;
+
+    /// The admin keys the repository holds, exactly one of them active.
+    fn admin_keys(&self) -> impl Future<Output = Vec<AdminKeyInfo>> + Send;
+
+    /// Confirm with a call to the canister that `admin` controls it, then make every later call
+    /// as `admin`. Calls already under way finish as the previous key, which stays held.
+    fn rotate_admin_identity(
+        &self,
+        admin: AdminIdentity,
+    ) -> impl Future<Output = Result<AdminKeyInfo, AdminKeyRotationError>> + Send;
+}
+
+/// `BookingRepository` is the offchain service's own record of [Booking]s.
+///
+/// The canister remains the source of truth for reservations; this store keeps the customer and
+/// payment details the service needs between requests, and must be shared between every running
+/// instance of the service.
+pub trait BookingRepository: Send + Sync + Clone + 'static {
+    /// Insert `booking`, or replace the stored booking with the same booking ID, and append
+    /// `events` to the outbox.
+    ///
+    /// The booking and its events MUST be written atomically: either both are stored or neither.
+    fn save_booking(
+        &self,
+        booking: &Booking,
+        events: &[BookingEvent],
+    ) -> impl Future<Output = Result<(), BookingRepositoryError>> + Send;
+
+    fn find_booking(
+        &self,
+        booking_id: u64,
+    ) -> impl Future<Output = Result<Option<Booking>, BookingRepositoryError>> + Send;
+
+    fn find_bookings_by_email(
+        &self,
+        email: &EmailAddress,
+    ) -> impl Future<Output = Result<Vec<Booking>, BookingRepositoryError>> + Send;
+
+    fn find_bookings_by_phone_number(
+        &self,
+        phone_number: &PhoneNumber,
+    ) -> impl Future<Output = Result<Vec<Booking>, BookingRepositoryError>> + Send;
+
+    fn find_bookings_by_principal(
+        &self,
+        customer_principal: &str,
+    ) -> impl Future<Output = Result<Vec<Booking>, BookingRepositoryError>> + Send;
+}
+
+/// `SlotHoldRepository` stores the [SlotHold]s placed while customers pay, shared by every
+/// instance of the service.
+pub trait SlotHoldRepository: Send + Sync + Clone + 'static {
+    /// Place `hold` unless another customer holds an overlapping slot of the same car at `now`.
+    ///
+    /// Checking for conflicts and placing the hold MUST be atomic, so that two concurrent
+    /// requests for the same slot cannot both succeed.
+    ///
+    /// # Errors
+    ///
+    /// - MUST return [SlotHoldError::Held] with the conflicting hold's expiry if there is one.
+    fn place_hold(
+        &self,
+        hold: &SlotHold,
+        now: u64,
+    ) -> impl Future<Output = Result<(), SlotHoldError>> + Send;
+
+    /// Remove the hold of `booking_id`, if any.
+    fn release_hold(
+        &self,
+        booking_id: u64,
+    ) -> impl Future<Output = Result<(), BookingRepositoryError>> + Send;
+
+    /// The slots of `car_id` held at `now` that overlap `range`.
+    fn held_intervals(
+        &self,
+        car_id: u64,
+        range: &AvailabilityRange,
+        now: u64,
+    ) -> impl Future<Output = Result<Vec<Interval>, BookingRepositoryError>> + Send;
+}
+
+/// `DataRequestRepository` keeps the record of data subject requests and their outcomes that the
+/// service must be able to show, shared by every instance of the service.
+pub trait DataRequestRepository: Send + Sync + Clone + 'static {
+    /// Append `record` to the record of requests.
+    fn record_data_request(
+        &self,
+        record: &DataRequestRecord,
+    ) -> impl Future<Output = Result<(), BookingRepositoryError>> + Send;
+}
+
+/// `AdminKeyRepository` stores the admin key last rotated to, so that every instance of the
+/// service calls the canister as the same key, also after a restart.
+pub trait AdminKeyRepository: Send + Sync + Clone + 'static {
+    /// Make `admin` the stored key, replacing any other.
+    fn save_admin_key(&self, admin: &AdminIdentity) -> impl Future<Output = Result<(), BookingRepositoryError>> + Send;
+
+    /// The stored key, or `None` if the key has never been rotated.
+    fn find_admin_key(&self) -> impl Future<Output = Result<Option<AdminIdentity>, BookingRepositoryError>> + Send;
+}
+
+/// `DocumentStore` keeps encrypted KYC documents. Documents are sealed before they reach the
+/// store, which only ever sees ciphertext.
+pub trait DocumentStore: Send + Sync + Clone + 'static {
+    /// Store `sealed` as the document `id` of `booking_id`, replacing any previous contents.
+    fn put_document(
+        &self,
+        booking_id: u64,
+        id: &DocumentId,
+        sealed: Vec<u8>,
+    ) -> impl Future<Output = Result<(), DocumentError>> + Send;
+
+    /// The sealed contents of document `id` of `booking_id`, or `None` if there is none.
+    fn get_document(
+        &self,
+        booking_id: u64,
+        id: &DocumentId,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>, DocumentError>> + Send;
+
+    /// The IDs of every document of `booking_id`.
+    fn list_documents(
+        &self,
+        booking_id: u64,
+    ) -> impl Future<Output = Result<Vec<DocumentId>, DocumentError>> + Send;
+
+    /// Delete document `id` of `booking_id`. Deleting a document that doesn't exist succeeds.
+    fn delete_document(
+        &self,
+        booking_id: u64,
+        id: &DocumentId,
+    ) -> impl Future<Output = Result<(), DocumentError>> + Send;
+}
+
+/// `VerificationRepository` stores the [OtpChallenge] most recently sent to each contact, shared
+/// by every instance of the service so that rate limits hold across instances.
+pub trait VerificationRepository: Send + Sync + Clone + 'static {
+    /// Replace the challenge of `contact` with the one `issue` makes from the current one, if
+    /// any, and return it.
+    ///
+    /// Reading and replacing the challenge MUST be atomic, so that concurrent requests cannot
+    /// both pass the rate limit.
+    fn issue_otp<F>(
+        &self,
+        contact: &str,
+        issue: F,
+    ) -> impl Future<Output = Result<OtpChallenge, VerificationError>> + Send
+    where
+        F: FnOnce(Option<&OtpChallenge>) -> Result<OtpChallenge, VerificationError> + Send;
+
+    /// Count an attempt at the challenge of `contact` and return it, including that attempt.
+    fn record_otp_attempt(
+        &self,
+        contact: &str,
+    ) -> impl Future<Output = Result<Option<OtpChallenge>, VerificationError>> + Send;
+
+    /// Mark the code of `contact` as used.
+    fn consume_otp(&self, contact: &str) -> impl Future<Output = Result<(), VerificationError>> + Send;
+
+    /// Forget the challenge of `contact`, rate limit included.
+    fn remove_otp(&self, contact: &str) -> impl Future<Output = Result<(), VerificationError>> + Send;
+}
+
+/// `VerificationSender` delivers one-time passwords to customers.
+pub trait VerificationSender: Send + Sync + Clone + 'static {
+    fn send_otp(&self, contact: &Contact, code: &str) -> impl Future<Output = Result<(), VerificationError>> + Send;
 }
 
-/// `TransactionMetrics` describes an aggregator of transaction-related metrics, such as a time-series
-/// database.
-pub trait TransactionMetrics: Send + Sync + Clone + 'static {
-    /// Record a successful transaction creation.
-    fn record_transaction_creation_success(&self) -> impl Future<Output = ()> + Send;
+/// `OutboxRepository` is the durable queue of [BookingEvent]s written by
+/// [BookingRepository::save_booking].
+pub trait OutboxRepository: Send + Sync + Clone + 'static {
+    /// Pending events whose next attempt is due at or before `now`, oldest first.
+    fn due_outbox_events(
+        &self,
+        now: u64,
+        limit: u32,
+    ) -> impl Future<Output = Result<Vec<OutboxEvent>, BookingRepositoryError>> + Send;
+
+    fn find_outbox_event(
+        &self,
+        id: u64,
+    ) -> impl Future<Output = Result<Option<OutboxEvent>, BookingRepositoryError>> + Send;
+
+    /// Every event of `booking_id`, oldest first.
+    fn find_outbox_events_by_booking(
+        &self,
+        booking_id: u64,
+    ) -> impl Future<Output = Result<Vec<OutboxEvent>, BookingRepositoryError>> + Send;
+
+    fn dead_lettered_outbox_events(
+        &self,
+    ) -> impl Future<Output = Result<Vec<OutboxEvent>, BookingRepositoryError>> + Send;
+
+    /// Persist the delivery state of `event`.
+    fn update_outbox_event(
+        &self,
+        event: &OutboxEvent,
+    ) -> impl Future<Output = Result<(), BookingRepositoryError>> + Send;
 
-    /// Record a transaction creation failure.
-    fn record_transaction_creation_failure(&self) -> impl Future<Output = ()> + Send;
+    /// Run `job` only if no other instance of the service is dispatching the outbox.
+    ///
+    /// Returns `None` without running `job` when another instance holds the dispatch lock.
+    fn run_dispatch_exclusive<F>(
+        &self,
+        job: F,
+    ) -> impl Future<Output = Result<Option<F::Output>, BookingRepositoryError>> + Send
+    where
+        F: Future + Send,
+        F::Output: Send;
 }
 
-/// `TransactionNotifier` triggers notifications related to transactions.
+/// The future returned by [BookingEventHandler::handle].
+pub type HandlerFuture<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>>;
+
+/// `BookingEventHandler` reacts to [BookingEvent]s delivered from the outbox, e.g. by sending an
+/// email or calling a webhook.
 ///
-/// Whether the notification medium (email, SMS, etc.) is known by the business logic is a
-/// judgement call based on your use case.
+/// Unlike the other ports this trait is object safe, so that any number of handlers can be
+/// subscribed on the [EventBus](super::event_bus::EventBus).
 ///
-/// Some domains will always require email, for example, so hiding this detail would be
-/// pointless.
+/// Delivery is at-least-once: a handler may see the same event again if the service stops
+/// between handling it and recording the delivery.
+pub trait BookingEventHandler: Send + Sync + 'static {
+    /// A stable name, recorded in the outbox once the handler has processed an event.
+    fn name(&self) -> &'static str;
+
+    fn handle<'a>(&'a self, event: &'a BookingEvent, booking: &'a Booking) -> HandlerFuture<'a>;
+}
+
+/// `TransactionEventHandler` reacts to [TransactionEvent]s published on the
+/// [EventBus](super::event_bus::EventBus), e.g. by recording a metric.
 ///
-/// For others, code coordinating notifications will be complex enough to warrant its own domain.
-/// In this case, a `TransactionNotifier` adapter will call that domain's `Service`.
-pub trait TransactionNotifier: Send + Sync + Clone + 'static {
-    fn transaction_created(&self, transaction: &Transaction) -> impl Future<Output = ()> + Send;
+/// Handlers run in-process while the request is being served; there are no retries, so a failed
+/// handler is logged and the event is dropped.
+pub trait TransactionEventHandler: Send + Sync + 'static {
+    fn name(&self) -> &'static str;
+
+    fn handle<'a>(&'a self, event: &'a TransactionEvent) -> HandlerFuture<'a>;
 }
@@ -3,80 +3,510 @@
    transaction-domain logic is defined here.
 */
 
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
 use anyhow::anyhow;
 
 use crate::{canister::backend::RazorpayPayment, domain::transactions::models::transaction::{
     CreateTransactionError, CreateTransactionRequest, Transaction
 }};
-use crate::domain::transactions::ports::{TransactionNotifier, TransactionMetrics, TransactionRepository, TransactionService};
+use crate::domain::transactions::models::booking::{Booking, BookingRepositoryError, BookingStatus, CancelBookingError};
+use crate::domain::transactions::event_bus::EventBus;
+use crate::domain::transactions::models::age_policy::AgePolicy;
+use crate::domain::transactions::models::availability::{Availability, AvailabilityError, AvailabilityRange, Interval};
+use crate::domain::transactions::models::data_request::{
+    BookingRecord, CustomerDataExport, DataRequestError, DataRequestKind, DataRequestOutcome, DataRequestRecord, DataSubject,
+    DocumentRef, ErasureReport, PaymentRecord,
+};
+use crate::domain::transactions::models::document::{Document, DocumentCipher, DocumentError, DocumentId, DocumentKind};
+use crate::domain::transactions::models::hold::{SlotHold, DEFAULT_HOLD_DURATION};
+use crate::domain::transactions::models::event::{BookingEvent, TransactionEvent};
+use crate::domain::transactions::models::outbox::{OutboxError, OutboxEvent, OutboxStatus};
+use crate::domain::transactions::models::verification::{client_key, Contact, OtpChallenge, VerificationError, VerificationToken, Verifier};
+use crate::domain::transactions::ports::{
+    AdminKeyRepository, BookingRepository, DataRequestRepository, DocumentStore, OutboxRepository, SlotHoldRepository, TransactionRepository, TransactionService,
+    VerificationRepository, VerificationSender,
+};
+use crate::identity::admin::{AdminIdentity, AdminKeyInfo, AdminKeyRotationError};
 
 use super::ports::PaymentService;
 
 /// Canonical implementation of the [TransactionService] port, through which the transaction domain API is
 /// consumed.
+///
+/// Side effects such as notifications and metrics are not performed here: the service records
+/// [BookingEvent]s in the outbox along with each booking and publishes [TransactionEvent]s on the
+/// [EventBus], whose subscribers are registered at startup.
 #[derive(Debug, Clone)]
-pub struct Service<R, M, N, P>
+pub struct Service<R, P, B, D, V>
 where
     R: TransactionRepository,
-    M: TransactionMetrics,
-    N: TransactionNotifier,
     P: PaymentService,
+    B: BookingRepository
+        + OutboxRepository
+        + SlotHoldRepository
+        + VerificationRepository
+        + DataRequestRepository
+        + AdminKeyRepository,
+    D: DocumentStore,
+    V: VerificationSender,
 {
     repo: R,
-    metrics: M,
-    transaction_notifier: N,
     payment_service: P,
+    bookings: B,
+    documents: D,
+    document_cipher: DocumentCipher,
+    verification_sender: V,
+    verifier: Verifier,
+    events: EventBus,
+    availability: AvailabilityCache,
+    hold_duration: Duration,
+    age_policy: AgePolicy,
+}
+
+/// How long a car's availability is served from memory before the canister is queried again.
+const AVAILABILITY_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// How often deleting a document of an erased booking is tried before it is reported.
+const DOCUMENT_DELETE_ATTEMPTS: u32 = 3;
+
+/// The delay before the first retry of a document deletion, growing with every retry.
+const DOCUMENT_DELETE_BACKOFF: Duration = Duration::from_millis(200);
+
+/// How often the stored admin key is checked for a rotation made through another instance.
+pub const ADMIN_KEY_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The most cars whose availability is kept in memory at once.
+const AVAILABILITY_CACHE_CARS: usize = 1024;
+
+/// The bookings of recently queried cars, so that calendar views re-rendering a month, or a week
+/// within it, don't query the canister every time.
+///
+/// One range is kept per car, and at most [AVAILABILITY_CACHE_CARS] cars, dropping the one
+/// fetched longest ago, so varying the requested car or range can't grow the cache.
+#[derive(Debug, Clone, Default)]
+struct AvailabilityCache(Arc<Mutex<HashMap<u64, CachedBookings>>>);
+
+#[derive(Debug)]
+struct CachedBookings {
+    fetched_at: Instant,
+    range: AvailabilityRange,
+    booked: Vec<Interval>,
+}
+
+impl AvailabilityCache {
+    /// The bookings of `car_id` overlapping `range`, if it lies within the last range fetched for
+    /// the car less than [AVAILABILITY_CACHE_TTL] ago.
+    fn get(&self, car_id: u64, range: &AvailabilityRange) -> Option<Vec<Interval>> {
+        let cache = self.0.lock().ok()?;
+        let cached = cache.get(&car_id)?;
+        (cached.fetched_at.elapsed() < AVAILABILITY_CACHE_TTL && cached.range.contains(range))
+            .then(|| cached.booked.clone())
+    }
+
+    /// Remember the `booked` intervals of `car_id` fetched for `range`.
+    fn insert(&self, car_id: u64, range: &AvailabilityRange, booked: &[Interval]) {
+        if let Ok(mut cache) = self.0.lock() {
+            cache.retain(|_, cached| cached.fetched_at.elapsed() < AVAILABILITY_CACHE_TTL);
+            if !cache.contains_key(&car_id) && cache.len() >= AVAILABILITY_CACHE_CARS {
+                let oldest = cache.iter().min_by_key(|(_, cached)| cached.fetched_at).map(|(car_id, _)| *car_id);
+                if let Some(oldest) = oldest {
+                    cache.remove(&oldest);
+                }
+            }
+            cache.insert(car_id, CachedBookings { fetched_at: Instant::now(), range: *range, booked: booked.to_vec() });
+        }
+    }
+
+    /// Forget the bookings of `car_id`, e.g. after it was booked through this instance.
+    fn invalidate(&self, car_id: u64) {
+        if let Ok(mut cache) = self.0.lock() {
+            cache.remove(&car_id);
+        }
+    }
 }
 
-impl<R, M, N, P> Service<R, M, N, P>
+impl<R, P, B, D, V> Service<R, P, B, D, V>
 where
     R: TransactionRepository,
-    M: TransactionMetrics,
-    N: TransactionNotifier,
     P: PaymentService,
+    B: BookingRepository
+        + OutboxRepository
+        + SlotHoldRepository
+        + VerificationRepository
+        + DataRequestRepository
+        + AdminKeyRepository,
+    D: DocumentStore,
+    V: VerificationSender,
 {
-    pub fn new(repo: R, metrics: M, transaction_notifier: N, payment_service: P) -> Self {
+    pub fn new(
+        repo: R,
+        payment_service: P,
+        bookings: B,
+        documents: D,
+        document_cipher: DocumentCipher,
+        verification_sender: V,
+        events: EventBus,
+    ) -> Self {
         Self {
             repo,
-            metrics,
-            transaction_notifier,
             payment_service,
+            bookings,
+            documents,
+            document_cipher,
+            verification_sender,
+            verifier: Verifier::random(),
+            events,
+            availability: AvailabilityCache::default(),
+            hold_duration: DEFAULT_HOLD_DURATION,
+            age_policy: AgePolicy::default(),
+        }
+    }
+
+    /// The ages allowed to drive each category of car.
+    pub fn with_age_policy(mut self, age_policy: AgePolicy) -> Self {
+        self.age_policy = age_policy;
+        self
+    }
+
+    /// Signs verification tokens, which must be shared by every instance of the service.
+    pub fn with_verifier(mut self, verifier: Verifier) -> Self {
+        self.verifier = verifier;
+        self
+    }
+
+    /// How long a slot stays held, and its payment link payable, after the link is created.
+    pub fn with_hold_duration(mut self, hold_duration: Duration) -> Self {
+        self.hold_duration = hold_duration;
+        self
+    }
+
+    /// Append a data subject request to the durable record of requests. The request has been
+    /// answered by now, so a failure is logged rather than returned.
+    async fn record_data_request(
+        &self,
+        request: DataRequestKind,
+        subject: Option<&DataSubject>,
+        outcome: DataRequestOutcome,
+        reason: Option<String>,
+        booking_ids: Vec<u64>,
+    ) {
+        let requested_at = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs();
+        let record = DataRequestRecord { request, subject: subject.map(DataSubject::kind), outcome, reason, booking_ids, requested_at };
+        if let Err(e) = self.bookings.record_data_request(&record).await {
+            tracing::error!(
+                target: "audit",
+                request = request.as_str(),
+                outcome = outcome.as_str(),
+                "Failed to record data subject request: {:?}",
+                e
+            );
+        }
+    }
+
+    /// Switch to the admin key last rotated to through any instance, if it isn't the active key
+    /// already, every [ADMIN_KEY_POLL_INTERVAL], forever.
+    pub async fn poll_admin_key(self) {
+        let mut interval = tokio::time::interval(ADMIN_KEY_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            self.sync_admin_key().await;
+        }
+    }
+
+    async fn sync_admin_key(&self) {
+        let stored = match self.bookings.find_admin_key().await {
+            Ok(Some(stored)) => stored,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::error!("Failed to load the stored admin key: {:?}", e);
+                return;
+            }
+        };
+        let principal = stored.principal().to_text();
+        let active = self.repo.admin_keys().await.into_iter().any(|key| key.active && key.principal == principal);
+        if active {
+            return;
+        }
+
+        match self.repo.rotate_admin_identity(stored).await {
+            Ok(key) => tracing::info!(
+                target: "audit",
+                principal = %key.principal,
+                key_type = %key.key_type,
+                source = "store",
+                outcome = "completed",
+                "admin key rotation"
+            ),
+            Err(e) => tracing::warn!(
+                target: "audit",
+                principal = %principal,
+                source = "store",
+                outcome = "failed",
+                reason = %e,
+                "admin key rotation"
+            ),
+        }
+    }
+
+    /// Switch to `admin`, then store it for the other instances.
+    ///
+    /// If it can't be stored, this instance has switched already but the others haven't, so the
+    /// error is returned for the rotation to be repeated.
+    async fn rotate_and_store(&self, admin: AdminIdentity) -> Result<AdminKeyInfo, AdminKeyRotationError> {
+        let key = self.repo.rotate_admin_identity(admin.clone()).await?;
+        self.bookings
+            .save_admin_key(&admin)
+            .await
+            .map_err(|e| anyhow!("Switched to {} here, but failed to store it for the other instances: {e}", key.principal))?;
+        Ok(key)
+    }
+
+    /// Record a reservation confirmed by the canister in the booking store, together with its
+    /// [BookingEvent::BookingReserved] event.
+    ///
+    /// The canister has already reserved the car at this point, so a storage failure is logged
+    /// rather than returned to the customer.
+    async fn record_reservation(&self, transaction: &Transaction, payment: &RazorpayPayment) {
+        let booking = match self.bookings.find_booking(transaction.booking_id()).await {
+            Ok(Some(booking)) => booking,
+            Ok(None) => Booking::new(transaction.clone(), None, BookingStatus::AwaitingPayment, None, None),
+            Err(e) => {
+                tracing::error!("Failed to load booking {}: {:?}", transaction.booking_id(), e);
+                return;
+            }
+        };
+
+        self.availability.invalidate(transaction.car_id());
+        let booking = booking.reserved(transaction.clone(), payment.payment_id.clone());
+        let event = BookingEvent::BookingReserved {
+            booking_id: transaction.booking_id(),
+            car_id: transaction.car_id(),
+            payment_id: payment.payment_id.clone(),
+        };
+        if let Err(e) = self.bookings.save_booking(&booking, &[event]).await {
+            tracing::error!("Failed to record reservation {}: {:?}", transaction.booking_id(), e);
+        }
+        // The canister holds the reservation now.
+        self.release_hold(transaction.booking_id()).await;
+    }
+
+    /// Check that `req` carries a verification token for both its email address and its mobile
+    /// number.
+    fn check_verified(&self, req: &CreateTransactionRequest) -> Result<(), VerificationError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs();
+        for contact in [Contact::Email(req.email().clone()), Contact::Phone(req.phone_number().clone())] {
+            if !self.verifier.is_verified(req.verification_tokens(), &contact, now) {
+                return Err(VerificationError::NotVerified { contact });
+            }
+        }
+        Ok(())
+    }
+
+    /// The bookings of `subject`. Erased bookings are not found, as erasure removes both the
+    /// email address and the principal.
+    async fn find_subject_bookings(&self, subject: &DataSubject) -> Result<Vec<Booking>, DataRequestError> {
+        Ok(match subject {
+            DataSubject::Email(email) => self.bookings.find_bookings_by_email(email).await?,
+            DataSubject::Principal(principal) => self.bookings.find_bookings_by_principal(&principal.to_text()).await?,
+        })
+    }
+
+    async fn collect_customer_data(&self, subject: &DataSubject) -> Result<CustomerDataExport, DataRequestError> {
+        let exported_at = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs();
+        let bookings = self.find_subject_bookings(subject).await?;
+
+        let mut documents = Vec::new();
+        let mut unreadable_documents = Vec::new();
+        let mut notifications = Vec::new();
+        for booking in &bookings {
+            let booking_id = booking.booking_id();
+            for id in self.documents.list_documents(booking_id).await? {
+                let Some(sealed) = self.documents.get_document(booking_id, &id).await? else {
+                    continue;
+                };
+                // One broken document must not keep the customer from the rest of their data.
+                let document = self
+                    .document_cipher
+                    .open(booking_id, &id, &sealed)
+                    .and_then(|contents| Document::from_stored(booking_id, id.clone(), &contents));
+                match document {
+                    Ok(document) => documents.push(document),
+                    Err(e) => {
+                        tracing::error!("Failed to read document {} of booking {} for export: {}", id, booking_id, e);
+                        unreadable_documents.push(DocumentRef { booking_id, document_id: id });
+                    }
+                }
+            }
+            notifications.extend(self.bookings.find_outbox_events_by_booking(booking_id).await?);
+        }
+
+        Ok(CustomerDataExport {
+            exported_at,
+            bookings: bookings.iter().map(BookingRecord::from).collect(),
+            documents,
+            unreadable_documents,
+            notifications,
+            payments: bookings.iter().map(PaymentRecord::from).collect(),
+        })
+    }
+
+    async fn erase_subject_data(&self, subject: &DataSubject) -> Result<ErasureReport, DataRequestError> {
+        let erased_at = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs();
+        let bookings = self.find_subject_bookings(subject).await?;
+
+        let mut contacts = Vec::new();
+        if let DataSubject::Email(email) = subject {
+            contacts.push(Contact::Email(email.clone()).key());
+        }
+        let mut booking_ids = Vec::new();
+        let mut documents_deleted = 0;
+        let mut documents_not_deleted = Vec::new();
+        for booking in bookings.into_iter().filter(|booking| booking.erased_at().is_none()) {
+            let booking_id = booking.booking_id();
+            let transaction = booking.transaction();
+            contacts.push(Contact::Email(transaction.email().clone()).key());
+            contacts.push(Contact::Phone(transaction.phone_number().clone()).key());
+            // Erase the booking first: once it is saved, the subject no longer leads to it, so
+            // whatever fails after is reported rather than left for a repeated request.
+            self.bookings.save_booking(&booking.erased(erased_at), &[]).await?;
+            booking_ids.push(booking_id);
+            self.release_hold(booking_id).await;
+
+            for id in self.documents.list_documents(booking_id).await? {
+                match self.delete_document_with_retries(booking_id, &id).await {
+                    Ok(()) => documents_deleted += 1,
+                    Err(e) => {
+                        tracing::error!("Failed to delete document {} of erased booking {}: {}", id, booking_id, e);
+                        documents_not_deleted.push(DocumentRef { booking_id, document_id: id });
+                    }
+                }
+            }
+        }
+
+        contacts.sort();
+        contacts.dedup();
+        for contact in contacts {
+            self.bookings.remove_otp(&contact).await?;
+        }
+
+        Ok(ErasureReport {
+            erased_at,
+            booking_ids,
+            documents_deleted,
+            documents_not_deleted,
+        })
+    }
+
+    /// Delete document `id` of `booking_id`, trying [DOCUMENT_DELETE_ATTEMPTS] times.
+    async fn delete_document_with_retries(&self, booking_id: u64, id: &DocumentId) -> Result<(), DocumentError> {
+        let mut attempt = 1;
+        loop {
+            match self.documents.delete_document(booking_id, id).await {
+                Err(e) if attempt < DOCUMENT_DELETE_ATTEMPTS => {
+                    tracing::warn!("Retrying deletion of document {} of booking {}: {}", id, booking_id, e);
+                    tokio::time::sleep(DOCUMENT_DELETE_BACKOFF * attempt).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Release the slot hold of `booking_id`. A hold that can't be released expires on its own,
+    /// so failures are only logged.
+    async fn release_hold(&self, booking_id: u64) {
+        if let Err(e) = self.bookings.release_hold(booking_id).await {
+            tracing::error!("Failed to release hold for {}: {:?}", booking_id, e);
         }
     }
 }
 
-impl<R, M, N, P> TransactionService for Service<R, M, N, P>
+impl<R, P, B, D, V> TransactionService for Service<R, P, B, D, V>
 where
     R: TransactionRepository,
-    M: TransactionMetrics,
-    N: TransactionNotifier,
     P: PaymentService,
+    B: BookingRepository
+        + OutboxRepository
+        + SlotHoldRepository
+        + VerificationRepository
+        + DataRequestRepository
+        + AdminKeyRepository,
+    D: DocumentStore,
+    V: VerificationSender,
 {
-    /// Create the [Transaction] specified in `req` and trigger notifications.
+    /// Create the [Transaction] specified in `req` and queue its notifications.
     ///
     /// # Errors
     ///
     /// - Propagates any [CreateTransactionError] returned by the [TransactionRepository].
     async fn create_transaction(&self, booking_id: u64, payment: &RazorpayPayment) -> Result<Transaction, CreateTransactionError> {
         let result = self.repo.create_transaction(booking_id, payment).await;
-        if result.is_err() {
-            self.metrics.record_transaction_creation_failure().await;
-        } else {
-            self.metrics.record_transaction_creation_success().await;
-            self.transaction_notifier
-                .transaction_created(result.as_ref().unwrap())
-                .await;
+        match &result {
+            Err(e) => {
+                let reason = e.to_string();
+                self.events.publish(TransactionEvent::TransactionCreationFailed { booking_id, reason }).await;
+            }
+            Ok(transaction) => {
+                self.events.publish(TransactionEvent::TransactionCreated { booking_id }).await;
+                self.record_reservation(transaction, payment).await;
+            }
         }
 
         result
     }
 
     async fn create_payment_link(&self, req: &CreateTransactionRequest) -> Result<String, CreateTransactionError> {
+        self.check_verified(req)?;
+        self.age_policy.check(req.car_id(), req.age().value())?;
+
         let result = self.repo.check_if_car_available(req).await;
         match result {
             Ok(tx) => {
+                // Hold the slot until the payment link expires, so that nobody else is sent a
+                // link for a car that `reserve_car` would then refuse.
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs();
+                let expires_at = now + self.hold_duration.as_secs();
+                let hold = SlotHold::new(tx.booking_id, tx.car_id, req.email().clone(), req.start_time(), req.end_time(), expires_at);
+                self.bookings.place_hold(&hold, now).await?;
+                self.availability.invalidate(tx.car_id);
+
+                // The canister may leave out the customer; the caller is the principal that signed in.
+                let booking = Booking::awaiting_payment(req.transaction(tx.booking_id), tx.total_amount)
+                    .with_customer_principal(Some(req.caller().to_text()));
+                if let Err(e) = self.bookings.save_booking(&booking, &[]).await {
+                    self.release_hold(tx.booking_id).await;
+                    return Err(e.into());
+                }
+
                 // total amount + 2.36 % razorpay fee and taxes
-                self.payment_service.create_payment_link(tx.total_amount * 1.0236, tx.booking_id).await.map_err(|f| CreateTransactionError::Unknown(anyhow!("Failed to generate the payment link {f}")))
+                let amount = tx.total_amount * 1.0236;
+                let payment_link = match self.payment_service.create_payment_link(amount, tx.booking_id, expires_at).await {
+                    Ok(payment_link) => payment_link,
+                    Err(f) => {
+                        self.release_hold(tx.booking_id).await;
+                        // The customer can't pay for the booking, so it will never be reserved.
+                        let cancelled = BookingEvent::BookingCancelled { booking_id: tx.booking_id, car_id: tx.car_id };
+                        if let Err(e) = self.bookings.save_booking(&booking.cancelled(), &[cancelled]).await {
+                            tracing::error!("Failed to cancel booking {}: {:?}", tx.booking_id, e);
+                        }
+                        let reason = format!("Failed to generate the payment link {f}");
+                        self.events.publish(TransactionEvent::PaymentLinkCreationFailed { booking_id: tx.booking_id, reason: reason.clone() }).await;
+                        return Err(CreateTransactionError::Unknown(anyhow!(reason)));
+                    }
+                };
+
+                let event = BookingEvent::PaymentLinkCreated {
+                    booking_id: tx.booking_id,
+                    car_id: tx.car_id,
+                    payment_link: payment_link.clone(),
+                    amount,
+                };
+                self.bookings.save_booking(&booking.with_payment_link(payment_link.clone()), &[event]).await?;
+                Ok(payment_link)
             },
             Err(e) => Err(e),
         }
@@ -85,4 +515,270 @@ where
     async fn get_principal(&self) -> Result<String, CreateTransactionError> {
         self.repo.get_principal().await
     }
+
+    async fn find_booking(&self, booking_id: u64) -> Result<Option<Booking>, BookingRepositoryError> {
+        self.bookings.find_booking(booking_id).await
+    }
+
+    async fn cancel_booking(&self, booking_id: u64) -> Result<Booking, CancelBookingError> {
+        let booking = self.bookings.find_booking(booking_id).await?.ok_or(CancelBookingError::NotFound { booking_id })?;
+        match booking.status() {
+            BookingStatus::Reserved => return Err(CancelBookingError::AlreadyReserved { booking_id }),
+            BookingStatus::Cancelled => return Ok(booking),
+            BookingStatus::AwaitingPayment => {}
+        }
+
+        let car_id = booking.transaction().car_id();
+        let booking = booking.cancelled();
+        self.bookings.save_booking(&booking, &[BookingEvent::BookingCancelled { booking_id, car_id }]).await?;
+        self.release_hold(booking_id).await;
+        self.availability.invalidate(car_id);
+        Ok(booking)
+    }
+
+    async fn car_availability(&self, car_id: u64, from: u64, to: u64) -> Result<Availability, AvailabilityError> {
+        let range = AvailabilityRange::new(from, to)?;
+        let booked = match self.availability.get(car_id, &range) {
+            Some(booked) => booked,
+            None => {
+                let booked = self.repo.booked_intervals(car_id, &range).await?;
+                self.availability.insert(car_id, &range, &booked);
+                booked
+            }
+        };
+
+        // Holds come and go with every payment link, and are cheap to read, so they aren't cached.
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs();
+        let held = self.bookings.held_intervals(car_id, &range, now).await?;
+        Ok(Availability::from_slots(car_id, &range, booked, held))
+    }
+
+    async fn dead_letters(&self) -> Result<Vec<OutboxEvent>, OutboxError> {
+        Ok(self.bookings.dead_lettered_outbox_events().await?)
+    }
+
+    async fn replay_dead_letter(&self, id: u64) -> Result<OutboxEvent, OutboxError> {
+        let event = self.bookings.find_outbox_event(id).await?.ok_or(OutboxError::NotFound { id })?;
+        if event.status() != OutboxStatus::DeadLettered {
+            return Err(OutboxError::NotDeadLettered { id });
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs();
+        let event = event.replayed(now);
+        self.bookings.update_outbox_event(&event).await?;
+        tracing::info!("Replaying dead-lettered outbox event {} ({})", id, event.event().name());
+        Ok(event)
+    }
+
+    async fn upload_document(
+        &self,
+        booking_id: u64,
+        kind: DocumentKind,
+        declared_content_type: Option<String>,
+        contents: Vec<u8>,
+    ) -> Result<Document, DocumentError> {
+        let document = Document::new(booking_id, kind, declared_content_type.as_deref(), &contents)?;
+        let booking = self.bookings.find_booking(booking_id).await.map_err(|e| anyhow!(e))?;
+        if booking.is_none() {
+            return Err(DocumentError::BookingNotFound { booking_id });
+        }
+
+        let sealed = self.document_cipher.seal(&document, &contents)?;
+        self.documents.put_document(booking_id, document.id(), sealed).await?;
+        let document_id = document.id().to_string();
+        self.events.publish(TransactionEvent::DocumentUploaded { booking_id, document_id }).await;
+        Ok(document)
+    }
+
+    async fn get_document(&self, booking_id: u64, document_id: &str) -> Result<(Document, Vec<u8>), DocumentError> {
+        let id = DocumentId::parse(document_id)?;
+        let sealed = self
+            .documents
+            .get_document(booking_id, &id)
+            .await?
+            .ok_or_else(|| DocumentError::NotFound { document_id: document_id.to_string() })?;
+        let contents = self.document_cipher.open(booking_id, &id, &sealed)?;
+        let document = Document::from_stored(booking_id, id, &contents)?;
+
+        let document_id = document.id().to_string();
+        self.events.publish(TransactionEvent::DocumentAccessed { booking_id, document_id }).await;
+        Ok((document, contents))
+    }
+
+    async fn send_verification(&self, contact: &Contact, client: IpAddr) -> Result<(), VerificationError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs();
+        // Count the send against the client first, so that one client can't send codes to any
+        // number of contacts by spreading them below the per-contact limit.
+        let client_limits = *self.verifier.client_limits();
+        let key = client_key(client);
+        let challenge_key = key.clone();
+        self.bookings
+            .issue_otp(&key, move |previous| OtpChallenge::issue(previous, challenge_key, String::new(), now, &client_limits))
+            .await?;
+
+        let code = self.verifier.generate_code();
+        let code_hash = self.verifier.hash_code(contact, &code);
+        let limits = *self.verifier.limits();
+        let key = contact.key();
+        let contact_key = key.clone();
+        self.bookings
+            .issue_otp(&key, move |previous| OtpChallenge::issue(previous, contact_key, code_hash, now, &limits))
+            .await?;
+
+        // The code counts towards the rate limit even if it could not be delivered.
+        self.verification_sender.send_otp(contact, &code).await.inspect_err(|e| {
+            tracing::warn!("Failed to send a verification code by {}: {}", contact.channel(), e);
+        })
+    }
+
+    async fn confirm_verification(&self, contact: &Contact, code: &str) -> Result<(VerificationToken, u64), VerificationError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_secs();
+        let key = contact.key();
+        let challenge = self.bookings.record_otp_attempt(&key).await?.ok_or(VerificationError::Expired)?;
+        self.verifier.check_code(&challenge, contact, code, now)?;
+        self.bookings.consume_otp(&key).await?;
+        Ok(self.verifier.issue_token(contact, now))
+    }
+
+    async fn export_customer_data(&self, subject: &DataSubject) -> Result<CustomerDataExport, DataRequestError> {
+        let result = self.collect_customer_data(subject).await;
+        let (outcome, reason, booking_ids) = match &result {
+            Ok(export) => {
+                tracing::info!(
+                    target: "audit",
+                    request = "export",
+                    subject = subject.kind(),
+                    bookings = export.bookings.len(),
+                    documents = export.documents.len(),
+                    outcome = "completed",
+                    "data subject request"
+                );
+                let booking_ids = export.bookings.iter().map(|booking| booking.booking_id).collect();
+                (DataRequestOutcome::Completed, None, booking_ids)
+            }
+            Err(e) => {
+                tracing::warn!(
+                    target: "audit",
+                    request = "export",
+                    subject = subject.kind(),
+                    outcome = "failed",
+                    reason = %e,
+                    "data subject request"
+                );
+                (DataRequestOutcome::Failed, Some(e.to_string()), Vec::new())
+            }
+        };
+        self.record_data_request(DataRequestKind::Export, Some(subject), outcome, reason, booking_ids).await;
+        result
+    }
+
+    async fn erase_customer_data(&self, subject: &DataSubject) -> Result<ErasureReport, DataRequestError> {
+        let result = self.erase_subject_data(subject).await;
+        let (outcome, reason, booking_ids) = match &result {
+            Ok(report) => {
+                tracing::info!(
+                    target: "audit",
+                    request = "erasure",
+                    subject = subject.kind(),
+                    booking_ids = ?report.booking_ids,
+                    documents_deleted = report.documents_deleted,
+                    documents_not_deleted = ?report.documents_not_deleted,
+                    outcome = "completed",
+                    "data subject request"
+                );
+                (DataRequestOutcome::Completed, None, report.booking_ids.clone())
+            }
+            // Bookings erased before the failure stay erased; the request can be repeated for the rest.
+            Err(e) => {
+                tracing::warn!(
+                    target: "audit",
+                    request = "erasure",
+                    subject = subject.kind(),
+                    outcome = "failed",
+                    reason = %e,
+                    "data subject request"
+                );
+                (DataRequestOutcome::Failed, Some(e.to_string()), Vec::new())
+            }
+        };
+        self.record_data_request(DataRequestKind::Erasure, Some(subject), outcome, reason, booking_ids).await;
+        result
+    }
+
+    async fn refuse_data_request(&self, request: DataRequestKind, reason: &str) {
+        tracing::warn!(
+            target: "audit",
+            request = request.as_str(),
+            outcome = "refused",
+            reason = reason,
+            "data subject request"
+        );
+        self.record_data_request(request, None, DataRequestOutcome::Refused, Some(reason.to_string()), Vec::new()).await;
+    }
+
+    async fn admin_keys(&self) -> Vec<AdminKeyInfo> {
+        self.repo.admin_keys().await
+    }
+
+    async fn rotate_admin_key(&self, pem: &str, passphrase: Option<&str>) -> Result<AdminKeyInfo, AdminKeyRotationError> {
+        let result = match AdminIdentity::from_pem(pem, passphrase) {
+            Ok(admin) => self.rotate_and_store(admin).await,
+            Err(e) => Err(e.into()),
+        };
+        match &result {
+            Ok(key) => tracing::info!(
+                target: "audit",
+                principal = %key.principal,
+                key_type = %key.key_type,
+                outcome = "completed",
+                "admin key rotation"
+            ),
+            Err(e) => tracing::warn!(
+                target: "audit",
+                outcome = "failed",
+                reason = %e,
+                "admin key rotation"
+            ),
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(from: u64, to: u64) -> AvailabilityRange {
+        AvailabilityRange::new(from, to).unwrap()
+    }
+
+    #[test]
+    fn test_availability_is_served_within_the_cached_range() {
+        let cache = AvailabilityCache::default();
+        cache.insert(7, &range(100, 1000), &[Interval { start: 200, end: 300 }]);
+
+        assert_eq!(cache.get(7, &range(150, 400)), Some(vec![Interval { start: 200, end: 300 }]));
+        assert_eq!(cache.get(7, &range(50, 400)), None);
+        assert_eq!(cache.get(8, &range(150, 400)), None);
+
+        cache.invalidate(7);
+        assert_eq!(cache.get(7, &range(150, 400)), None);
+    }
+
+    #[test]
+    fn test_cache_keeps_one_range_per_car_and_a_bounded_number_of_cars() {
+        let cache = AvailabilityCache::default();
+        for from in 0..10 {
+            cache.insert(7, &range(from, from + 100), &[]);
+        }
+        // Car 7 is the one fetched longest ago
+        std::thread::sleep(Duration::from_millis(1));
+        for car_id in 0..AVAILABILITY_CACHE_CARS as u64 + 10 {
+            cache.insert(car_id + 100, &range(0, 100), &[]);
+        }
+
+        let cached = cache.0.lock().unwrap();
+        assert_eq!(cached.len(), AVAILABILITY_CACHE_CARS);
+        assert!(!cached.contains_key(&7));
+    }
 }
@@ -1,10 +1,69 @@
-use std::time::{self, Duration, SystemTime};
+use std::time::{Duration, SystemTime};
 
+use candid::Principal;
 use ic_agent::{identity::{DelegatedIdentity, Delegation, Secp256k1Identity, SignedDelegation}, Identity};
-use k256::elliptic_curve::{rand_core::OsRng, JwkEcKey};
+use k256::elliptic_curve::{rand_core::OsRng, sec1::ToEncodedPoint, JwkEcKey};
+use pkcs8::{ObjectIdentifier, SubjectPublicKeyInfoRef};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-pub const DELEGATION_MAX_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 7);
+use crate::domain::transactions::models::transaction::ErrorCode;
+
+/// How long the delegations the server signs for itself last, unless configured otherwise.
+pub const DEFAULT_DELEGATION_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+/// Delegations are renewed when less than this is left, which covers the ingress expiry of a
+/// call made just before.
+pub const DELEGATION_RENEWAL_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// The longest delegation chain the Internet Computer accepts.
+pub const MAX_DELEGATION_CHAIN_LENGTH: usize = 20;
+
+/// DER headers of the public keys a delegation can be signed with, followed by the raw key.
+pub(crate) const ED25519_DER_PREFIX: [u8; 12] = [0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00];
+const SECP256K1_DER_PREFIX: [u8; 23] = [
+    0x30, 0x56, 0x30, 0x10, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x05, 0x2b, 0x81, 0x04, 0x00,
+    0x0a, 0x03, 0x42, 0x00,
+];
+const PRIME256V1_DER_PREFIX: [u8; 26] = [
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce,
+    0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+/// Algorithm of canister signature keys, used by Internet Identity.
+const CANISTER_SIGNATURE_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.4.1.56387.1.2");
+
+/// Why a [DelegatedIdentityWire] was not accepted.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum DelegationError {
+    #[error("the delegation chain is empty")]
+    EmptyChain,
+    #[error("the delegation chain has {0} delegations, at most {MAX_DELEGATION_CHAIN_LENGTH} are allowed")]
+    ChainTooLong(usize),
+    #[error("delegation {0} has expired")]
+    Expired(usize),
+    #[error("delegation {0} does not allow calls to this service")]
+    TargetNotAllowed(usize),
+    #[error("delegation {0} is signed with an unsupported key type")]
+    UnsupportedKey(usize),
+    #[error("delegation {0} has an invalid signature")]
+    InvalidSignature(usize),
+    #[error("the session key is invalid")]
+    InvalidSessionKey,
+    #[error("the session key is not the one the delegation chain delegates to")]
+    SessionKeyMismatch,
+}
+
+impl ErrorCode for DelegationError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::EmptyChain | Self::ChainTooLong(_) => "delegation_chain_invalid",
+            Self::Expired(_) => "delegation_expired",
+            Self::TargetNotAllowed(_) => "delegation_target_not_allowed",
+            Self::UnsupportedKey(_) | Self::InvalidSignature(_) => "delegation_signature_invalid",
+            Self::InvalidSessionKey | Self::SessionKeyMismatch => "delegation_session_key_invalid",
+        }
+    }
+}
 
 
 /// Delegated identity that can be serialized over the wire
@@ -21,17 +80,19 @@ pub struct DelegatedIdentityWire {
 }
 
 impl DelegatedIdentityWire {
-    fn delegate_with_max_age(from: &impl Identity, max_age: Duration) -> Self {
+    /// Delegate from `from` to a fresh session key for `lifetime`, for calls to the `target`
+    /// canister only.
+    pub fn delegate(from: &(impl Identity + ?Sized), target: Principal, lifetime: Duration) -> Self {
         let to_secret = k256::SecretKey::random(&mut OsRng);
         let to_identity = Secp256k1Identity::from_private_key(to_secret.clone());
         let current_epoch = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH).unwrap();
-        let expiry =  current_epoch + max_age;
+        let expiry =  current_epoch + lifetime;
         let expiry_ns = expiry.as_nanos() as u64;
         let delegation = Delegation {
             pubkey: to_identity.public_key().unwrap(),
             expiration: expiry_ns,
-            targets: None,
+            targets: Some(vec![target]),
         };
         let sig = from.sign_delegation(&delegation).unwrap();
         let signed_delegation = SignedDelegation {
@@ -49,16 +110,139 @@ impl DelegatedIdentityWire {
         }
     }
 
-    pub fn delegate(from: &impl Identity) -> Self {
-        Self::delegate_with_max_age(from, DELEGATION_MAX_AGE)
+    /// A chain of one delegation from the Ed25519 `root` to a fresh session key, unrestricted
+    /// and expiring at `expiration`, for tests that need an identity that verifies.
+    #[cfg(test)]
+    pub(crate) fn signed_by(root: &ed25519_consensus::SigningKey, expiration: u64) -> Self {
+        let session = k256::SecretKey::random(&mut OsRng);
+        let delegation = Delegation { pubkey: secp256k1_der(&session.public_key()), expiration, targets: None };
+        let signature = root.sign(&delegation.signable()).to_bytes().to_vec();
+
+        Self {
+            from_key: [ED25519_DER_PREFIX.as_slice(), root.verification_key().as_bytes()].concat(),
+            to_secret: session.to_jwk(),
+            delegation_chain: vec![SignedDelegation { delegation, signature }],
+        }
     }
 
-    pub fn delegate_short_lived_identity(from: &impl Identity) -> Self {
-        let max_age = time::Duration::from_secs(24 * 60 * 60); // 1 day
-        Self::delegate_with_max_age(from, max_age)
+    /// Check that the chain delegates from `from_key` to `to_secret`, that every delegation is
+    /// signed by the key before it, unexpired at `now_ns` and allows calls to `target`.
+    /// Returns the principal the canister will see as the caller.
+    ///
+    /// The root key may be an Internet Identity canister signature key; its signature needs the
+    /// subnet's certificate to verify, so it is left to the Internet Computer, which checks the
+    /// whole chain again on every call made with the identity.
+    pub fn verify(&self, target: Principal, now_ns: u64) -> Result<Principal, DelegationError> {
+        if self.delegation_chain.is_empty() {
+            return Err(DelegationError::EmptyChain);
+        }
+        let signer = verify_chain(&self.from_key, &self.delegation_chain, target, now_ns, true)?;
+
+        let session_key = k256::SecretKey::from_jwk(&self.to_secret).map_err(|_| DelegationError::InvalidSessionKey)?;
+        if signer != secp256k1_der(&session_key.public_key()) {
+            return Err(DelegationError::SessionKeyMismatch);
+        }
+
+        Ok(Principal::self_authenticating(&self.from_key))
     }
 }
 
+/// Check that `signature` over `message` was made by the identity with the DER encoded
+/// `public_key`, either directly or by the last key of `delegation_chain`, whose delegations must
+/// be unexpired at `now_ns` and allow calls to `target`. Returns the identity's principal.
+///
+/// Unlike [DelegatedIdentityWire::verify], nothing checks the result again, so canister signature
+/// keys are rejected. A bad `signature` is reported as the signature after the last delegation.
+pub fn verify_signed_message(
+    public_key: &[u8],
+    delegation_chain: &[SignedDelegation],
+    message: &[u8],
+    signature: &[u8],
+    target: Principal,
+    now_ns: u64,
+) -> Result<Principal, DelegationError> {
+    let signer = verify_chain(public_key, delegation_chain, target, now_ns, false)?;
+    verify_signature(delegation_chain.len(), signer, message, signature)?;
+    Ok(Principal::self_authenticating(public_key))
+}
+
+/// Walk `chain` from `root`, returning the key it ends at. The signature of a canister signature
+/// `root` is skipped when `trust_canister_signatures`, and rejected otherwise.
+fn verify_chain<'a>(
+    root: &'a [u8],
+    chain: &'a [SignedDelegation],
+    target: Principal,
+    now_ns: u64,
+    trust_canister_signatures: bool,
+) -> Result<&'a [u8], DelegationError> {
+    if chain.len() > MAX_DELEGATION_CHAIN_LENGTH {
+        return Err(DelegationError::ChainTooLong(chain.len()));
+    }
+
+    let mut signer = root;
+    for (index, signed) in chain.iter().enumerate() {
+        let delegation = &signed.delegation;
+        if delegation.expiration <= now_ns {
+            return Err(DelegationError::Expired(index));
+        }
+        if delegation.targets.as_ref().is_some_and(|targets| !targets.contains(&target)) {
+            return Err(DelegationError::TargetNotAllowed(index));
+        }
+        if !(index == 0 && trust_canister_signatures && is_canister_signature_key(signer)) {
+            verify_signature(index, signer, &delegation.signable(), &signed.signature)?;
+        }
+        signer = &delegation.pubkey;
+    }
+    Ok(signer)
+}
+
+/// Whether `key` is a DER SubjectPublicKeyInfo whose algorithm is [CANISTER_SIGNATURE_OID].
+fn is_canister_signature_key(key: &[u8]) -> bool {
+    SubjectPublicKeyInfoRef::try_from(key).is_ok_and(|info| info.algorithm.oid == CANISTER_SIGNATURE_OID)
+}
+
+/// The DER encoding of `key`, as used for delegation public keys.
+fn secp256k1_der(key: &k256::PublicKey) -> Vec<u8> {
+    [SECP256K1_DER_PREFIX.as_slice(), key.to_encoded_point(false).as_bytes()].concat()
+}
+
+/// Verify the `signature` of delegation `index` over `message` with the DER encoded `key`.
+fn verify_signature(index: usize, key: &[u8], message: &[u8], signature: &[u8]) -> Result<(), DelegationError> {
+    let verified = if let Some(key) = key.strip_prefix(ED25519_DER_PREFIX.as_slice()) {
+        verify_ed25519(key, message, signature)
+    } else if let Some(key) = key.strip_prefix(SECP256K1_DER_PREFIX.as_slice()) {
+        verify_secp256k1(key, message, signature)
+    } else if let Some(key) = key.strip_prefix(PRIME256V1_DER_PREFIX.as_slice()) {
+        verify_prime256v1(key, message, signature)
+    } else {
+        return Err(DelegationError::UnsupportedKey(index));
+    };
+    verified.ok_or(DelegationError::InvalidSignature(index))
+}
+
+fn verify_ed25519(key: &[u8], message: &[u8], signature: &[u8]) -> Option<()> {
+    let key = ed25519_consensus::VerificationKey::try_from(<[u8; 32]>::try_from(key).ok()?).ok()?;
+    let signature = ed25519_consensus::Signature::from(<[u8; 64]>::try_from(signature).ok()?);
+    key.verify(&signature, message).ok()
+}
+
+/// ECDSA signatures over the SHA-256 digest of `message`, as 64 bytes of `r || s`.
+fn verify_secp256k1(key: &[u8], message: &[u8], signature: &[u8]) -> Option<()> {
+    use k256::ecdsa::signature::Verifier;
+
+    let key = k256::ecdsa::VerifyingKey::from_sec1_bytes(key).ok()?;
+    let signature = k256::ecdsa::Signature::from_slice(signature).ok()?;
+    key.verify(message, &signature).ok()
+}
+
+fn verify_prime256v1(key: &[u8], message: &[u8], signature: &[u8]) -> Option<()> {
+    use p256::ecdsa::signature::Verifier;
+
+    let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(key).ok()?;
+    let signature = p256::ecdsa::Signature::from_slice(signature).ok()?;
+    key.verify(message, &signature).ok()
+}
+
 impl std::fmt::Debug for DelegatedIdentityWire {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("DelegatedIdentityWire").finish()
@@ -78,4 +262,136 @@ impl TryFrom<DelegatedIdentityWire> for DelegatedIdentity {
             identity.delegation_chain,
         ))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use k256::ecdsa::signature::Signer;
+
+    use super::*;
+
+    const NOW: u64 = 1_734_556_800_000_000_000;
+    const HOUR: u64 = 3_600_000_000_000;
+
+    fn backend() -> Principal {
+        Principal::from_slice(&[0, 0, 0, 0, 1, 48, 14, 239, 1, 1])
+    }
+
+    fn ed25519_der(key: &ed25519_consensus::SigningKey) -> Vec<u8> {
+        [ED25519_DER_PREFIX.as_slice(), key.verification_key().as_bytes()].concat()
+    }
+
+    fn delegation(pubkey: Vec<u8>, expiration: u64, targets: Option<Vec<Principal>>) -> Delegation {
+        Delegation { pubkey, expiration, targets }
+    }
+
+    /// A chain from an Ed25519 root key, through an intermediate secp256k1 key, to a session key.
+    fn wire(expiration: u64, targets: Option<Vec<Principal>>) -> DelegatedIdentityWire {
+        let root = ed25519_consensus::SigningKey::from([7; 32]);
+        let intermediate = k256::SecretKey::random(&mut OsRng);
+        let session = k256::SecretKey::random(&mut OsRng);
+
+        let first = delegation(secp256k1_der(&intermediate.public_key()), expiration, None);
+        let first_signature = root.sign(&first.signable()).to_bytes().to_vec();
+        let second = delegation(secp256k1_der(&session.public_key()), expiration, targets);
+        let second_signature: k256::ecdsa::Signature =
+            k256::ecdsa::SigningKey::from(&intermediate).sign(&second.signable());
+
+        DelegatedIdentityWire {
+            from_key: ed25519_der(&root),
+            to_secret: session.to_jwk(),
+            delegation_chain: vec![
+                SignedDelegation { delegation: first, signature: first_signature },
+                SignedDelegation { delegation: second, signature: second_signature.to_vec() },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_valid_chain_is_accepted_for_the_root_principal() {
+        let wire = wire(NOW + HOUR, Some(vec![backend()]));
+        let root = ed25519_consensus::SigningKey::from([7; 32]);
+
+        assert_eq!(wire.verify(backend(), NOW), Ok(Principal::self_authenticating(ed25519_der(&root))));
+    }
+
+    #[test]
+    fn test_signed_message_is_verified_through_the_chain() {
+        let root = ed25519_consensus::SigningKey::from([7; 32]);
+        let direct = root.sign(b"nonce").to_bytes();
+        let verify = |key: &[u8], chain: &[SignedDelegation], message: &[u8], signature: &[u8]| {
+            verify_signed_message(key, chain, message, signature, backend(), NOW)
+        };
+        assert_eq!(verify(&ed25519_der(&root), &[], b"nonce", &direct), Ok(Principal::self_authenticating(ed25519_der(&root))));
+        assert_eq!(verify(&ed25519_der(&root), &[], b"other", &direct), Err(DelegationError::InvalidSignature(0)));
+
+        let wire = wire(NOW + HOUR, None);
+        let session = k256::SecretKey::from_jwk(&wire.to_secret).unwrap();
+        let signature: k256::ecdsa::Signature = k256::ecdsa::SigningKey::from(&session).sign(b"nonce");
+        assert!(verify(&wire.from_key, &wire.delegation_chain, b"nonce", &signature.to_vec()).is_ok());
+        assert_eq!(
+            verify(&wire.from_key, &wire.delegation_chain, b"nonce", &direct),
+            Err(DelegationError::InvalidSignature(2))
+        );
+    }
+
+    #[test]
+    fn test_prime256v1_root_is_accepted() {
+        let root = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let session = k256::SecretKey::random(&mut OsRng);
+        let delegation = delegation(secp256k1_der(&session.public_key()), NOW + HOUR, None);
+        let signature: p256::ecdsa::Signature = root.sign(&delegation.signable());
+        let root_der = [PRIME256V1_DER_PREFIX.as_slice(), root.verifying_key().to_encoded_point(false).as_bytes()].concat();
+        let wire = DelegatedIdentityWire {
+            from_key: root_der,
+            to_secret: session.to_jwk(),
+            delegation_chain: vec![SignedDelegation { delegation, signature: signature.to_vec() }],
+        };
+
+        assert!(wire.verify(backend(), NOW).is_ok());
+    }
+
+    #[test]
+    fn test_expired_delegation_is_rejected() {
+        assert_eq!(wire(NOW, None).verify(backend(), NOW), Err(DelegationError::Expired(0)));
+    }
+
+    #[test]
+    fn test_delegation_for_other_canisters_is_rejected() {
+        let wire = wire(NOW + HOUR, Some(vec![Principal::anonymous()]));
+
+        assert_eq!(wire.verify(backend(), NOW), Err(DelegationError::TargetNotAllowed(1)));
+    }
+
+    #[test]
+    fn test_only_keys_of_the_canister_signature_algorithm_are_canister_signature_keys() {
+        let oid = [0x06, 0x0a, 0x2b, 0x06, 0x01, 0x04, 0x01, 0x83, 0xb8, 0x43, 0x01, 0x02];
+        let canister_key = [[0x30, 0x13, 0x30, 0x0c].as_slice(), &oid, &[0x03, 0x03, 0x00, 0xca, 0xfe]].concat();
+        assert!(is_canister_signature_key(&canister_key));
+
+        // An Ed25519 key whose bytes happen to contain the identifier.
+        let ed25519_key = [ED25519_DER_PREFIX.as_slice(), &oid, &[0; 20]].concat();
+        assert!(!is_canister_signature_key(&ed25519_key));
+        assert!(!is_canister_signature_key(&canister_key[..canister_key.len() - 1]));
+    }
+
+    #[test]
+    fn test_tampered_chain_is_rejected() {
+        let mut forged = wire(NOW + HOUR, None);
+        forged.delegation_chain[1].delegation.expiration += HOUR;
+        forged.delegation_chain[0].signature[0] ^= 1;
+        assert_eq!(forged.verify(backend(), NOW), Err(DelegationError::InvalidSignature(0)));
+
+        let mut swapped = wire(NOW + HOUR, None);
+        swapped.to_secret = k256::SecretKey::random(&mut OsRng).to_jwk();
+        assert_eq!(swapped.verify(backend(), NOW), Err(DelegationError::SessionKeyMismatch));
+
+        let mut unknown = wire(NOW + HOUR, None);
+        unknown.from_key = vec![0x30, 0x00];
+        assert_eq!(unknown.verify(backend(), NOW), Err(DelegationError::UnsupportedKey(0)));
+
+        let mut empty = wire(NOW + HOUR, None);
+        empty.delegation_chain.clear();
+        assert_eq!(empty.verify(backend(), NOW), Err(DelegationError::EmptyChain));
+    }
+}
@@ -1,41 +1,131 @@
-use std::{env, sync::Arc};
+use std::sync::Arc;
+use std::time::Duration;
 
+use anyhow::Context;
 use candid::Principal;
-use ic_agent::{agent::AgentBuilder, Agent, Identity};
+use ic_agent::{agent::AgentBuilder, Agent, AgentError, Identity};
+
+use crate::canister;
 
 const LIVE_AGENT_URL: &str = "https://ic0.app";
 
 const LOCAL_AGENT_URL: &str = "http://localhost:4943";
 
-#[derive(Clone)]
-pub struct AgentWrapper(Agent);
+/// The Internet Computer the service talks to, mainnet or a local dfx or PocketIC replica, and
+/// the backend canister on it.
+///
+/// Every agent built for the network shares one HTTP client, and with it the connection pool,
+/// so a new agent per caller costs no TCP or TLS handshake.
+#[derive(Clone, Debug)]
+pub struct IcNetwork {
+    url: String,
+    local: bool,
+    backend_id: Principal,
+    http_client: reqwest::Client,
+    /// The root key of a local replica, which makes up a new one every time it starts. Agents
+    /// verify mainnet's responses with the key built into them.
+    root_key: Option<Vec<u8>>,
+    /// How long a signed message may wait to be accepted by the replica, or the agent's default.
+    ingress_expiry: Option<Duration>,
+}
 
-impl AgentWrapper {
-    pub  fn build(builder_func: impl FnOnce(AgentBuilder) -> AgentBuilder) -> Self {
+impl IcNetwork {
+    /// Mainnet, with the backend canister listed under `ic` in `did/canister_ids.json`.
+    pub fn live() -> Self {
+        Self {
+            url: LIVE_AGENT_URL.to_string(),
+            local: false,
+            backend_id: canister::ic::BACKEND_ID,
+            http_client: reqwest::Client::new(),
+            root_key: None,
+            ingress_expiry: None,
+        }
+    }
+
+    /// A local replica, with the backend canister listed under `local` in
+    /// `did/canister_ids.json`.
+    pub fn local() -> Self {
+        Self {
+            url: LOCAL_AGENT_URL.to_string(),
+            local: true,
+            backend_id: canister::local::BACKEND_ID,
+            http_client: reqwest::Client::new(),
+            root_key: None,
+            ingress_expiry: None,
+        }
+    }
+
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = url.to_string();
+        self
+    }
+
+    pub fn with_backend_id(mut self, backend_id: Principal) -> Self {
+        self.backend_id = backend_id;
+        self
+    }
+
+    /// Sign messages to expire after `ingress_expiry`. Must not exceed
+    /// [DELEGATION_RENEWAL_MARGIN](crate::identity::delegated_identity::DELEGATION_RENEWAL_MARGIN),
+    /// so no message outlives the delegation it was signed under.
+    pub fn with_ingress_expiry(mut self, ingress_expiry: Duration) -> Self {
+        self.ingress_expiry = Some(ingress_expiry);
+        self
+    }
 
-        // dotenv::dotenv().ok();
+    // Getter for url
+    pub fn url(&self) -> &str {
+        &self.url
+    }
 
-        // let live = env::var("BACKEND").unwrap_or("LIVE".to_string()) == "LIVE" ;
+    // Getter for backend_id
+    pub fn backend_id(&self) -> Principal {
+        self.backend_id
+    }
 
-        let live = true;
+    pub fn is_local(&self) -> bool {
+        self.local
+    }
 
-        // let url = "https://ic0.app";
-        let url = if live {
-            LIVE_AGENT_URL
-        } else {
-            LOCAL_AGENT_URL
-        };
+    /// Fetch the root key of a local replica, which every agent built for this network then
+    /// trusts. Does nothing on mainnet, whose key must never be taken from the network.
+    pub async fn fetch_root_key(&mut self) -> anyhow::Result<()> {
+        if !self.local {
+            return Ok(());
+        }
+        let agent = Agent::builder().with_url(self.url.as_str()).with_http_client(self.http_client.clone()).build()?;
+        agent
+            .fetch_root_key()
+            .await
+            .with_context(|| format!("Failed to fetch the root key of the replica at {}", self.url))?;
+        self.root_key = Some(agent.read_root_key());
+        Ok(())
+    }
+}
 
-        let mut builder =  Agent::builder().with_url(url);
+#[derive(Clone)]
+pub struct AgentWrapper(Agent);
+
+impl AgentWrapper {
+    /// # Errors
+    ///
+    /// - [AgentError] if the agent can't be built, e.g. because the network's URL is invalid.
+    pub fn build(network: &IcNetwork, builder_func: impl FnOnce(AgentBuilder) -> AgentBuilder) -> Result<Self, AgentError> {
+        let mut builder = Agent::builder()
+            .with_url(network.url())
+            .with_http_client(network.http_client.clone())
+            .with_ingress_expiry(network.ingress_expiry);
         builder = builder_func(builder);
-        let agent  =builder.build().unwrap();
-        
-        Self(agent)
+        let agent = builder.build()?;
+        if let Some(root_key) = &network.root_key {
+            agent.set_root_key(root_key.clone());
+        }
+
+        Ok(Self(agent))
     }
 
     pub fn get_agent(&self) -> &Agent {
-        let agent = &self.0;
-        agent
+        &self.0
     }
 
     pub fn set_arc_id(&mut self, id: Arc<impl Identity + 'static>) {
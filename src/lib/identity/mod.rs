@@ -1,3 +1,4 @@
 pub mod ic;
 pub mod delegated_identity;
-pub mod identity;
\ No newline at end of file
+pub mod session;
+pub mod admin;
\ No newline at end of file
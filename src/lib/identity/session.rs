@@ -0,0 +1,288 @@
+use std::fmt::Formatter;
+use std::time::Duration;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use candid::Principal;
+use hmac::{Hmac, Mac};
+use ic_agent::identity::SignedDelegation;
+use k256::elliptic_curve::rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use thiserror::Error;
+
+use super::delegated_identity::{verify_signed_message, DelegationError};
+
+/// How long a sign-in nonce can be signed and exchanged for a session.
+pub const SESSION_NONCE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// How long a session token is accepted, unless configured otherwise.
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Every nonce starts with this label, so that a signature over one can't be mistaken for a
+/// signature over anything else.
+const NONCE_LABEL: &str = "fueldao-session";
+
+/// The only JOSE header session tokens are issued with, and the only one accepted.
+const TOKEN_HEADER: &str = r#"{"alg":"HS256","typ":"JWT"}"#;
+
+/// A nonce for a client to sign with its identity, proving that it controls the principal.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SessionNonce {
+    pub nonce: String,
+    pub expires_at: u64,
+}
+
+/// A bearer token standing in for the customer's identity on later requests.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SessionToken {
+    pub token: String,
+    pub principal: String,
+    pub expires_at: u64,
+}
+
+/// The claims of a valid session token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Session {
+    principal: Principal,
+    expires_at: u64,
+}
+
+impl Session {
+    // Getter for principal
+    pub fn principal(&self) -> Principal {
+        self.principal
+    }
+
+    // Getter for expires_at
+    pub fn expires_at(&self) -> u64 {
+        self.expires_at
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    iat: u64,
+    exp: u64,
+}
+
+/// Issues sign-in nonces and session tokens signed with the deployment's secret.
+///
+/// Both are stateless: nonces are `fueldao-session.<expires_at>.<random>.<signature>` and
+/// tokens are HS256 JWTs, so every instance sharing the secret accepts them without a lookup.
+#[derive(Clone)]
+pub struct SessionIssuer {
+    secret: Vec<u8>,
+    ttl: Duration,
+}
+
+impl std::fmt::Debug for SessionIssuer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionIssuer").field("ttl", &self.ttl).finish_non_exhaustive()
+    }
+}
+
+impl SessionIssuer {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            secret: secret.to_vec(),
+            ttl: DEFAULT_SESSION_TTL,
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    // Getter for ttl
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn issue_nonce(&self, now: u64) -> SessionNonce {
+        let mut random = [0u8; 16];
+        OsRng.fill_bytes(&mut random);
+        let expires_at = now + SESSION_NONCE_TTL.as_secs();
+        let unsigned = format!("{NONCE_LABEL}.{expires_at}.{}", hex(&random));
+        let signature = hex(&self.mac(b"nonce|", unsigned.as_bytes()).finalize().into_bytes());
+        SessionNonce { nonce: format!("{unsigned}.{signature}"), expires_at }
+    }
+
+    /// Exchange a signature over a nonce from [SessionIssuer::issue_nonce] for a session token.
+    ///
+    /// `signature` is made over the bytes of `nonce` by the identity with the DER encoded
+    /// `public_key`, or by the last key of its `delegation_chain`, whose delegations must allow
+    /// calls to `target`.
+    pub fn sign_in(
+        &self,
+        nonce: &str,
+        public_key: &[u8],
+        delegation_chain: &[SignedDelegation],
+        signature: &[u8],
+        target: Principal,
+        now: u64,
+    ) -> Result<SessionToken, SessionError> {
+        self.check_nonce(nonce, now)?;
+        let now_ns = now.saturating_mul(1_000_000_000);
+        let principal =
+            verify_signed_message(public_key, delegation_chain, nonce.as_bytes(), signature, target, now_ns)?;
+        Ok(self.issue_token(principal, now))
+    }
+
+    fn check_nonce(&self, nonce: &str, now: u64) -> Result<(), SessionError> {
+        let (unsigned, signature) = nonce.rsplit_once('.').ok_or(SessionError::InvalidNonce)?;
+        let signature = unhex(signature).ok_or(SessionError::InvalidNonce)?;
+        self.mac(b"nonce|", unsigned.as_bytes())
+            .verify_slice(&signature)
+            .map_err(|_| SessionError::InvalidNonce)?;
+
+        let expires_at = unsigned
+            .strip_prefix(NONCE_LABEL)
+            .and_then(|rest| rest.split('.').nth(1))
+            .and_then(|expires_at| expires_at.parse::<u64>().ok())
+            .ok_or(SessionError::InvalidNonce)?;
+        if now >= expires_at {
+            return Err(SessionError::NonceExpired);
+        }
+        Ok(())
+    }
+
+    pub fn issue_token(&self, principal: Principal, now: u64) -> SessionToken {
+        let expires_at = now + self.ttl.as_secs();
+        let claims = Claims { sub: principal.to_text(), iat: now, exp: expires_at };
+        let claims = serde_json::to_vec(&claims).expect("claims serialize to JSON");
+        let unsigned = format!("{}.{}", URL_SAFE_NO_PAD.encode(TOKEN_HEADER), URL_SAFE_NO_PAD.encode(claims));
+        let signature = self.mac(b"", unsigned.as_bytes()).finalize().into_bytes();
+        SessionToken {
+            token: format!("{unsigned}.{}", URL_SAFE_NO_PAD.encode(signature)),
+            principal: principal.to_text(),
+            expires_at,
+        }
+    }
+
+    /// The session `token` stands for, if it was issued by this deployment and has not expired
+    /// at `now`.
+    pub fn verify_token(&self, token: &str, now: u64) -> Result<Session, SessionError> {
+        let (unsigned, signature) = token.rsplit_once('.').ok_or(SessionError::InvalidToken)?;
+        let (header, claims) = unsigned.split_once('.').ok_or(SessionError::InvalidToken)?;
+        // Refuse any other algorithm, `none` in particular, before looking at the signature.
+        if URL_SAFE_NO_PAD.decode(header).ok().as_deref() != Some(TOKEN_HEADER.as_bytes()) {
+            return Err(SessionError::InvalidToken);
+        }
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| SessionError::InvalidToken)?;
+        self.mac(b"", unsigned.as_bytes())
+            .verify_slice(&signature)
+            .map_err(|_| SessionError::InvalidToken)?;
+
+        let claims: Claims = URL_SAFE_NO_PAD
+            .decode(claims)
+            .ok()
+            .and_then(|claims| serde_json::from_slice(&claims).ok())
+            .ok_or(SessionError::InvalidToken)?;
+        if now >= claims.exp {
+            return Err(SessionError::TokenExpired);
+        }
+        let principal = Principal::from_text(&claims.sub).map_err(|_| SessionError::InvalidToken)?;
+        Ok(Session { principal, expires_at: claims.exp })
+    }
+
+    fn mac(&self, domain: &[u8], message: &[u8]) -> Hmac<Sha256> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(domain);
+        mac.update(message);
+        mac
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    let digit = |c: u8| char::from(c).to_digit(16);
+    text.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => Some((digit(*high)? * 16 + digit(*low)?) as u8),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Errors that may occur while signing in or presenting a session token.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SessionError {
+    #[error("The sign-in nonce is invalid")]
+    InvalidNonce,
+
+    #[error("The sign-in nonce has expired, request a new one")]
+    NonceExpired,
+
+    #[error("The signature does not prove control of the principal: {0}")]
+    Delegation(#[from] DelegationError),
+
+    #[error("The session token is invalid")]
+    InvalidToken,
+
+    #[error("The session has expired, sign in again")]
+    TokenExpired,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::delegated_identity::ED25519_DER_PREFIX;
+
+    const NOW: u64 = 1_734_556_800;
+
+    fn issuer() -> SessionIssuer {
+        SessionIssuer::new(b"a session secret of at least 32 bytes")
+    }
+
+    fn sign_in(nonce: &str, key: &ed25519_consensus::SigningKey, now: u64) -> Result<SessionToken, SessionError> {
+        let public_key = [ED25519_DER_PREFIX.as_slice(), key.verification_key().as_bytes()].concat();
+        let signature = key.sign(nonce.as_bytes()).to_bytes();
+        issuer().sign_in(nonce, &public_key, &[], &signature, Principal::anonymous(), now)
+    }
+
+    #[test]
+    fn test_signed_nonce_is_exchanged_for_a_session() {
+        let key = ed25519_consensus::SigningKey::from([7; 32]);
+        let nonce = issuer().issue_nonce(NOW);
+        assert!(nonce.nonce.starts_with("fueldao-session."));
+
+        let token = sign_in(&nonce.nonce, &key, NOW + 1).unwrap();
+        let session = issuer().verify_token(&token.token, NOW + 1).unwrap();
+        assert_eq!(session.expires_at(), NOW + 1 + DEFAULT_SESSION_TTL.as_secs());
+        assert_eq!(issuer().verify_token(&token.token, session.expires_at()), Err(SessionError::TokenExpired));
+    }
+
+    #[test]
+    fn test_nonce_must_be_ours_and_unexpired() {
+        let key = ed25519_consensus::SigningKey::from([7; 32]);
+        let nonce = issuer().issue_nonce(NOW).nonce;
+
+        assert_eq!(sign_in(&nonce, &key, NOW + SESSION_NONCE_TTL.as_secs()), Err(SessionError::NonceExpired));
+        let forged = nonce.replacen(&(NOW + SESSION_NONCE_TTL.as_secs()).to_string(), &u64::MAX.to_string(), 1);
+        assert_eq!(sign_in(&forged, &key, NOW), Err(SessionError::InvalidNonce));
+        let other = SessionIssuer::new(b"another secret").issue_nonce(NOW).nonce;
+        assert_eq!(sign_in(&other, &key, NOW), Err(SessionError::InvalidNonce));
+    }
+
+    #[test]
+    fn test_tampered_tokens_are_rejected() {
+        let token = issuer().issue_token(Principal::anonymous(), NOW).token;
+        let (header, rest) = token.split_once('.').unwrap();
+
+        let unsigned = format!("{}.{rest}", URL_SAFE_NO_PAD.encode(r#"{"alg":"none","typ":"JWT"}"#));
+        assert_eq!(issuer().verify_token(&unsigned, NOW), Err(SessionError::InvalidToken));
+        let truncated = format!("{header}.{}", rest.split_once('.').unwrap().0);
+        assert_eq!(issuer().verify_token(&truncated, NOW), Err(SessionError::InvalidToken));
+        assert_eq!(
+            SessionIssuer::new(b"another secret").verify_token(&token, NOW),
+            Err(SessionError::InvalidToken)
+        );
+    }
+}
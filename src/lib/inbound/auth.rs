@@ -0,0 +1,88 @@
+/*!
+    Module `auth` provides extractors that authenticate callers of the HTTP API.
+*/
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+use candid::Principal;
+
+use super::handlers::create_transaction::ApiError;
+use super::http::AppState;
+use crate::domain::transactions::ports::TransactionService;
+use crate::identity::session::Session;
+use crate::utils::constant_time_eq;
+
+/// Proof that the request carried the admin API token as a bearer token.
+///
+/// Every request is rejected when no admin token is configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdminAuth;
+
+#[async_trait]
+impl<TS: TransactionService> FromRequestParts<AppState<TS>> for AdminAuth {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState<TS>) -> Result<Self, Self::Rejection> {
+        let expected = state
+            .admin_api_token
+            .as_deref()
+            .ok_or_else(|| ApiError::Unauthorized("Admin API is disabled".to_string()))?;
+
+        let provided = bearer_token(parts)?;
+
+        if constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+            Ok(AdminAuth)
+        } else {
+            Err(ApiError::Unauthorized("Invalid bearer token".to_string()))
+        }
+    }
+}
+
+/// Proof that the request carried a session token from `POST /api/auth/session`, naming the
+/// principal the customer signed in as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionAuth {
+    principal: Principal,
+}
+
+impl SessionAuth {
+    // Getter for principal
+    pub fn principal(&self) -> Principal {
+        self.principal
+    }
+}
+
+impl From<Session> for SessionAuth {
+    fn from(session: Session) -> Self {
+        Self { principal: session.principal() }
+    }
+}
+
+#[async_trait]
+impl<TS: TransactionService> FromRequestParts<AppState<TS>> for SessionAuth {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState<TS>) -> Result<Self, Self::Rejection> {
+        let token = bearer_token(parts)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(anyhow::Error::from)?.as_secs();
+
+        state
+            .session_issuer
+            .verify_token(token, now)
+            .map(SessionAuth::from)
+            .map_err(ApiError::from)
+    }
+}
+
+fn bearer_token(parts: &Parts) -> Result<&str, ApiError> {
+    parts
+        .headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| ApiError::Unauthorized("Missing bearer token".to_string()))
+}
@@ -0,0 +1,93 @@
+/*!
+    Module `client_ip` provides an extractor for the address of the client that sent a request.
+*/
+
+use std::net::{IpAddr, SocketAddr};
+
+use axum::async_trait;
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::request::Parts;
+
+use super::handlers::create_transaction::ApiError;
+use super::http::AppState;
+use crate::domain::transactions::ports::TransactionService;
+
+/// The address of the client that sent the request.
+///
+/// Behind a proxy every request comes from the proxy, so the address is taken from the header
+/// the proxy sets, [AppState::client_ip_header], when it is configured and present. The proxy
+/// must replace any value the client sent in it, as Fly's does for `Fly-Client-IP`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl<TS: TransactionService> FromRequestParts<AppState<TS>> for ClientIp {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState<TS>) -> Result<Self, Self::Rejection> {
+        let forwarded = state
+            .client_ip_header
+            .as_ref()
+            .and_then(|header| parts.headers.get(header))
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok());
+        if let Some(ip) = forwarded {
+            return Ok(ClientIp(ip));
+        }
+
+        parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| ClientIp(addr.ip()))
+            .ok_or_else(|| ApiError::InternalServerError("The client's address is unknown".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::http::{HeaderName, Request};
+    use candid::Principal;
+
+    use super::*;
+    use crate::identity::session::SessionIssuer;
+    use crate::inbound::handlers::create_transaction::tests::idle_service;
+
+    fn state(client_ip_header: Option<&'static str>) -> AppState<impl TransactionService> {
+        AppState {
+            transaction_service: Arc::new(idle_service()),
+            admin_api_token: None,
+            session_issuer: Arc::new(SessionIssuer::new(b"secret")),
+            backend_id: Principal::anonymous(),
+            client_ip_header: client_ip_header.map(HeaderName::from_static),
+        }
+    }
+
+    async fn client_ip(state: &AppState<impl TransactionService>, header: Option<&str>) -> Result<ClientIp, ApiError> {
+        let mut request = Request::builder().uri("/api/verify/send");
+        if let Some(value) = header {
+            request = request.header("fly-client-ip", value);
+        }
+        let mut request = request.body(()).unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 4000))));
+        let (mut parts, _) = request.into_parts();
+        ClientIp::from_request_parts(&mut parts, state).await
+    }
+
+    #[tokio::test]
+    async fn test_client_ip_is_taken_from_the_proxy_header_when_configured() {
+        let state = state(Some("fly-client-ip"));
+
+        assert_eq!(client_ip(&state, Some("203.0.113.7")).await.unwrap(), ClientIp([203, 0, 113, 7].into()));
+        // Requests that didn't pass the proxy come from the client itself.
+        assert_eq!(client_ip(&state, None).await.unwrap(), ClientIp([10, 0, 0, 1].into()));
+    }
+
+    #[tokio::test]
+    async fn test_client_ip_header_is_ignored_unless_configured() {
+        let state = state(None);
+
+        assert_eq!(client_ip(&state, Some("203.0.113.7")).await.unwrap(), ClientIp([10, 0, 0, 1].into()));
+    }
+}
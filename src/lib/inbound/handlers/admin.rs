@@ -0,0 +1,124 @@
+/*!
+   Module `admin` specifies HTTP handlers for operating the service, such as inspecting and
+   replaying the outbox dead-letter list and rotating the canister controller key.
+*/
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Deserialize;
+
+use super::create_transaction::{ApiError, ApiSuccess};
+use crate::domain::transactions::models::outbox::{OutboxError, OutboxEvent};
+use crate::domain::transactions::ports::TransactionService;
+use crate::identity::admin::{AdminKeyInfo, AdminKeyRotationError};
+use crate::inbound::auth::AdminAuth;
+use crate::inbound::http::AppState;
+
+impl From<OutboxError> for ApiError {
+    fn from(e: OutboxError) -> Self {
+        match e {
+            OutboxError::NotFound { .. } => Self::NotFound(e.to_string()),
+            OutboxError::NotDeadLettered { .. } => Self::UnprocessableEntity(e.to_string()),
+            OutboxError::Storage(err) => {
+                Self::InternalServerError(format!("Failed to access outbox: {}", err))
+            }
+        }
+    }
+}
+
+impl From<AdminKeyRotationError> for ApiError {
+    fn from(e: AdminKeyRotationError) -> Self {
+        match e {
+            AdminKeyRotationError::Key(_) | AdminKeyRotationError::NotAuthorized { .. } => {
+                Self::UnprocessableEntity(e.to_string())
+            }
+            AdminKeyRotationError::Unknown(cause) => {
+                tracing::error!("Admin key rotation failed: {:?}", cause);
+                Self::InternalServerError("Failed to rotate the admin key".to_string())
+            }
+        }
+    }
+}
+
+/// List the outbox events that exhausted their delivery attempts.
+///
+/// # Responses
+///
+/// - 200 OK: the dead-lettered events, oldest first.
+/// - 401 Unauthorized: the admin token was missing or wrong.
+pub async fn list_dead_letters<TS: TransactionService>(
+    _: AdminAuth,
+    State(state): State<AppState<TS>>,
+) -> Result<ApiSuccess<Vec<OutboxEvent>>, ApiError> {
+    state
+        .transaction_service
+        .dead_letters()
+        .await
+        .map_err(ApiError::from)
+        .map(|events| ApiSuccess::new(StatusCode::OK, events))
+}
+
+/// Queue a dead-lettered outbox event for delivery again.
+///
+/// # Responses
+///
+/// - 200 OK: the event is pending delivery.
+/// - 401 Unauthorized: the admin token was missing or wrong.
+/// - 404 Not Found: there is no outbox event with this ID.
+/// - 422 Unprocessable entity: the event is not dead-lettered.
+pub async fn replay_dead_letter<TS: TransactionService>(
+    _: AdminAuth,
+    State(state): State<AppState<TS>>,
+    Path(id): Path<u64>,
+) -> Result<ApiSuccess<OutboxEvent>, ApiError> {
+    state
+        .transaction_service
+        .replay_dead_letter(id)
+        .await
+        .map_err(ApiError::from)
+        .map(|event| ApiSuccess::new(StatusCode::OK, event))
+}
+
+
+/// The PEM of an admin key and, if the key is encrypted, its passphrase.
+#[derive(Clone, Deserialize)]
+pub struct RotateAdminKeyHttpRequestBody {
+    pub pem: String,
+    pub passphrase: Option<String>,
+}
+
+/// List the keys held for calling the backend canister, and which of them is active.
+///
+/// # Responses
+///
+/// - 200 OK: the keys, without their secrets.
+/// - 401 Unauthorized: the admin token was missing or wrong.
+pub async fn list_admin_keys<TS: TransactionService>(
+    _: AdminAuth,
+    State(state): State<AppState<TS>>,
+) -> ApiSuccess<Vec<AdminKeyInfo>> {
+    ApiSuccess::new(StatusCode::OK, state.transaction_service.admin_keys().await)
+}
+
+/// Switch the calls to the backend canister to another controller key, once a call made with it
+/// has succeeded. Calls under way finish with the previous key. The request is written to the
+/// audit log.
+///
+/// # Responses
+///
+/// - 200 OK: the key is active.
+/// - 401 Unauthorized: the admin token was missing or wrong.
+/// - 422 Unprocessable entity: the key could not be loaded, or does not control the canister.
+pub async fn rotate_admin_key<TS: TransactionService>(
+    _: AdminAuth,
+    State(state): State<AppState<TS>>,
+    Json(body): Json<RotateAdminKeyHttpRequestBody>,
+) -> Result<ApiSuccess<AdminKeyInfo>, ApiError> {
+    state
+        .transaction_service
+        .rotate_admin_key(&body.pem, body.passphrase.as_deref())
+        .await
+        .map_err(ApiError::from)
+        .map(|key| ApiSuccess::new(StatusCode::OK, key))
+}
\ No newline at end of file
@@ -0,0 +1,52 @@
+/*!
+   Module `availability` specifies an HTTP handler for a car's availability calendar.
+*/
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use serde::Deserialize;
+
+use super::create_transaction::{ApiError, ApiSuccess};
+use crate::domain::transactions::models::availability::{Availability, AvailabilityError};
+use crate::domain::transactions::ports::TransactionService;
+use crate::inbound::http::AppState;
+
+impl From<AvailabilityError> for ApiError {
+    fn from(e: AvailabilityError) -> Self {
+        match e {
+            AvailabilityError::InvalidRange { .. } | AvailabilityError::RangeTooLong { .. } => {
+                Self::UnprocessableEntity(e.to_string())
+            }
+            AvailabilityError::Unknown(cause) => {
+                Self::InternalServerError(format!("Failed to load availability: {}", cause))
+            }
+        }
+    }
+}
+
+/// The query string of an availability request, as unix timestamps in seconds.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AvailabilityQuery {
+    pub from: u64,
+    pub to: u64,
+}
+
+/// List the booked, held and free intervals of a car. Held slots are awaiting payment by another
+/// customer and become free again if their hold expires.
+///
+/// # Responses
+///
+/// - 200 OK: the intervals covering `[from, to)`, in chronological order.
+/// - 422 Unprocessable entity: `to` is not after `from`, or the range is too long.
+pub async fn get_car_availability<TS: TransactionService>(
+    State(state): State<AppState<TS>>,
+    Path(car_id): Path<u64>,
+    Query(query): Query<AvailabilityQuery>,
+) -> Result<ApiSuccess<Availability>, ApiError> {
+    state
+        .transaction_service
+        .car_availability(car_id, query.from, query.to)
+        .await
+        .map_err(ApiError::from)
+        .map(|availability| ApiSuccess::new(StatusCode::OK, availability))
+}
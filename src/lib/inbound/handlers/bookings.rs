@@ -0,0 +1,91 @@
+/*!
+   Module `bookings` specifies HTTP handlers for customers to manage their bookings.
+*/
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use serde::Serialize;
+
+use super::create_transaction::{check_booking_owner, ApiError, ApiSuccess};
+use crate::domain::transactions::models::booking::{Booking, CancelBookingError};
+use crate::domain::transactions::ports::TransactionService;
+use crate::inbound::auth::SessionAuth;
+use crate::inbound::http::AppState;
+
+impl From<CancelBookingError> for ApiError {
+    fn from(e: CancelBookingError) -> Self {
+        match e {
+            CancelBookingError::NotFound { .. } => Self::NotFound(e.to_string()),
+            CancelBookingError::AlreadyReserved { .. } => Self::Conflict(e.to_string()),
+            CancelBookingError::Storage(cause) => cause.into(),
+        }
+    }
+}
+
+/// The response body data field for a cancelled booking.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CancelBookingResponseData {
+    id: u64,
+    status: &'static str,
+}
+
+impl From<&Booking> for CancelBookingResponseData {
+    fn from(booking: &Booking) -> Self {
+        Self {
+            id: booking.booking_id(),
+            status: booking.status().as_str(),
+        }
+    }
+}
+
+/// Cancel a booking that has not been paid for, releasing the car for its dates.
+///
+/// # Responses
+///
+/// - 200 OK: the booking is cancelled, including when it already was.
+/// - 401 Unauthorized: the session token was missing, invalid or expired.
+/// - 403 Forbidden: the booking is not the signed-in principal's.
+/// - 404 Not Found: there is no booking with this ID.
+/// - 409 Conflict: the booking is paid for and reserved.
+pub async fn cancel_booking<TS: TransactionService>(
+    session: SessionAuth,
+    State(state): State<AppState<TS>>,
+    Path(booking_id): Path<u64>,
+) -> Result<ApiSuccess<CancelBookingResponseData>, ApiError> {
+    check_booking_owner(&state, booking_id, &session).await?;
+
+    state
+        .transaction_service
+        .cancel_booking(booking_id)
+        .await
+        .map_err(ApiError::from)
+        .map(|ref booking| ApiSuccess::new(StatusCode::OK, booking.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use candid::Principal;
+
+    use super::*;
+    use crate::inbound::handlers::create_transaction::tests::{service_with_booking, session_for, test_state};
+
+    #[tokio::test]
+    async fn test_customer_cancels_their_booking() {
+        let customer = Principal::from_slice(&[1; 29]);
+        let state = test_state(service_with_booking(1, customer));
+
+        let actual = cancel_booking(session_for(customer), state, Path(1)).await;
+
+        let expected = CancelBookingResponseData { id: 1, status: "cancelled" };
+        assert_eq!(actual, Ok(ApiSuccess::new(StatusCode::OK, expected)));
+    }
+
+    #[tokio::test]
+    async fn test_cancelling_another_principals_booking_is_forbidden() {
+        let state = test_state(service_with_booking(1, Principal::from_slice(&[1; 29])));
+
+        let actual = cancel_booking(session_for(Principal::from_slice(&[2; 29])), state, Path(1)).await;
+
+        assert!(matches!(actual, Err(ApiError::Forbidden(_))), "expected 403, but got {:?}", actual);
+    }
+}
@@ -3,16 +3,25 @@
    associated data structures.
 */
 
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use axum::extract::State;
-use axum::http::StatusCode;
+use axum::http::{header, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
+use candid::Principal;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::canister::backend::RazorpayPayment;
+use crate::domain::transactions::models::booking::BookingRepositoryError;
 use crate::domain::transactions::models::transaction::*;
+use crate::domain::transactions::models::driving_licence::{DrivingLicence, DrivingLicenceError};
+use crate::domain::transactions::models::phone_number::{PhoneNumber, PhoneNumberError};
+use crate::domain::transactions::models::verification::VerificationToken;
 use crate::domain::transactions::ports::TransactionService;
+use crate::identity::delegated_identity::{DelegatedIdentityWire, DelegationError};
+use crate::inbound::auth::SessionAuth;
 use crate::inbound::http::AppState;
 #[derive(Debug, Clone)]
 pub struct ApiSuccess<T: Serialize + PartialEq>(StatusCode, Json<ApiResponseBody<T>>);
@@ -27,7 +36,7 @@ where
 }
 
 impl<T: Serialize + PartialEq> ApiSuccess<T> {
-    fn new(status: StatusCode, data: T) -> Self {
+    pub fn new(status: StatusCode, data: T) -> Self {
         ApiSuccess(status, Json(ApiResponseBody::new(status, data)))
     }
 }
@@ -43,6 +52,15 @@ pub enum ApiError {
     InternalServerError(String),
     UnprocessableEntity(String),
     Conflict(String),
+    NotFound(String),
+    Unauthorized(String),
+    Forbidden(String),
+    /// A dependency is unavailable; the request may succeed if tried again.
+    ServiceUnavailable(String),
+    /// The client should wait `retry_after` seconds before trying again.
+    TooManyRequests { message: String, retry_after: u64 },
+    /// One or more request fields failed validation.
+    Validation(Vec<FieldError>),
 }
 
 impl From<anyhow::Error> for ApiError {
@@ -53,38 +71,31 @@ impl From<anyhow::Error> for ApiError {
 
 impl From<CreateTransactionError> for ApiError {
     fn from(e: CreateTransactionError) -> Self {
-        match e {
+        match &e {
             CreateTransactionError::Unknown(cause) => {
                 tracing::error!("Request Failed{:?}\n{}", cause, cause.backtrace());
-                Self::Conflict(cause.to_string())
-            }
-            CreateTransactionError::InvalidAge(age_error) => {
-                Self::UnprocessableEntity(format!("Invalid age: {}", age_error))
-            }
-            CreateTransactionError::InvalidPAN(pan_error) => {
-                Self::UnprocessableEntity(format!("Invalid PAN: {}", pan_error))
+                Self::InternalServerError(cause.to_string())
             }
-            CreateTransactionError::InvalidAadhar(aadhar_error) => {
-                Self::UnprocessableEntity(format!("Invalid Aadhar: {}", aadhar_error))
-            }
-            CreateTransactionError::UserNameEmpty(user_name_empty_error) => {
-                Self::UnprocessableEntity(format!(
-                    "Username cannot be empty: {}",
-                    user_name_empty_error
-                ))
+            CreateTransactionError::InvalidAge(_) | CreateTransactionError::AgeRestricted(_) => {
+                Self::Validation(vec![FieldError::new("date_of_birth", &e)])
             }
-            CreateTransactionError::InvalidEmail(email_address_error) => {
-                Self::UnprocessableEntity(format!("Invalid email address: {}", email_address_error))
+            CreateTransactionError::InvalidPAN(_) => Self::Validation(vec![FieldError::new("pan", &e)]),
+            CreateTransactionError::InvalidAadhar(_) => Self::Validation(vec![FieldError::new("aadhar", &e)]),
+            CreateTransactionError::UserNameEmpty(_) => Self::Validation(vec![FieldError::new("name", &e)]),
+            CreateTransactionError::InvalidEmail(_) => {
+                Self::Validation(vec![FieldError::new("email_address", &e)])
             }
-            CreateTransactionError::InvalidMobile(mobile_number_error) => {
-                Self::UnprocessableEntity(format!("Invalid mobile number: {}", mobile_number_error))
+            CreateTransactionError::InvalidMobile(PhoneNumberError::InvalidCountryCode { .. }) => {
+                Self::Validation(vec![FieldError::new("country_code", &e)])
             }
-            CreateTransactionError::StartTimeError => {
-                Self::UnprocessableEntity("Invalid start time for transaction".to_string())
+            CreateTransactionError::InvalidMobile(_) => {
+                Self::Validation(vec![FieldError::new("mobile_number", &e)])
             }
-            CreateTransactionError::EndTimeError => {
-                Self::UnprocessableEntity("Invalid end time for transaction".to_string())
+            CreateTransactionError::InvalidDrivingLicence(licence_error) => {
+                Self::Validation(vec![FieldError::new(driving_licence_field(licence_error), &e)])
             }
+            CreateTransactionError::StartTimeError => Self::Validation(vec![FieldError::new("start_time", &e)]),
+            CreateTransactionError::EndTimeError => Self::Validation(vec![FieldError::new("end_time", &e)]),
             CreateTransactionError::TransactionExists { transaction_id } => {
                 Self::UnprocessableEntity(format!(
                     "Transaction with ID {} already exists",
@@ -94,16 +105,29 @@ impl From<CreateTransactionError> for ApiError {
             CreateTransactionError::InsufficientFunds => {
                 Self::UnprocessableEntity("Insufficient funds for this transaction".to_string())
             }
-            CreateTransactionError::CanisterCommunicationError(err) => Self::InternalServerError(
-                format!("Failed to communicate with the canister: {}", err),
-            ),
+            CreateTransactionError::CanisterCommunicationError(err) => {
+                tracing::warn!("Failed to communicate with the canister: {}", err);
+                Self::ServiceUnavailable("The booking service is unavailable, try again shortly".to_string())
+            }
             CreateTransactionError::CanisterRejectedError(err) => {
-                Self::UnprocessableEntity(format!("Could not reserver car: {}", err))
+                Self::UnprocessableEntity(format!("Could not reserve car: {}", err))
+            }
+            CreateTransactionError::Storage(err) => {
+                Self::InternalServerError(format!("Failed to access booking store: {}", err))
             }
+            CreateTransactionError::SlotHeld { .. } => Self::Conflict(e.to_string()),
+            CreateTransactionError::Unverified(verification_error) => verification_error.into(),
         }
     }
 }
 
+impl From<BookingRepositoryError> for ApiError {
+    fn from(e: BookingRepositoryError) -> Self {
+        tracing::error!("Failed to access booking store: {:?}", e);
+        Self::InternalServerError("Failed to access booking store".to_string())
+    }
+}
+
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         use ApiError::*;
@@ -129,10 +153,41 @@ impl IntoResponse for ApiError {
             )
                 .into_response(),
             Conflict(message) => (
-                StatusCode::UNPROCESSABLE_ENTITY,
+                StatusCode::CONFLICT,
                 Json(ApiResponseBody::new_error(StatusCode::CONFLICT, message)),
             )
                 .into_response(),
+            NotFound(message) => (
+                StatusCode::NOT_FOUND,
+                Json(ApiResponseBody::new_error(StatusCode::NOT_FOUND, message)),
+            )
+                .into_response(),
+            Unauthorized(message) => (
+                StatusCode::UNAUTHORIZED,
+                Json(ApiResponseBody::new_error(StatusCode::UNAUTHORIZED, message)),
+            )
+                .into_response(),
+            Forbidden(message) => (
+                StatusCode::FORBIDDEN,
+                Json(ApiResponseBody::new_error(StatusCode::FORBIDDEN, message)),
+            )
+                .into_response(),
+            ServiceUnavailable(message) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ApiResponseBody::new_error(StatusCode::SERVICE_UNAVAILABLE, message)),
+            )
+                .into_response(),
+            TooManyRequests { message, retry_after } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, HeaderValue::from(retry_after))],
+                Json(ApiResponseBody::new_error(StatusCode::TOO_MANY_REQUESTS, message)),
+            )
+                .into_response(),
+            Validation(errors) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ApiResponseBody::new_validation_error(errors)),
+            )
+                .into_response(),
         }
     }
 }
@@ -157,7 +212,20 @@ impl ApiResponseBody<ApiErrorData> {
     pub fn new_error(status_code: StatusCode, message: String) -> Self {
         Self {
             status_code: status_code.as_u16(),
-            data: ApiErrorData { message },
+            data: ApiErrorData {
+                message,
+                errors: Vec::new(),
+            },
+        }
+    }
+
+    pub fn new_validation_error(errors: Vec<FieldError>) -> Self {
+        Self {
+            status_code: StatusCode::UNPROCESSABLE_ENTITY.as_u16(),
+            data: ApiErrorData {
+                message: "Request validation failed".to_string(),
+                errors,
+            },
         }
     }
 }
@@ -166,6 +234,30 @@ impl ApiResponseBody<ApiErrorData> {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct ApiErrorData {
     pub message: String,
+    /// Every invalid field of the request, when the request failed validation.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+/// A single invalid request field.
+///
+/// `field` is the name of the field in the request body and `code` is the stable [ErrorCode] of
+/// the failure, so that clients can attach a translated message to the right input.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &'static str, error: &(impl ErrorCode + std::fmt::Display)) -> Self {
+        Self {
+            field,
+            code: error.code(),
+            message: error.to_string(),
+        }
+    }
 }
 
 /// The body of a [Transaction] creation request.
@@ -174,19 +266,32 @@ pub struct ConfirmTransactionHttpRequestBody {
     booking_id: u64, payment: RazorpayPayment,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct CreateTransactionHttpRequestBody {
     pub name: String,
     pub email_address: String,
     pub pan: String,
-    pub age: u8,
+    /// ISO 8601 date, e.g. `1990-04-23`.
+    pub date_of_birth: String,
     pub car_id: u64,
     pub aadhar: u64,
     pub country_code: u16,
+    /// The national number, or the full number in E.164 form, e.g. `+919876543210`.
     pub mobile_number: String,
-    pub principal_jwk: String,
+    /// Indian driving licence number, e.g. `MH14 20110062821`.
+    pub driving_licence_number: String,
+    /// Code of the state that issued the licence, e.g. `MH`.
+    pub driving_licence_state: String,
+    /// ISO 8601 date; the licence must be valid until `end_time`.
+    pub driving_licence_expiry: String,
+    /// A delegation from the customer's identity to a session key, limited to the backend
+    /// canister. The customer's own secret key never leaves their device.
+    pub delegated_identity: DelegatedIdentityWire,
     pub start_time: u64,
     pub end_time: u64,
+    /// Tokens from `POST /api/verify/confirm` for both `email_address` and `mobile_number`.
+    #[serde(default)]
+    pub verification_tokens: Vec<String>,
 }
 
 #[derive(Debug, Clone, Error)]
@@ -198,6 +303,10 @@ pub enum ParseCreateTransactionHttpRequestError {
     #[error(transparent)]
     Pan(#[from] PANError),
     #[error(transparent)]
+    PanNameMismatch(#[from] PANNameMismatchError),
+    #[error(transparent)]
+    DateOfBirth(#[from] DateOfBirthError),
+    #[error(transparent)]
     Age(#[from] AgeError),
     #[error(transparent)]
     StartTime(#[from] StartTimeError),
@@ -206,83 +315,150 @@ pub enum ParseCreateTransactionHttpRequestError {
     #[error(transparent)]
     Aadhar(#[from] AadharError),
     #[error(transparent)]
-    MobileNumber(#[from] MobileNumberError),
+    PhoneNumber(#[from] PhoneNumberError),
+    #[error(transparent)]
+    DrivingLicence(#[from] DrivingLicenceError),
+    #[error(transparent)]
+    Delegation(#[from] DelegationError),
+}
+
+impl ParseCreateTransactionHttpRequestError {
+    /// The name of the offending field in [CreateTransactionHttpRequestBody].
+    pub fn field(&self) -> &'static str {
+        match self {
+            Self::Name(_) => "name",
+            Self::EmailAddress(_) => "email_address",
+            Self::Pan(_) | Self::PanNameMismatch(_) => "pan",
+            Self::DateOfBirth(_) | Self::Age(_) => "date_of_birth",
+            Self::StartTime(_) => "start_time",
+            Self::EndTime(_) => "end_time",
+            Self::Aadhar(_) => "aadhar",
+            Self::PhoneNumber(PhoneNumberError::InvalidCountryCode { .. }) => "country_code",
+            Self::PhoneNumber(_) => "mobile_number",
+            Self::DrivingLicence(e) => driving_licence_field(e),
+            Self::Delegation(_) => "delegated_identity",
+        }
+    }
+}
+
+impl ErrorCode for ParseCreateTransactionHttpRequestError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Name(e) => e.code(),
+            Self::EmailAddress(e) => e.code(),
+            Self::Pan(e) => e.code(),
+            Self::PanNameMismatch(e) => e.code(),
+            Self::DateOfBirth(e) => e.code(),
+            Self::Age(e) => e.code(),
+            Self::StartTime(e) => e.code(),
+            Self::EndTime(e) => e.code(),
+            Self::Aadhar(e) => e.code(),
+            Self::PhoneNumber(e) => e.code(),
+            Self::DrivingLicence(e) => e.code(),
+            Self::Delegation(e) => e.code(),
+        }
+    }
+}
+
+/// The field of [CreateTransactionHttpRequestBody] that `e` is about.
+fn driving_licence_field(e: &DrivingLicenceError) -> &'static str {
+    match e {
+        DrivingLicenceError::InvalidNumber { .. } => "driving_licence_number",
+        DrivingLicenceError::InvalidIssuingState { .. } | DrivingLicenceError::StateMismatch { .. } => {
+            "driving_licence_state"
+        }
+        DrivingLicenceError::InvalidExpiry { .. } | DrivingLicenceError::ExpiresBeforeEndTime { .. } => {
+            "driving_licence_expiry"
+        }
+    }
+}
+
+/// Every field of a [CreateTransactionHttpRequestBody] that failed validation.
+#[derive(Debug, Clone, Error)]
+#[error("{} field(s) failed validation", .0.len())]
+pub struct InvalidCreateTransactionHttpRequest(pub Vec<ParseCreateTransactionHttpRequestError>);
+
+/// Keep the value of `result`, or record its error and continue validating the other fields.
+fn collect<T, E: Into<ParseCreateTransactionHttpRequestError>>(
+    result: Result<T, E>,
+    errors: &mut Vec<ParseCreateTransactionHttpRequestError>,
+) -> Option<T> {
+    result.map_err(|e| errors.push(e.into())).ok()
 }
 
 impl CreateTransactionHttpRequestBody {
-    /// Converts the HTTP request body into a domain request.
+    /// Converts the HTTP request body into a domain request, validating every field.
+    /// `delegated_identity` must be allowed to call the `backend_id` canister.
     pub fn try_into_domain(
         self,
-    ) -> Result<CreateTransactionRequest, ParseCreateTransactionHttpRequestError> {
-        let name = UserName::new(&self.name)?;
-        let email = EmailAddress::new(&self.email_address)?;
-        let pan = PAN::new(&self.pan)?;
-        let age = Age::new(self.age)?;
-        let aadhar = Aadhar::new(&self.aadhar.to_string())?;
-        let mobile_number =
-            MobileNumber::new(self.mobile_number.parse().map_err(|_| MobileNumberError {
-                invalid_mobile_number: self.mobile_number.clone(),
-            })?)?;
+        backend_id: Principal,
+    ) -> Result<CreateTransactionRequest, InvalidCreateTransactionHttpRequest> {
+        let mut errors = Vec::new();
+        let name = collect(UserName::new(&self.name), &mut errors);
+        let email = collect(EmailAddress::new(&self.email_address), &mut errors);
+        let pan = collect(PAN::new(&self.pan), &mut errors);
+        if let (Some(name), Some(pan)) = (&name, &pan) {
+            collect(pan.check_surname(name), &mut errors);
+        }
+        let aadhar = collect(Aadhar::new(&self.aadhar.to_string()), &mut errors);
+        let phone_number = collect(PhoneNumber::new(self.country_code, &self.mobile_number), &mut errors);
+        let start_time = collect(StartTime::new(self.start_time), &mut errors);
+        let end_time = collect(EndTime::new(self.end_time, self.start_time), &mut errors);
+        let driving_licence = collect(
+            DrivingLicence::new(
+                &self.driving_licence_number,
+                &self.driving_licence_state,
+                &self.driving_licence_expiry,
+            ),
+            &mut errors,
+        );
+        if let (Some(driving_licence), Some(end_time)) = (&driving_licence, &end_time) {
+            collect(driving_licence.check_valid_until(end_time.value()), &mut errors);
+        }
+        let date_of_birth = collect(DateOfBirth::new(&self.date_of_birth), &mut errors);
+        // Age on the first day of the booking, so that customers can book ahead of a birthday.
+        let age = match (&date_of_birth, &start_time) {
+            (Some(date_of_birth), Some(start_time)) => collect(
+                Age::new(date_of_birth.age_at(start_time.value()).unwrap_or_default()),
+                &mut errors,
+            ),
+            _ => None,
+        };
+        let now_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+        let caller = collect(self.delegated_identity.verify(backend_id, now_ns), &mut errors);
+
+        let (Some(name), Some(email), Some(pan), Some(age), Some(date_of_birth), Some(aadhar), Some(phone_number), Some(driving_licence), Some(start_time), Some(end_time), Some(caller)) =
+            (name, email, pan, age, date_of_birth, aadhar, phone_number, driving_licence, start_time, end_time, caller)
+        else {
+            return Err(InvalidCreateTransactionHttpRequest(errors));
+        };
+        // Checks across fields can fail even when every field parsed.
+        if !errors.is_empty() {
+            return Err(InvalidCreateTransactionHttpRequest(errors));
+        }
 
         Ok(CreateTransactionRequest::new(
             name,
             email,
             age,
+            date_of_birth,
             pan,
             aadhar,
-            mobile_number,
-            self.country_code,
+            phone_number,
+            driving_licence,
             self.car_id,
-            StartTime::new(self.start_time)?,
-            EndTime::new(self.end_time, self.start_time)?,
-            self.principal_jwk,
-        ))
+            start_time,
+            end_time,
+            self.delegated_identity,
+            caller,
+        )
+        .with_verification_tokens(self.verification_tokens.iter().map(|token| VerificationToken::new(token)).collect()))
     }
 }
 
-impl From<ParseCreateTransactionHttpRequestError> for ApiError {
-    fn from(e: ParseCreateTransactionHttpRequestError) -> Self {
-        match e {
-            ParseCreateTransactionHttpRequestError::Name(user_name_empty_error) => {
-                Self::UnprocessableEntity(format!(
-                    "Username cannot be empty: {}",
-                    user_name_empty_error
-                ))
-            }
-            ParseCreateTransactionHttpRequestError::EmailAddress(email_error) => {
-                Self::UnprocessableEntity(format!(
-                    "Invalid email address: {}",
-                    email_error.invalid_email
-                ))
-            }
-            ParseCreateTransactionHttpRequestError::Pan(pan_error) => {
-                Self::UnprocessableEntity(format!("Invalid PAN: {}", pan_error.invalid_pan))
-            }
-            ParseCreateTransactionHttpRequestError::Age(age_error) => {
-                Self::UnprocessableEntity(format!("Invalid age: {}", age_error.invalid_age))
-            }
-            ParseCreateTransactionHttpRequestError::Aadhar(aadhar_error) => {
-                Self::UnprocessableEntity(format!(
-                    "Invalid Aadhar: {}",
-                    aadhar_error.invalid_aadhar
-                ))
-            }
-            ParseCreateTransactionHttpRequestError::MobileNumber(mobile_error) => {
-                Self::UnprocessableEntity(format!(
-                    "Invalid mobile number: {}",
-                    mobile_error.invalid_mobile_number
-                ))
-            }
-            ParseCreateTransactionHttpRequestError::StartTime(start_time_error) => {
-                Self::UnprocessableEntity(format!(
-                    "Invalid start time: {}",
-                    start_time_error.start_time
-                ))
-            }
-            ParseCreateTransactionHttpRequestError::EndTime(end_time_error) => {
-                Self::UnprocessableEntity(format!("Invalid end time: {}", end_time_error.end_time))
-            } // Add more cases if you have additional validation errors to handle
-        }
+impl From<InvalidCreateTransactionHttpRequest> for ApiError {
+    fn from(e: InvalidCreateTransactionHttpRequest) -> Self {
+        Self::Validation(e.0.iter().map(|error| FieldError::new(error.field(), error)).collect())
     }
 }
 
@@ -319,13 +495,16 @@ impl From<&Transaction> for CreateTransactionResponseData {
 /// # Responses
 ///
 /// - 201 Created: the [Transaction] was successfully created.
+/// - 401 Unauthorized: the session token was missing, invalid or expired.
+/// - 403 Forbidden: the booking is not the signed-in principal's.
+/// - 404 Not Found: there is no booking with this ID.
 /// - 422 Unprocessable entity: A [Transaction] with invalid data was provided.
 pub async fn create_transaction<TS: TransactionService>(
+    session: SessionAuth,
     State(state): State<AppState<TS>>,
     Json(body): Json<ConfirmTransactionHttpRequestBody>,
 ) -> Result<ApiSuccess<CreateTransactionResponseData>, ApiError> {
-    // Validate input and convert to domain request
-    // let domain_req = body.try_into_domain()?;
+    check_booking_owner(&state, body.booking_id, &session).await?;
 
     state
         .transaction_service
@@ -335,12 +514,24 @@ pub async fn create_transaction<TS: TransactionService>(
         .map(|ref transaction| ApiSuccess::new(StatusCode::CREATED, transaction.into()))
 }
 
+/// Hold the car and create a payment link for the booking.
+///
+/// # Responses
+///
+/// - 200 OK: the payment link.
+/// - 401 Unauthorized: the session token was missing, invalid or expired.
+/// - 403 Forbidden: `delegated_identity` is not for the principal that signed in.
+/// - 422 Unprocessable entity: A request with invalid data was provided.
 pub async fn create_payment_link<TS: TransactionService>(
+    session: SessionAuth,
     State(state): State<AppState<TS>>,
     Json(body): Json<CreateTransactionHttpRequestBody>,
 ) -> Result<ApiSuccess<CreatePaymentLink>, ApiError> {
     // Validate input and convert to domain request
-    let domain_req = body.try_into_domain()?;
+    let domain_req = body.try_into_domain(state.backend_id)?;
+    if domain_req.caller() != session.principal() {
+        return Err(ApiError::Forbidden("The delegated identity is not the signed-in principal".to_string()));
+    }
 
     state
         .transaction_service
@@ -350,6 +541,29 @@ pub async fn create_payment_link<TS: TransactionService>(
         .map(|ref transaction| ApiSuccess::new(StatusCode::OK, transaction.into()))
 }
 
+/// Check that `booking_id` was made by the principal signed in to `session`.
+///
+/// # Errors
+///
+/// - [ApiError::NotFound] if there is no booking with `booking_id`.
+/// - [ApiError::Forbidden] if the booking was made by another principal, or its customer
+///   details were erased.
+pub(super) async fn check_booking_owner<TS: TransactionService>(
+    state: &AppState<TS>,
+    booking_id: u64,
+    session: &SessionAuth,
+) -> Result<(), ApiError> {
+    let booking = state
+        .transaction_service
+        .find_booking(booking_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Booking {booking_id} not found")))?;
+    if booking.customer_principal() != Some(session.principal().to_text().as_str()) {
+        return Err(ApiError::Forbidden(format!("Booking {booking_id} is not yours")));
+    }
+    Ok(())
+}
+
 pub async  fn get_principal<TS: TransactionService>(
     State(state): State<AppState<TS>>,
 ) -> Result<ApiSuccess<String>, ApiError> {
@@ -362,25 +576,39 @@ pub async  fn get_principal<TS: TransactionService>(
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use std::sync::Arc;
 
     use anyhow::anyhow;
     use candid::Principal;
+    use k256::elliptic_curve::rand_core::OsRng;
     // use uuid::Uuid;
 
     use crate::canister::backend::RazorpayPayment;
+    use crate::domain::transactions::models::availability::{Availability, AvailabilityError};
+    use crate::domain::transactions::models::booking::{Booking, CancelBookingError};
+    use crate::domain::transactions::models::data_request::{
+        CustomerDataExport, DataRequestError, DataRequestKind, DataSubject, ErasureReport,
+    };
+    use crate::domain::transactions::models::document::{Document, DocumentError, DocumentKind};
+    use crate::domain::transactions::models::outbox::{OutboxError, OutboxEvent};
     use crate::domain::transactions::models::transaction::{CreateTransactionRequest, Transaction};
+    use crate::domain::transactions::models::verification::{Contact, VerificationError};
     use crate::domain::transactions::ports::TransactionService;
+    use crate::identity::admin::{AdminKeyError, AdminKeyInfo, AdminKeyRotationError};
+    use crate::identity::session::SessionIssuer;
 
     use super::*;
 
     #[derive(Clone)]
-    struct MockTransactionService {
+    pub(crate) struct MockTransactionService {
         create_transaction_result:
             Arc<std::sync::Mutex<Result<Transaction, CreateTransactionError>>>,
         create_payment_link_result:
             Arc<Result<String, CreateTransactionError>>,
+        booking: Option<Booking>,
+        /// The data subject requests refused through [TransactionService::refuse_data_request].
+        pub(crate) refused_data_requests: Arc<std::sync::Mutex<Vec<(DataRequestKind, String)>>>,
     }
 
     impl TransactionService for MockTransactionService {
@@ -406,38 +634,123 @@ mod tests {
         async fn get_principal(&self) -> Result<String, CreateTransactionError> {
             Ok(Principal::anonymous().to_text())
         }
+
+        async fn find_booking(&self, booking_id: u64) -> Result<Option<Booking>, BookingRepositoryError> {
+            Ok(self.booking.clone().filter(|booking| booking.booking_id() == booking_id))
+        }
+
+        async fn cancel_booking(&self, booking_id: u64) -> Result<Booking, CancelBookingError> {
+            let booking = self.find_booking(booking_id).await?.ok_or(CancelBookingError::NotFound { booking_id })?;
+            Ok(booking.cancelled())
+        }
+
+        async fn car_availability(&self, _: u64, _: u64, _: u64) -> Result<Availability, AvailabilityError> {
+            Err(AvailabilityError::Unknown(anyhow!("substitute error")))
+        }
+
+        async fn dead_letters(&self) -> Result<Vec<OutboxEvent>, OutboxError> {
+            Ok(vec![])
+        }
+
+        async fn replay_dead_letter(&self, id: u64) -> Result<OutboxEvent, OutboxError> {
+            Err(OutboxError::NotFound { id })
+        }
+
+        async fn upload_document(
+            &self,
+            booking_id: u64,
+            _kind: DocumentKind,
+            _declared_content_type: Option<String>,
+            _contents: Vec<u8>,
+        ) -> Result<Document, DocumentError> {
+            Err(DocumentError::BookingNotFound { booking_id })
+        }
+
+        async fn get_document(&self, _booking_id: u64, document_id: &str) -> Result<(Document, Vec<u8>), DocumentError> {
+            Err(DocumentError::NotFound { document_id: document_id.to_string() })
+        }
+
+        async fn send_verification(&self, _: &Contact, _: std::net::IpAddr) -> Result<(), VerificationError> {
+            Err(VerificationError::RateLimited { retry_after: 60 })
+        }
+
+        async fn confirm_verification(&self, _: &Contact, _: &str) -> Result<(VerificationToken, u64), VerificationError> {
+            Err(VerificationError::InvalidCode)
+        }
+
+        async fn export_customer_data(&self, _: &DataSubject) -> Result<CustomerDataExport, DataRequestError> {
+            Err(DataRequestError::Storage(anyhow!("substitute error")))
+        }
+
+        async fn erase_customer_data(&self, _: &DataSubject) -> Result<ErasureReport, DataRequestError> {
+            Err(DataRequestError::Storage(anyhow!("substitute error")))
+        }
+
+        async fn refuse_data_request(&self, request: DataRequestKind, reason: &str) {
+            self.refused_data_requests.lock().unwrap().push((request, reason.to_string()));
+        }
+
+        async fn admin_keys(&self) -> Vec<AdminKeyInfo> {
+            vec![]
+        }
+
+        async fn rotate_admin_key(&self, _: &str, _: Option<&str>) -> Result<AdminKeyInfo, AdminKeyRotationError> {
+            Err(AdminKeyError::NoPrivateKey.into())
+        }
     }
 
-    #[tokio::test(flavor = "multi_thread")]
-    async fn test_create_transaction_success() {
-        let booking_id = 1;
-        let car_id = 101;
-        let transaction_id = 1;
+    /// A service whose only booking, `booking_id`, was made by `customer`.
+    pub(crate) fn service_with_booking(booking_id: u64, customer: Principal) -> MockTransactionService {
+        let transaction = Transaction::test_fixture(booking_id, 101);
+        let booking = Booking::awaiting_payment(transaction.clone(), 1000.0)
+            .with_customer_principal(Some(customer.to_text()));
+        MockTransactionService {
+            create_payment_link_result: Arc::new(Ok("https://shortlink.com".to_string())),
+            create_transaction_result: Arc::new(std::sync::Mutex::new(Ok(transaction))),
+            booking: Some(booking),
+            refused_data_requests: Arc::default(),
+        }
+    }
 
-        let service = MockTransactionService {
+    /// A service with no bookings, whose calls fail.
+    pub(crate) fn idle_service() -> MockTransactionService {
+        MockTransactionService {
             create_payment_link_result: Arc::new(Ok("https://shortlink.com".to_string())),
-            create_transaction_result: Arc::new(std::sync::Mutex::new(Ok(Transaction::new(
-                booking_id,
-                car_id,
-                UserName::new("Test User").unwrap(),
-                EmailAddress::new("test@example.com").unwrap(),
-                Age::new(25).unwrap(),
-                91,
-                MobileNumber::new(9876543210).unwrap(),
-                PAN::new("ABCDE1234F").unwrap(),
-                Aadhar::new("123456789012").unwrap(),
-                1734556800, 1734564000
-            )))),
-        };
+            create_transaction_result: Arc::new(std::sync::Mutex::new(Err(CreateTransactionError::InsufficientFunds))),
+            booking: None,
+            refused_data_requests: Arc::default(),
+        }
+    }
 
-        let state = axum::extract::State(AppState {
+    pub(crate) fn test_state(service: MockTransactionService) -> axum::extract::State<AppState<MockTransactionService>> {
+        axum::extract::State(AppState {
             transaction_service: Arc::new(service),
-        });
+            admin_api_token: None,
+            session_issuer: Arc::new(SessionIssuer::new(b"secret")),
+            backend_id: Principal::anonymous(),
+            client_ip_header: None,
+        })
+    }
+
+    pub(crate) fn session_for(principal: Principal) -> SessionAuth {
+        let issuer = SessionIssuer::new(b"secret");
+        SessionAuth::from(issuer.verify_token(&issuer.issue_token(principal, 1000).token, 1000).unwrap())
+    }
 
-        let body = axum::extract::Json(ConfirmTransactionHttpRequestBody {
+    fn confirm_body(booking_id: u64) -> axum::extract::Json<ConfirmTransactionHttpRequestBody> {
+        axum::extract::Json(ConfirmTransactionHttpRequestBody {
             booking_id,
             payment: RazorpayPayment { payment_link_id: None, payment_id: String::new(), ref_id: booking_id.to_string() }
-        });
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_create_transaction_success() {
+        let booking_id = 1;
+        let transaction_id = 1;
+
+        let state = test_state(service_with_booking(booking_id, Principal::anonymous()));
+        let body = confirm_body(booking_id);
 
         let expected = ApiSuccess::new(
             StatusCode::CREATED,
@@ -446,7 +759,7 @@ mod tests {
             },
         );
 
-        let actual = create_transaction(state, body).await;
+        let actual = create_transaction(session_for(Principal::anonymous()), state, body).await;
 
         assert!(
             actual.is_ok(),
@@ -461,4 +774,129 @@ mod tests {
             expected, actual
         );
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_create_transaction_for_another_principals_booking_is_forbidden() {
+        let customer = Principal::from_slice(&[1; 29]);
+        let state = test_state(service_with_booking(1, customer));
+
+        let actual = create_transaction(session_for(Principal::from_slice(&[2; 29])), state, confirm_body(1)).await;
+
+        assert!(matches!(actual, Err(ApiError::Forbidden(_))), "expected 403, but got {:?}", actual);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_create_transaction_for_an_unknown_booking_is_not_found() {
+        let state = test_state(service_with_booking(1, Principal::anonymous()));
+
+        let actual = create_transaction(session_for(Principal::anonymous()), state, confirm_body(2)).await;
+
+        assert!(matches!(actual, Err(ApiError::NotFound(_))), "expected 404, but got {:?}", actual);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_canister_rejection_is_passed_on_as_unprocessable() {
+        let service = service_with_booking(1, Principal::anonymous());
+        *service.create_transaction_result.lock().unwrap() =
+            Err(CreateTransactionError::CanisterRejectedError("Car is not available".to_string()));
+
+        let actual = create_transaction(session_for(Principal::anonymous()), test_state(service), confirm_body(1)).await;
+
+        assert_eq!(actual, Err(ApiError::UnprocessableEntity("Could not reserve car: Car is not available".to_string())));
+    }
+
+    /// A request body in which every field is valid.
+    fn valid_body() -> CreateTransactionHttpRequestBody {
+        let customer = ed25519_consensus::SigningKey::from([7; 32]);
+        CreateTransactionHttpRequestBody {
+            name: "Test User".to_string(),
+            email_address: "test@example.com".to_string(),
+            pan: "ABCPU1234F".to_string(),
+            date_of_birth: "1999-01-01".to_string(),
+            car_id: 101,
+            aadhar: 234567890124,
+            country_code: 91,
+            mobile_number: "9876543210".to_string(),
+            driving_licence_number: "MH14 20110062821".to_string(),
+            driving_licence_state: "MH".to_string(),
+            driving_licence_expiry: "2100-12-31".to_string(),
+            delegated_identity: DelegatedIdentityWire::signed_by(&customer, u64::MAX),
+            start_time: 4102444800,
+            end_time: 4102448400,
+            verification_tokens: Vec::new(),
+        }
+    }
+
+    /// The fields and codes of the errors `body` is rejected with.
+    fn invalid_fields(body: CreateTransactionHttpRequestBody) -> Vec<(&'static str, &'static str)> {
+        match body.try_into_domain(Principal::anonymous()) {
+            Ok(_) => vec![],
+            Err(e) => e.0.iter().map(|error| (error.field(), error.code())).collect(),
+        }
+    }
+
+    #[test]
+    fn test_valid_body_is_accepted() {
+        assert_eq!(invalid_fields(valid_body()), []);
+    }
+
+    #[test]
+    fn test_licence_expiring_before_the_booking_ends_is_rejected() {
+        // The booking ends at 06:30 on 2100-01-01 in IST
+        let body = CreateTransactionHttpRequestBody { driving_licence_expiry: "2099-12-31".to_string(), ..valid_body() };
+
+        assert_eq!(invalid_fields(body), [("driving_licence_expiry", "licence_expires_before_end_time")]);
+    }
+
+    #[test]
+    fn test_pan_of_someone_with_another_surname_is_rejected() {
+        let body = CreateTransactionHttpRequestBody { pan: "ABCPK1234F".to_string(), ..valid_body() };
+
+        assert_eq!(invalid_fields(body), [("pan", "pan_name_mismatch")]);
+    }
+
+    #[test]
+    fn test_every_invalid_field_is_reported() {
+        let body = CreateTransactionHttpRequestBody {
+            name: "Test User".to_string(),
+            email_address: "not-an-email".to_string(),
+            pan: "ABC".to_string(),
+            date_of_birth: "1999-01-01".to_string(),
+            car_id: 101,
+            aadhar: 234567890124,
+            country_code: 91,
+            mobile_number: "98765".to_string(),
+            driving_licence_number: "MH14 20110062821".to_string(),
+            driving_licence_state: "MH".to_string(),
+            // The booking ends at 06:30 on 2100-01-01 in IST
+            driving_licence_expiry: "2099-12-31".to_string(),
+            // A session key without the delegation chain linking it to the customer.
+            delegated_identity: serde_json::from_value(serde_json::json!({
+                "from_key": [],
+                "to_secret": k256::SecretKey::random(&mut OsRng).to_jwk(),
+                "delegation_chain": [],
+            }))
+            .unwrap(),
+            start_time: 4102444800,
+            end_time: 4102448400,
+            verification_tokens: Vec::new(),
+        };
+
+        let ApiError::Validation(errors) = ApiError::from(body.try_into_domain(Principal::anonymous()).unwrap_err()) else {
+            panic!("expected a validation error");
+        };
+
+        let fields: Vec<_> = errors.iter().map(|e| (e.field, e.code)).collect();
+        assert_eq!(
+            fields,
+            [
+                ("email_address", "invalid_email"),
+                ("pan", "invalid_pan"),
+                ("mobile_number", "invalid_mobile_number"),
+                ("driving_licence_expiry", "licence_expires_before_end_time"),
+                ("delegated_identity", "delegation_chain_invalid"),
+            ]
+        );
+        assert_eq!(errors[1].message, "ABC is not a valid PAN");
+    }
 }
@@ -0,0 +1,157 @@
+/*!
+   Module `data_requests` specifies HTTP handlers for staff to answer customers exercising their
+   rights under the Digital Personal Data Protection Act: exporting and erasing their data.
+*/
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use candid::Principal;
+use serde::Deserialize;
+
+use super::create_transaction::{ApiError, ApiSuccess, FieldError};
+use crate::domain::transactions::models::data_request::{
+    CustomerDataExport, DataRequestError, DataRequestKind, DataSubject, ErasureReport,
+};
+use crate::domain::transactions::models::transaction::EmailAddress;
+use crate::domain::transactions::ports::TransactionService;
+use crate::inbound::auth::AdminAuth;
+use crate::inbound::http::AppState;
+
+impl From<DataRequestError> for ApiError {
+    fn from(e: DataRequestError) -> Self {
+        match e {
+            DataRequestError::Storage(cause) => {
+                tracing::error!("Data subject request failed: {:?}", cause);
+                Self::InternalServerError("Failed to process data subject request".to_string())
+            }
+        }
+    }
+}
+
+/// The customer a request is about: either `email_address` or `principal`.
+///
+/// Sent in the body rather than the path, so that it stays out of access logs.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DataSubjectHttpRequestBody {
+    pub email_address: Option<String>,
+    pub principal: Option<String>,
+}
+
+impl DataSubjectHttpRequestBody {
+    fn try_into_domain(self) -> Result<DataSubject, ApiError> {
+        match (self.email_address, self.principal) {
+            (Some(email_address), None) => EmailAddress::new(&email_address)
+                .map(DataSubject::Email)
+                .map_err(|e| ApiError::Validation(vec![FieldError::new("email_address", &e)])),
+            (None, Some(principal)) => Principal::from_text(principal.trim())
+                .map(DataSubject::Principal)
+                .map_err(|e| ApiError::UnprocessableEntity(format!("Invalid principal: {e}"))),
+            _ => Err(ApiError::UnprocessableEntity(
+                "Provide either email_address or principal".to_string(),
+            )),
+        }
+    }
+}
+
+/// Check the admin token and read the subject of a `request`. A request refused here is
+/// recorded with a fixed reason, as what was sent may be a customer's details.
+async fn accepted_subject<TS: TransactionService>(
+    state: &AppState<TS>,
+    request: DataRequestKind,
+    auth: Result<AdminAuth, ApiError>,
+    body: Result<Json<DataSubjectHttpRequestBody>, JsonRejection>,
+) -> Result<DataSubject, ApiError> {
+    let subject = auth
+        .map_err(|e| ("unauthorized", e))
+        .and_then(|_| body.map_err(|rejection| ("invalid_body", ApiError::UnprocessableEntity(rejection.body_text()))))
+        .and_then(|Json(body)| body.try_into_domain().map_err(|e| ("invalid_subject", e)));
+
+    match subject {
+        Ok(subject) => Ok(subject),
+        Err((reason, e)) => {
+            state.transaction_service.refuse_data_request(request, reason).await;
+            Err(e)
+        }
+    }
+}
+
+/// Export everything stored about a customer as a JSON bundle of their bookings, documents,
+/// notifications and payments. Every request, refused ones included, is written to the audit log
+/// and the record of requests.
+///
+/// # Responses
+///
+/// - 200 OK: the export, empty if nothing is stored about the customer.
+/// - 401 Unauthorized: the admin token was missing or wrong.
+/// - 422 Unprocessable entity: the body is not JSON naming a valid email address or principal.
+pub async fn export_customer_data<TS: TransactionService>(
+    auth: Result<AdminAuth, ApiError>,
+    State(state): State<AppState<TS>>,
+    body: Result<Json<DataSubjectHttpRequestBody>, JsonRejection>,
+) -> Result<ApiSuccess<CustomerDataExport>, ApiError> {
+    let subject = accepted_subject(&state, DataRequestKind::Export, auth, body).await?;
+
+    state
+        .transaction_service
+        .export_customer_data(&subject)
+        .await
+        .map_err(ApiError::from)
+        .map(|export| ApiSuccess::new(StatusCode::OK, export))
+}
+
+/// Erase a customer's personal data, keeping the financial records of their bookings. Erasing
+/// again is harmless. Every request, refused ones included, is written to the audit log and the
+/// record of requests.
+///
+/// # Responses
+///
+/// - 200 OK: the bookings that were anonymised and the number of documents deleted.
+/// - 401 Unauthorized: the admin token was missing or wrong.
+/// - 422 Unprocessable entity: the body is not JSON naming a valid email address or principal.
+pub async fn erase_customer_data<TS: TransactionService>(
+    auth: Result<AdminAuth, ApiError>,
+    State(state): State<AppState<TS>>,
+    body: Result<Json<DataSubjectHttpRequestBody>, JsonRejection>,
+) -> Result<ApiSuccess<ErasureReport>, ApiError> {
+    let subject = accepted_subject(&state, DataRequestKind::Erasure, auth, body).await?;
+
+    state
+        .transaction_service
+        .erase_customer_data(&subject)
+        .await
+        .map_err(ApiError::from)
+        .map(|report| ApiSuccess::new(StatusCode::OK, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inbound::handlers::create_transaction::tests::{idle_service, test_state};
+
+    fn body(email_address: &str) -> Result<Json<DataSubjectHttpRequestBody>, JsonRejection> {
+        Ok(Json(DataSubjectHttpRequestBody { email_address: Some(email_address.to_string()), principal: None }))
+    }
+
+    #[tokio::test]
+    async fn test_refused_requests_are_recorded_without_the_subject() {
+        let service = idle_service();
+        let refused = service.refused_data_requests.clone();
+        let unauthorized = Err(ApiError::Unauthorized("Invalid bearer token".to_string()));
+
+        let actual = export_customer_data(unauthorized, test_state(service.clone()), body("test@example.com")).await;
+        assert!(matches!(actual, Err(ApiError::Unauthorized(_))), "expected 401, but got {:?}", actual);
+
+        let actual = erase_customer_data(Ok(AdminAuth), test_state(service), body("not an email")).await;
+        assert!(matches!(actual, Err(ApiError::Validation(_))), "expected 422, but got {:?}", actual);
+
+        assert_eq!(
+            *refused.lock().unwrap(),
+            [
+                (DataRequestKind::Export, "unauthorized".to_string()),
+                (DataRequestKind::Erasure, "invalid_subject".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,112 @@
+/*!
+   Module `documents` specifies HTTP handlers for uploading a booking's KYC documents, and for
+   staff to retrieve them.
+*/
+
+use axum::extract::multipart::MultipartError;
+use axum::extract::{Multipart, Path, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+
+use super::create_transaction::{check_booking_owner, ApiError, ApiSuccess, FieldError};
+use crate::domain::transactions::models::document::{Document, DocumentError, DocumentKind};
+use crate::domain::transactions::ports::TransactionService;
+use crate::inbound::auth::{AdminAuth, SessionAuth};
+use crate::inbound::http::AppState;
+
+impl From<DocumentError> for ApiError {
+    fn from(e: DocumentError) -> Self {
+        match &e {
+            DocumentError::InvalidKind { .. } => Self::Validation(vec![FieldError::new("kind", &e)]),
+            DocumentError::UnsupportedType { .. } | DocumentError::Empty | DocumentError::TooLarge { .. } => {
+                Self::Validation(vec![FieldError::new("file", &e)])
+            }
+            DocumentError::BookingNotFound { .. } | DocumentError::NotFound { .. } => Self::NotFound(e.to_string()),
+            DocumentError::Storage(cause) => {
+                tracing::error!("Document storage failed: {:?}", cause);
+                Self::InternalServerError("Failed to store document".to_string())
+            }
+        }
+    }
+}
+
+impl From<MultipartError> for ApiError {
+    fn from(e: MultipartError) -> Self {
+        Self::UnprocessableEntity(format!("Invalid multipart body: {}", e.body_text()))
+    }
+}
+
+/// Upload a KYC document for a booking as `multipart/form-data`, with a `kind` field
+/// (`driving_licence`, `aadhaar` or `pan`) followed by the `file` itself.
+///
+/// # Responses
+///
+/// - 201 Created: the document was encrypted and stored.
+/// - 401 Unauthorized: the session token was missing, invalid or expired.
+/// - 403 Forbidden: the booking is not the signed-in principal's.
+/// - 404 Not Found: there is no booking with this ID.
+/// - 422 Unprocessable entity: a field is missing, or the file is not a JPEG, PNG or PDF of at
+///   most 5 MiB.
+pub async fn upload_document<TS: TransactionService>(
+    session: SessionAuth,
+    State(state): State<AppState<TS>>,
+    Path(booking_id): Path<u64>,
+    mut multipart: Multipart,
+) -> Result<ApiSuccess<Document>, ApiError> {
+    check_booking_owner(&state, booking_id, &session).await?;
+
+    let mut kind = None;
+    let mut file = None;
+    while let Some(field) = multipart.next_field().await? {
+        match field.name() {
+            Some("kind") => kind = Some(field.text().await?.parse::<DocumentKind>()?),
+            Some("file") => {
+                let content_type = field.content_type().map(str::to_string);
+                file = Some((content_type, field.bytes().await?.to_vec()));
+            }
+            _ => {}
+        }
+    }
+
+    let missing = |field| ApiError::UnprocessableEntity(format!("Missing multipart field {field}"));
+    let kind = kind.ok_or_else(|| missing("kind"))?;
+    let (content_type, contents) = file.ok_or_else(|| missing("file"))?;
+
+    state
+        .transaction_service
+        .upload_document(booking_id, kind, content_type, contents)
+        .await
+        .map_err(ApiError::from)
+        .map(|document| ApiSuccess::new(StatusCode::CREATED, document))
+}
+
+/// Download a decrypted KYC document. Only for staff holding the admin token; every download is
+/// written to the audit log.
+///
+/// # Responses
+///
+/// - 200 OK: the document, with its content type.
+/// - 401 Unauthorized: the admin token was missing or wrong.
+/// - 404 Not Found: the booking has no document with this ID.
+pub async fn get_document<TS: TransactionService>(
+    _: AdminAuth,
+    State(state): State<AppState<TS>>,
+    Path((booking_id, document_id)): Path<(u64, String)>,
+) -> Result<Response, ApiError> {
+    let (document, contents) = state.transaction_service.get_document(booking_id, &document_id).await?;
+
+    let disposition = format!(
+        "attachment; filename=\"{}.{}\"",
+        document.id(),
+        document.content_type().extension()
+    );
+    let headers = [
+        (header::CONTENT_TYPE, HeaderValue::from_static(document.content_type().mime())),
+        (
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_str(&disposition).map_err(|e| ApiError::InternalServerError(e.to_string()))?,
+        ),
+        (header::CACHE_CONTROL, HeaderValue::from_static("no-store")),
+    ];
+    Ok((StatusCode::OK, headers, contents).into_response())
+}
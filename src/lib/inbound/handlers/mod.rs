@@ -1 +1,8 @@
-pub(super) mod create_transaction;
\ No newline at end of file
+pub(super) mod create_transaction;
+pub(super) mod admin;
+pub(super) mod availability;
+pub(super) mod bookings;
+pub(super) mod documents;
+pub(super) mod verification;
+pub(super) mod data_requests;
+pub(super) mod session;
\ No newline at end of file
@@ -0,0 +1,71 @@
+/*!
+   Module `session` specifies HTTP handlers for customers to sign in with their Internet Computer
+   identity, in exchange for a session token that authenticates their booking requests.
+*/
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use ic_agent::identity::SignedDelegation;
+use serde::Deserialize;
+
+use super::create_transaction::{ApiError, ApiSuccess};
+use crate::domain::transactions::ports::TransactionService;
+use crate::identity::session::{SessionError, SessionNonce, SessionToken};
+use crate::inbound::http::AppState;
+
+impl From<SessionError> for ApiError {
+    fn from(e: SessionError) -> Self {
+        Self::Unauthorized(e.to_string())
+    }
+}
+
+/// A nonce from `POST /api/auth/nonce`, signed by the customer's identity.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateSessionHttpRequestBody {
+    pub nonce: String,
+    /// The DER encoded public key of the identity, whose principal the session is for.
+    pub public_key: Vec<u8>,
+    /// Delegations from `public_key` to the key that made `signature`, empty if `public_key`
+    /// signed the nonce itself. Each must allow calls to the backend canister.
+    #[serde(default)]
+    pub delegation_chain: Vec<SignedDelegation>,
+    /// The signature over the bytes of `nonce`.
+    pub signature: Vec<u8>,
+}
+
+/// Issue a nonce to sign in with.
+///
+/// # Responses
+///
+/// - 200 OK: the nonce, to be signed and sent to `POST /api/auth/session` before it expires.
+pub async fn create_session_nonce<TS: TransactionService>(
+    State(state): State<AppState<TS>>,
+) -> Result<ApiSuccess<SessionNonce>, ApiError> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(anyhow::Error::from)?.as_secs();
+
+    Ok(ApiSuccess::new(StatusCode::OK, state.session_issuer.issue_nonce(now)))
+}
+
+/// Exchange a signed nonce for a session token, to be sent as a bearer token on the booking and
+/// document routes.
+///
+/// # Responses
+///
+/// - 201 Created: the session token, its principal and expiry.
+/// - 401 Unauthorized: the nonce is invalid or expired, or the signature or delegations don't
+///   prove control of `public_key`.
+pub async fn create_session<TS: TransactionService>(
+    State(state): State<AppState<TS>>,
+    Json(body): Json<CreateSessionHttpRequestBody>,
+) -> Result<ApiSuccess<SessionToken>, ApiError> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(anyhow::Error::from)?.as_secs();
+
+    let session = state
+        .session_issuer
+        .sign_in(&body.nonce, &body.public_key, &body.delegation_chain, &body.signature, state.backend_id, now)?;
+    tracing::info!(target: "audit", principal = %session.principal, "Customer signed in");
+    Ok(ApiSuccess::new(StatusCode::CREATED, session))
+}
@@ -0,0 +1,155 @@
+/*!
+   Module `verification` specifies HTTP handlers for verifying a customer's email address or
+   mobile number with a one-time password, before they may request a payment link.
+*/
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use super::create_transaction::{ApiError, ApiSuccess, FieldError};
+use crate::domain::transactions::models::phone_number::{PhoneNumber, PhoneNumberError};
+use crate::domain::transactions::models::transaction::EmailAddress;
+use crate::domain::transactions::models::verification::{Contact, VerificationError, OTP_TTL};
+use crate::domain::transactions::ports::TransactionService;
+use crate::inbound::client_ip::ClientIp;
+use crate::inbound::http::AppState;
+
+impl From<&VerificationError> for ApiError {
+    fn from(e: &VerificationError) -> Self {
+        match e {
+            VerificationError::RateLimited { retry_after } => Self::TooManyRequests {
+                message: e.to_string(),
+                retry_after: *retry_after,
+            },
+            VerificationError::InvalidCode | VerificationError::Expired | VerificationError::TooManyAttempts => {
+                Self::Validation(vec![FieldError::new("code", e)])
+            }
+            VerificationError::NotVerified { contact: Contact::Email(_) } => {
+                Self::Validation(vec![FieldError::new("email_address", e)])
+            }
+            VerificationError::NotVerified { contact: Contact::Phone(_) } => {
+                Self::Validation(vec![FieldError::new("mobile_number", e)])
+            }
+            VerificationError::Send(cause) => {
+                Self::InternalServerError(format!("Failed to send verification code: {}", cause))
+            }
+            VerificationError::Storage(cause) => {
+                Self::InternalServerError(format!("Failed to access verification store: {}", cause))
+            }
+        }
+    }
+}
+
+/// The contact to verify: either `email_address`, or `country_code` and `mobile_number`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ContactHttpRequestBody {
+    pub email_address: Option<String>,
+    pub country_code: Option<u16>,
+    /// The national number, or the full number in E.164 form, e.g. `+919876543210`.
+    pub mobile_number: Option<String>,
+}
+
+impl ContactHttpRequestBody {
+    fn try_into_domain(self) -> Result<Contact, ApiError> {
+        match (self.email_address, self.mobile_number) {
+            (Some(email_address), None) => EmailAddress::new(&email_address)
+                .map(Contact::Email)
+                .map_err(|e| ApiError::Validation(vec![FieldError::new("email_address", &e)])),
+            (None, Some(mobile_number)) => {
+                let country_code = self.country_code.ok_or_else(|| {
+                    ApiError::UnprocessableEntity("country_code is required with mobile_number".to_string())
+                })?;
+                PhoneNumber::new(country_code, &mobile_number).map(Contact::Phone).map_err(|e| {
+                    let field = match e {
+                        PhoneNumberError::InvalidCountryCode { .. } => "country_code",
+                        PhoneNumberError::InvalidNumber { .. } => "mobile_number",
+                    };
+                    ApiError::Validation(vec![FieldError::new(field, &e)])
+                })
+            }
+            _ => Err(ApiError::UnprocessableEntity(
+                "Provide either email_address, or country_code and mobile_number".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ConfirmVerificationHttpRequestBody {
+    #[serde(flatten)]
+    pub contact: ContactHttpRequestBody,
+    pub code: String,
+}
+
+/// The response body data field after a code was sent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerificationSentResponseData {
+    channel: &'static str,
+    /// Seconds until the code expires.
+    expires_in: u64,
+}
+
+/// The response body data field after a code was confirmed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerificationConfirmedResponseData {
+    /// Pass to `POST /api/payment` in `verification_tokens`.
+    verification_token: String,
+    expires_at: u64,
+}
+
+/// Send a six digit code to an email address or, by SMS, to a mobile number.
+///
+/// # Responses
+///
+/// - 202 Accepted: the code was sent and expires in `expires_in` seconds.
+/// - 422 Unprocessable entity: the contact details are invalid.
+/// - 429 Too Many Requests: a code was sent to this contact, or at this client's request, too
+///   recently; see `Retry-After`.
+pub async fn send_verification<TS: TransactionService>(
+    ClientIp(client): ClientIp,
+    State(state): State<AppState<TS>>,
+    Json(body): Json<ContactHttpRequestBody>,
+) -> Result<ApiSuccess<VerificationSentResponseData>, ApiError> {
+    let contact = body.try_into_domain()?;
+
+    state
+        .transaction_service
+        .send_verification(&contact, client)
+        .await
+        .map_err(|e| ApiError::from(&e))
+        .map(|_| {
+            let data = VerificationSentResponseData {
+                channel: contact.channel(),
+                expires_in: OTP_TTL.as_secs(),
+            };
+            ApiSuccess::new(StatusCode::ACCEPTED, data)
+        })
+}
+
+/// Confirm the code sent to a contact, in exchange for a verification token.
+///
+/// # Responses
+///
+/// - 200 OK: the code was correct.
+/// - 422 Unprocessable entity: the code is wrong or expired, or was guessed too often.
+pub async fn confirm_verification<TS: TransactionService>(
+    State(state): State<AppState<TS>>,
+    Json(body): Json<ConfirmVerificationHttpRequestBody>,
+) -> Result<ApiSuccess<VerificationConfirmedResponseData>, ApiError> {
+    let contact = body.contact.try_into_domain()?;
+
+    state
+        .transaction_service
+        .confirm_verification(&contact, &body.code)
+        .await
+        .map_err(|e| ApiError::from(&e))
+        .map(|(token, expires_at)| {
+            let data = VerificationConfirmedResponseData {
+                verification_token: token.as_str().to_string(),
+                expires_at,
+            };
+            ApiSuccess::new(StatusCode::OK, data)
+        })
+}
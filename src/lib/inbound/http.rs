@@ -6,7 +6,10 @@
 use std::{net::SocketAddr, sync::Arc};
 
 use anyhow::Context;
+use candid::Principal;
 use axum::{
+    extract::DefaultBodyLimit,
+    http::HeaderName,
     routing::{get, post},
     Router,
 };
@@ -14,19 +17,40 @@ use reqwest::StatusCode;
 use tokio::net;
 use tower_http::cors::CorsLayer;
 
+use super::handlers::admin::{list_admin_keys, list_dead_letters, replay_dead_letter, rotate_admin_key};
+use super::handlers::availability::get_car_availability;
+use super::handlers::bookings::cancel_booking;
 use super::handlers::create_transaction::{create_payment_link, create_transaction, get_principal};
+use super::handlers::data_requests::{erase_customer_data, export_customer_data};
+use super::handlers::documents::{get_document, upload_document};
+use super::handlers::session::{create_session, create_session_nonce};
+use super::handlers::verification::{confirm_verification, send_verification};
+use crate::domain::transactions::models::document::MAX_DOCUMENT_SIZE;
+use crate::identity::session::SessionIssuer;
 use crate::domain::transactions::ports::TransactionService; // Update this to your correct path // Update this to your correct path
 
 /// Configuration for the HTTP server.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct HttpServerConfig<'a> {
     pub port: &'a str,
+    /// Bearer token for the `/api/admin` routes, which reject every request when `None`.
+    pub admin_api_token: Option<&'a str>,
+    /// Signs the session tokens customers present on the booking and document routes.
+    pub session_issuer: SessionIssuer,
+    /// The canister customer delegations must allow calls to.
+    pub backend_id: Principal,
+    /// The header a proxy in front of the server puts the client's address in, e.g. `Fly-Client-IP`.
+    pub client_ip_header: Option<&'a str>,
 }
 
 #[derive(Debug, Clone)]
 /// The global application state shared between all request handlers.
 pub struct AppState<TS: TransactionService> {
     pub transaction_service: Arc<TS>,
+    pub admin_api_token: Option<Arc<str>>,
+    pub session_issuer: Arc<SessionIssuer>,
+    pub backend_id: Principal,
+    pub client_ip_header: Option<HeaderName>,
 }
 
 /// The application's HTTP server. The underlying HTTP package is opaque to module consumers.
@@ -51,6 +75,14 @@ impl HttpServer {
         // Construct dependencies to inject into handlers.
         let state = AppState {
             transaction_service: Arc::new(transaction_service),
+            admin_api_token: config.admin_api_token.map(Arc::from),
+            session_issuer: Arc::new(config.session_issuer),
+            backend_id: config.backend_id,
+            client_ip_header: config
+                .client_ip_header
+                .map(HeaderName::try_from)
+                .transpose()
+                .context("Invalid client IP header")?,
         };
 
         // let cors = CorsLayer::new()
@@ -67,7 +99,7 @@ impl HttpServer {
             .layer(trace_layer)
             .with_state(state);
 
-        tracing::info!("Config on {:?}", config);
+        tracing::info!("Config on port {}", config.port);
 
         let addr = SocketAddr::from((
             [0, 0, 0, 0, 0, 0, 0, 0],
@@ -85,7 +117,8 @@ impl HttpServer {
     /// Runs the HTTP server.
     pub async fn run(self) -> anyhow::Result<()> {
         tracing::debug!("listening on {}", self.listener.local_addr().unwrap());
-        axum::serve(self.listener, self.router)
+        // Handlers that rate limit by client fall back to the peer address.
+        axum::serve(self.listener, self.router.into_make_service_with_connect_info::<SocketAddr>())
             .await
             .context("received error from running server")?;
         Ok(())
@@ -97,6 +130,29 @@ fn api_routes<TS: TransactionService>() -> Router<AppState<TS>> {
     .route("/transactions", post(create_transaction::<TS>)) // Route for creating transactions
     .route("/payment", post(create_payment_link::<TS>)) // Route for creating transactions
     .route("/principal", get(get_principal::<TS>)) // Route for creating transactions
+    .route("/cars/:car_id/availability", get(get_car_availability::<TS>))
+    .route("/verify/send", post(send_verification::<TS>))
+    .route("/verify/confirm", post(confirm_verification::<TS>))
+    .route("/auth/nonce", post(create_session_nonce::<TS>))
+    .route("/auth/session", post(create_session::<TS>))
+    .route("/bookings/:booking_id/cancel", post(cancel_booking::<TS>))
+    .route(
+        "/bookings/:booking_id/documents",
+        // Leave room for the multipart boundaries and the other fields.
+        post(upload_document::<TS>).layer(DefaultBodyLimit::max(MAX_DOCUMENT_SIZE + 64 * 1024)),
+    )
+    .nest("/admin", admin_routes())
+}
+
+fn admin_routes<TS: TransactionService>() -> Router<AppState<TS>> {
+    Router::new()
+    .route("/outbox/dead-letters", get(list_dead_letters::<TS>))
+    .route("/outbox/dead-letters/:id/replay", post(replay_dead_letter::<TS>))
+    .route("/bookings/:booking_id/documents/:document_id", get(get_document::<TS>))
+    .route("/data-requests/export", post(export_customer_data::<TS>))
+    .route("/data-requests/erase", post(erase_customer_data::<TS>))
+    .route("/keys", get(list_admin_keys::<TS>))
+    .route("/keys/rotate", post(rotate_admin_key::<TS>))
 }
 
 async fn health_route() -> (StatusCode, &'static str) {
@@ -1,3 +1,5 @@
 pub mod http;
 pub(super) mod handlers;
-pub(super) mod responses;
\ No newline at end of file
+pub(super) mod responses;
+pub(super) mod auth;
+pub(super) mod client_ip;
\ No newline at end of file
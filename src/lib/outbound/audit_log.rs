@@ -0,0 +1,62 @@
+use crate::domain::transactions::models::booking::Booking;
+use crate::domain::transactions::models::event::{BookingEvent, TransactionEvent};
+use crate::domain::transactions::ports::{BookingEventHandler, HandlerFuture, TransactionEventHandler};
+
+/// Writes every booking and transaction event to the `audit` tracing target.
+///
+/// Only identifiers and statuses are logged, never the customer details held in the [Booking].
+#[derive(Debug, Clone, Default)]
+pub struct AuditLog;
+
+impl AuditLog {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl BookingEventHandler for AuditLog {
+    fn name(&self) -> &'static str {
+        "audit"
+    }
+
+    fn handle<'a>(&'a self, event: &'a BookingEvent, booking: &'a Booking) -> HandlerFuture<'a> {
+        Box::pin(async move {
+            tracing::info!(
+                target: "audit",
+                event = event.name(),
+                booking_id = event.booking_id(),
+                status = booking.status().as_str(),
+                "booking event"
+            );
+            Ok(())
+        })
+    }
+}
+
+impl TransactionEventHandler for AuditLog {
+    fn name(&self) -> &'static str {
+        "audit"
+    }
+
+    fn handle<'a>(&'a self, event: &'a TransactionEvent) -> HandlerFuture<'a> {
+        Box::pin(async move {
+            match event {
+                TransactionEvent::TransactionCreated { booking_id } => {
+                    tracing::info!(target: "audit", event = event.name(), booking_id, "transaction event");
+                }
+                TransactionEvent::TransactionCreationFailed { booking_id, reason }
+                | TransactionEvent::PaymentLinkCreationFailed { booking_id, reason } => {
+                    tracing::warn!(target: "audit", event = event.name(), booking_id, reason, "transaction event");
+                }
+                TransactionEvent::DocumentUploaded { booking_id, document_id }
+                | TransactionEvent::DocumentAccessed { booking_id, document_id } => {
+                    tracing::info!(target: "audit", event = event.name(), booking_id, document_id, "transaction event");
+                }
+                TransactionEvent::CanisterCallRetried { method, retries, succeeded } => {
+                    tracing::info!(target: "audit", event = event.name(), method, retries, succeeded, "transaction event");
+                }
+            }
+            Ok(())
+        })
+    }
+}
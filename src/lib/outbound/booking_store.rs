@@ -0,0 +1,221 @@
+use std::future::Future;
+
+use crate::domain::transactions::models::availability::{AvailabilityRange, Interval};
+use crate::domain::transactions::models::booking::{Booking, BookingRepositoryError};
+use crate::domain::transactions::models::data_request::DataRequestRecord;
+use crate::domain::transactions::models::event::BookingEvent;
+use crate::domain::transactions::models::hold::{SlotHold, SlotHoldError};
+use crate::domain::transactions::models::outbox::OutboxEvent;
+use crate::domain::transactions::models::phone_number::PhoneNumber;
+use crate::domain::transactions::models::transaction::EmailAddress;
+use crate::domain::transactions::models::verification::{OtpChallenge, VerificationError};
+use crate::domain::transactions::ports::{
+    AdminKeyRepository, BookingRepository, DataRequestRepository, OutboxRepository, SlotHoldRepository, VerificationRepository,
+};
+use crate::identity::admin::AdminIdentity;
+
+use super::in_memory::InMemoryBookingRepository;
+use super::postgres::{PostgresBookingRepository, PostgresConfig};
+
+/// Bookings re-encrypted per transaction by [BookingStore::reencrypt_customer_details].
+const REENCRYPT_BATCH_SIZE: u32 = 100;
+
+/// Which [BookingRepository] implementation the service should use.
+#[derive(Debug, Clone)]
+pub enum BookingStoreConfig {
+    InMemory,
+    Postgres(PostgresConfig),
+}
+
+/// The [BookingRepository] selected at startup from [BookingStoreConfig].
+#[derive(Debug, Clone)]
+pub enum BookingStore {
+    InMemory(InMemoryBookingRepository),
+    Postgres(PostgresBookingRepository),
+}
+
+impl BookingStore {
+    pub async fn new(config: &BookingStoreConfig) -> anyhow::Result<Self> {
+        match config {
+            BookingStoreConfig::InMemory => Ok(Self::InMemory(InMemoryBookingRepository::new())),
+            BookingStoreConfig::Postgres(config) => {
+                Ok(Self::Postgres(PostgresBookingRepository::new(config).await?))
+            }
+        }
+    }
+
+    /// Re-encrypt stored customer details that are not sealed under the active key. Bookings
+    /// held in memory are never written out, so there is nothing to do for them.
+    pub async fn reencrypt_customer_details(&self) -> anyhow::Result<u64> {
+        match self {
+            BookingStore::InMemory(_) => Ok(0),
+            BookingStore::Postgres(store) => store.reencrypt_bookings(REENCRYPT_BATCH_SIZE).await,
+        }
+    }
+}
+
+impl BookingRepository for BookingStore {
+    async fn save_booking(&self, booking: &Booking, events: &[BookingEvent]) -> Result<(), BookingRepositoryError> {
+        match self {
+            BookingStore::InMemory(store) => store.save_booking(booking, events).await,
+            BookingStore::Postgres(store) => store.save_booking(booking, events).await,
+        }
+    }
+
+    async fn find_booking(&self, booking_id: u64) -> Result<Option<Booking>, BookingRepositoryError> {
+        match self {
+            BookingStore::InMemory(store) => store.find_booking(booking_id).await,
+            BookingStore::Postgres(store) => store.find_booking(booking_id).await,
+        }
+    }
+
+    async fn find_bookings_by_email(&self, email: &EmailAddress) -> Result<Vec<Booking>, BookingRepositoryError> {
+        match self {
+            BookingStore::InMemory(store) => store.find_bookings_by_email(email).await,
+            BookingStore::Postgres(store) => store.find_bookings_by_email(email).await,
+        }
+    }
+
+    async fn find_bookings_by_phone_number(&self, phone_number: &PhoneNumber) -> Result<Vec<Booking>, BookingRepositoryError> {
+        match self {
+            BookingStore::InMemory(store) => store.find_bookings_by_phone_number(phone_number).await,
+            BookingStore::Postgres(store) => store.find_bookings_by_phone_number(phone_number).await,
+        }
+    }
+
+    async fn find_bookings_by_principal(&self, customer_principal: &str) -> Result<Vec<Booking>, BookingRepositoryError> {
+        match self {
+            BookingStore::InMemory(store) => store.find_bookings_by_principal(customer_principal).await,
+            BookingStore::Postgres(store) => store.find_bookings_by_principal(customer_principal).await,
+        }
+    }
+}
+
+impl OutboxRepository for BookingStore {
+    async fn due_outbox_events(&self, now: u64, limit: u32) -> Result<Vec<OutboxEvent>, BookingRepositoryError> {
+        match self {
+            BookingStore::InMemory(store) => store.due_outbox_events(now, limit).await,
+            BookingStore::Postgres(store) => store.due_outbox_events(now, limit).await,
+        }
+    }
+
+    async fn find_outbox_event(&self, id: u64) -> Result<Option<OutboxEvent>, BookingRepositoryError> {
+        match self {
+            BookingStore::InMemory(store) => store.find_outbox_event(id).await,
+            BookingStore::Postgres(store) => store.find_outbox_event(id).await,
+        }
+    }
+
+    async fn find_outbox_events_by_booking(&self, booking_id: u64) -> Result<Vec<OutboxEvent>, BookingRepositoryError> {
+        match self {
+            BookingStore::InMemory(store) => store.find_outbox_events_by_booking(booking_id).await,
+            BookingStore::Postgres(store) => store.find_outbox_events_by_booking(booking_id).await,
+        }
+    }
+
+    async fn dead_lettered_outbox_events(&self) -> Result<Vec<OutboxEvent>, BookingRepositoryError> {
+        match self {
+            BookingStore::InMemory(store) => store.dead_lettered_outbox_events().await,
+            BookingStore::Postgres(store) => store.dead_lettered_outbox_events().await,
+        }
+    }
+
+    async fn update_outbox_event(&self, event: &OutboxEvent) -> Result<(), BookingRepositoryError> {
+        match self {
+            BookingStore::InMemory(store) => store.update_outbox_event(event).await,
+            BookingStore::Postgres(store) => store.update_outbox_event(event).await,
+        }
+    }
+
+    async fn run_dispatch_exclusive<F>(&self, job: F) -> Result<Option<F::Output>, BookingRepositoryError>
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        match self {
+            BookingStore::InMemory(store) => store.run_dispatch_exclusive(job).await,
+            BookingStore::Postgres(store) => store.run_dispatch_exclusive(job).await,
+        }
+    }
+}
+
+impl SlotHoldRepository for BookingStore {
+    async fn place_hold(&self, hold: &SlotHold, now: u64) -> Result<(), SlotHoldError> {
+        match self {
+            BookingStore::InMemory(store) => store.place_hold(hold, now).await,
+            BookingStore::Postgres(store) => store.place_hold(hold, now).await,
+        }
+    }
+
+    async fn release_hold(&self, booking_id: u64) -> Result<(), BookingRepositoryError> {
+        match self {
+            BookingStore::InMemory(store) => store.release_hold(booking_id).await,
+            BookingStore::Postgres(store) => store.release_hold(booking_id).await,
+        }
+    }
+
+    async fn held_intervals(&self, car_id: u64, range: &AvailabilityRange, now: u64) -> Result<Vec<Interval>, BookingRepositoryError> {
+        match self {
+            BookingStore::InMemory(store) => store.held_intervals(car_id, range, now).await,
+            BookingStore::Postgres(store) => store.held_intervals(car_id, range, now).await,
+        }
+    }
+}
+
+impl VerificationRepository for BookingStore {
+    async fn issue_otp<F>(&self, contact: &str, issue: F) -> Result<OtpChallenge, VerificationError>
+    where
+        F: FnOnce(Option<&OtpChallenge>) -> Result<OtpChallenge, VerificationError> + Send,
+    {
+        match self {
+            BookingStore::InMemory(store) => store.issue_otp(contact, issue).await,
+            BookingStore::Postgres(store) => store.issue_otp(contact, issue).await,
+        }
+    }
+
+    async fn record_otp_attempt(&self, contact: &str) -> Result<Option<OtpChallenge>, VerificationError> {
+        match self {
+            BookingStore::InMemory(store) => store.record_otp_attempt(contact).await,
+            BookingStore::Postgres(store) => store.record_otp_attempt(contact).await,
+        }
+    }
+
+    async fn consume_otp(&self, contact: &str) -> Result<(), VerificationError> {
+        match self {
+            BookingStore::InMemory(store) => store.consume_otp(contact).await,
+            BookingStore::Postgres(store) => store.consume_otp(contact).await,
+        }
+    }
+
+    async fn remove_otp(&self, contact: &str) -> Result<(), VerificationError> {
+        match self {
+            BookingStore::InMemory(store) => store.remove_otp(contact).await,
+            BookingStore::Postgres(store) => store.remove_otp(contact).await,
+        }
+    }
+}
+
+impl DataRequestRepository for BookingStore {
+    async fn record_data_request(&self, record: &DataRequestRecord) -> Result<(), BookingRepositoryError> {
+        match self {
+            BookingStore::InMemory(store) => store.record_data_request(record).await,
+            BookingStore::Postgres(store) => store.record_data_request(record).await,
+        }
+    }
+}
+
+impl AdminKeyRepository for BookingStore {
+    async fn save_admin_key(&self, admin: &AdminIdentity) -> Result<(), BookingRepositoryError> {
+        match self {
+            BookingStore::InMemory(store) => store.save_admin_key(admin).await,
+            BookingStore::Postgres(store) => store.save_admin_key(admin).await,
+        }
+    }
+
+    async fn find_admin_key(&self) -> Result<Option<AdminIdentity>, BookingRepositoryError> {
+        match self {
+            BookingStore::InMemory(store) => store.find_admin_key().await,
+            BookingStore::Postgres(store) => store.find_admin_key().await,
+        }
+    }
+}
@@ -0,0 +1,229 @@
+/*!
+   Module `canister_call` bounds calls to the backend canister in time and retries the failures
+   that are safe to retry, with jittered exponential backoff.
+
+   A failure is only retried if the call can't have run, e.g. because the replica was busy, or
+   if running it twice is harmless, as for queries. An update whose outcome is unknown, e.g.
+   because the connection dropped while waiting for the response, is never retried: it may have
+   reserved the car already.
+*/
+
+use std::future::Future;
+use std::time::Duration;
+
+use ic_agent::agent::RejectCode;
+use ic_agent::AgentError;
+use k256::elliptic_curve::rand_core::{OsRng, RngCore};
+use thiserror::Error;
+use tokio::time::Instant;
+
+use crate::domain::transactions::models::transaction::CreateTransactionError;
+
+/// Tuning for calls to the canister.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanisterCallConfig {
+    /// How long a call may take, retries included.
+    pub deadline: Duration,
+    /// Retries after the first attempt.
+    pub max_retries: u32,
+    /// Upper bound of the delay before the first retry; doubled for every further retry.
+    pub base_backoff: Duration,
+    /// Upper bound for the retry delay.
+    pub max_backoff: Duration,
+}
+
+impl Default for CanisterCallConfig {
+    fn default() -> Self {
+        Self {
+            deadline: Duration::from_secs(30),
+            max_retries: 3,
+            base_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(4),
+        }
+    }
+}
+
+impl CanisterCallConfig {
+    /// The delay before retry number `retry`, counting from 1. The delay is drawn at random
+    /// below the exponential bound, so that callers failing together don't retry together.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let bound = self.base_backoff.saturating_mul(factor).min(self.max_backoff);
+        bound.mul_f64(f64::from(OsRng.next_u32()) / f64::from(u32::MAX))
+    }
+
+    /// Run `call` until it succeeds, fails in a way that can't be retried for a call of `kind`,
+    /// or runs out of retries or time. Returns the outcome and the number of retries made.
+    pub async fn run<T, F, Fut>(&self, kind: CallKind, mut call: F) -> (Result<T, CanisterCallError>, u32)
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, AgentError>>,
+    {
+        let deadline = Instant::now() + self.deadline;
+        let mut retries = 0;
+        loop {
+            let error = match tokio::time::timeout_at(deadline, call()).await {
+                Ok(Ok(value)) => return (Ok(value), retries),
+                Ok(Err(error)) => error,
+                Err(_) => return (Err(CanisterCallError::Timeout(self.deadline)), retries),
+            };
+
+            let failure = Failure::of(&error);
+            let delay = self.backoff(retries + 1);
+            let retryable = failure == Failure::NotExecuted || (failure == Failure::Unknown && kind == CallKind::Query);
+            if !retryable || retries >= self.max_retries || Instant::now() + delay >= deadline {
+                return (Err(failure.into_error(error)), retries);
+            }
+
+            retries += 1;
+            tracing::debug!("Retrying a canister call in {:?} after: {}", delay, error);
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Whether a call only reads canister state, so that running it twice is harmless.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallKind {
+    Query,
+    Update,
+}
+
+/// How far a failed call got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Failure {
+    /// The network, the replica or the canister turned the call away before it ran.
+    NotExecuted,
+    /// The call may or may not have run.
+    Unknown,
+    /// The call ran, or was refused for good, and would fail the same way again.
+    Rejected,
+}
+
+impl Failure {
+    fn of(error: &AgentError) -> Self {
+        match error {
+            // The replica is busy or overloaded and didn't accept the message.
+            AgentError::HttpError(payload) if matches!(payload.status, 429 | 503) => Failure::NotExecuted,
+            AgentError::HttpError(payload) if payload.status >= 500 => Failure::Unknown,
+            // E.g. a full canister queue; any changes the call made were rolled back.
+            AgentError::CertifiedReject(reject) | AgentError::UncertifiedReject(reject)
+                if reject.reject_code == RejectCode::SysTransient =>
+            {
+                Failure::NotExecuted
+            }
+            AgentError::TransportError(error) if error.is_connect() => Failure::NotExecuted,
+            AgentError::TransportError(_) | AgentError::TimeoutWaitingForResponse() => Failure::Unknown,
+            _ => Failure::Rejected,
+        }
+    }
+
+    fn into_error(self, error: AgentError) -> CanisterCallError {
+        match self {
+            Failure::NotExecuted | Failure::Unknown => CanisterCallError::Unavailable(error.to_string()),
+            Failure::Rejected => CanisterCallError::Rejected(error.to_string()),
+        }
+    }
+}
+
+/// Errors that may occur while calling the canister.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CanisterCallError {
+    #[error("The canister is unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("The canister did not answer within {0:?}")]
+    Timeout(Duration),
+
+    #[error("The canister rejected the call: {0}")]
+    Rejected(String),
+}
+
+impl From<CanisterCallError> for CreateTransactionError {
+    fn from(e: CanisterCallError) -> Self {
+        match e {
+            CanisterCallError::Unavailable(_) | CanisterCallError::Timeout(_) => Self::CanisterCommunicationError(e.to_string()),
+            CanisterCallError::Rejected(reason) => Self::CanisterRejectedError(reason),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use ic_agent::agent::RejectResponse;
+    use ic_agent::agent_error::HttpErrorPayload;
+
+    use super::*;
+
+    fn config() -> CanisterCallConfig {
+        CanisterCallConfig {
+            deadline: Duration::from_secs(5),
+            max_retries: 3,
+            base_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        }
+    }
+
+    fn http_error(status: u16) -> AgentError {
+        AgentError::HttpError(HttpErrorPayload { status, content_type: None, content: vec![] })
+    }
+
+    fn reject(reject_code: RejectCode) -> AgentError {
+        AgentError::CertifiedReject(RejectResponse { reject_code, reject_message: "rejected".to_string(), error_code: None })
+    }
+
+    /// Run a call of `kind` that fails with `errors` in turn and then succeeds.
+    async fn run(kind: CallKind, errors: Vec<AgentError>) -> (Result<u32, CanisterCallError>, u32) {
+        let errors = std::sync::Mutex::new(errors.into_iter());
+        let attempts = AtomicU32::new(0);
+        config()
+            .run(kind, || async {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                match errors.lock().unwrap().next() {
+                    Some(error) => Err(error),
+                    None => Ok(attempt),
+                }
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_busy_replica_is_retried_for_updates() {
+        let (result, retries) = run(CallKind::Update, vec![http_error(503), reject(RejectCode::SysTransient)]).await;
+        assert_eq!(result, Ok(3));
+        assert_eq!(retries, 2);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_outcomes_are_retried_for_queries_only() {
+        let (result, retries) = run(CallKind::Query, vec![AgentError::TimeoutWaitingForResponse()]).await;
+        assert_eq!(result, Ok(2));
+        assert_eq!(retries, 1);
+
+        let (result, retries) = run(CallKind::Update, vec![AgentError::TimeoutWaitingForResponse()]).await;
+        assert!(matches!(result, Err(CanisterCallError::Unavailable(_))), "got {result:?}");
+        assert_eq!(retries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_rejections_and_exhausted_retries_are_returned() {
+        let (result, retries) = run(CallKind::Query, vec![reject(RejectCode::CanisterError)]).await;
+        assert!(matches!(result, Err(CanisterCallError::Rejected(_))), "got {result:?}");
+        assert_eq!(retries, 0);
+
+        let (result, retries) = run(CallKind::Query, (0..10).map(|_| http_error(429)).collect()).await;
+        assert!(matches!(result, Err(CanisterCallError::Unavailable(_))), "got {result:?}");
+        assert_eq!(retries, config().max_retries);
+    }
+
+    #[test]
+    fn test_backoff_is_bounded() {
+        let config = CanisterCallConfig::default();
+        for retry in 1..10 {
+            assert!(config.backoff(retry) <= config.max_backoff);
+        }
+        assert!(config.backoff(1) <= config.base_backoff);
+    }
+}
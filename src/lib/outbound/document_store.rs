@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use crate::domain::transactions::models::document::{DocumentError, DocumentId};
+use crate::domain::transactions::ports::DocumentStore;
+
+use super::filesystem_document_store::FilesystemDocumentStore;
+use super::s3_document_store::{S3Config, S3DocumentStore};
+
+/// Which [DocumentStore] implementation the service should use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocumentStoreConfig {
+    Filesystem(PathBuf),
+    S3(S3Config),
+}
+
+/// The [DocumentStore] selected at startup from [DocumentStoreConfig].
+#[derive(Debug, Clone)]
+pub enum DocumentStorage {
+    Filesystem(FilesystemDocumentStore),
+    S3(S3DocumentStore),
+}
+
+impl DocumentStorage {
+    pub fn new(config: &DocumentStoreConfig) -> Self {
+        match config {
+            DocumentStoreConfig::Filesystem(root) => Self::Filesystem(FilesystemDocumentStore::new(root)),
+            DocumentStoreConfig::S3(config) => Self::S3(S3DocumentStore::new(config.clone())),
+        }
+    }
+}
+
+impl DocumentStore for DocumentStorage {
+    async fn put_document(&self, booking_id: u64, id: &DocumentId, sealed: Vec<u8>) -> Result<(), DocumentError> {
+        match self {
+            DocumentStorage::Filesystem(store) => store.put_document(booking_id, id, sealed).await,
+            DocumentStorage::S3(store) => store.put_document(booking_id, id, sealed).await,
+        }
+    }
+
+    async fn get_document(&self, booking_id: u64, id: &DocumentId) -> Result<Option<Vec<u8>>, DocumentError> {
+        match self {
+            DocumentStorage::Filesystem(store) => store.get_document(booking_id, id).await,
+            DocumentStorage::S3(store) => store.get_document(booking_id, id).await,
+        }
+    }
+
+    async fn list_documents(&self, booking_id: u64) -> Result<Vec<DocumentId>, DocumentError> {
+        match self {
+            DocumentStorage::Filesystem(store) => store.list_documents(booking_id).await,
+            DocumentStorage::S3(store) => store.list_documents(booking_id).await,
+        }
+    }
+
+    async fn delete_document(&self, booking_id: u64, id: &DocumentId) -> Result<(), DocumentError> {
+        match self {
+            DocumentStorage::Filesystem(store) => store.delete_document(booking_id, id).await,
+            DocumentStorage::S3(store) => store.delete_document(booking_id, id).await,
+        }
+    }
+}
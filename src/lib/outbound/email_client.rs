@@ -6,8 +6,10 @@ use base64::engine::general_purpose;
 use base64::Engine;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use crate::domain::transactions::models::booking::Booking;
+use crate::domain::transactions::models::event::BookingEvent;
 use crate::domain::transactions::models::transaction::Transaction;
-use crate::domain::transactions::ports::TransactionNotifier;
+use crate::domain::transactions::ports::{BookingEventHandler, HandlerFuture};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone)]
@@ -77,7 +79,7 @@ impl EmailClient {
                     Ok(())
                 } else {
                     let error_text = response.text().await?;
-                    eprintln!("Error: {}", error_text);
+                    tracing::error!("Failed to refresh email token: {}", error_text);
                     Err(anyhow!("Failed to refresh token"))
                 }
             }
@@ -86,31 +88,45 @@ impl EmailClient {
     }
 
     pub async fn send_email_gmail(&self, reservation: &Transaction) -> Result<(), String> {
+        let username = &reservation.name().0;
+        let to = &reservation.email().0;
+        let booking_id = format!("{}-{}", reservation.car_id(), &reservation.booking_id());
+        let start_date = crate::utils::format_datetime(reservation.start_time());
+        let end_date = crate::utils::format_datetime(reservation.end_time());
+
+        let subject = "Booking Confirmed with FuelEV";
+        let body = format!(
+            "Hey {username},\n\nThank you for choosing FuelEV! This is a confirmation email of your booking ID {booking_id} with us from {start_date} IST to {end_date} IST.\n\nWatch this space for more details regarding your vehicle details and other information to make it a smooth experience.\n\nRegards\nTeam FuelEV"
+        );
+        self.send_gmail(to, Some("bookings@fueldao.io"), subject, &body).await
+    }
+
+    /// Email the one-time password `code` that verifies the address `to`.
+    pub async fn send_otp_gmail(&self, to: &str, code: &str, valid_minutes: u64) -> Result<(), String> {
+        let subject = "Your FuelEV verification code";
+        let body = format!(
+            "Your FuelEV verification code is {code}. It expires in {valid_minutes} minutes.\n\nIf you did not request this code, you can ignore this email.\n\nRegards\nTeam FuelEV"
+        );
+        self.send_gmail(to, None, subject, &body).await
+    }
+
+    async fn send_gmail(&self, to: &str, cc: Option<&str>, subject: &str, body: &str) -> Result<(), String> {
         // Check if the access token is expired
         if self.is_token_expired().map_err(|f| f.to_string())? {
             self.refresh_token().await.map_err(|e| e.to_string())?;
         }
 
         let mail_state = self.get_config().ok();
-        let username = &reservation.name().0;
-        let to = &reservation.email().0;
-        let cc = "bookings@fueldao.io";
-        let booking_id = format!("{}-{}", reservation.car_id(), &reservation.booking_id());
-        let start_date = crate::utils::format_datetime(reservation.start_time());
-        let end_date = crate::utils::format_datetime(reservation.end_time());
 
         match mail_state {
             Some(state) => {
                 let access_token = state.access_token;
-                let subject = "Booking Confirmed with FuelEV";
-                let body = format!(
-                    "Hey {username},\n\nThank you for choosing FuelEV! This is a confirmation email of your booking ID {booking_id} with us from {start_date} IST to {end_date} IST.\n\nWatch this space for more details regarding your vehicle details and other information to make it a smooth experience.\n\nRegards\nTeam FuelEV"
-                );
                 let url = "https://www.googleapis.com/gmail/v1/users/me/messages/send";
 
                 // Create the email message
+                let cc = cc.map(|cc| format!("Cc: {cc}\r\n")).unwrap_or_default();
                 let email_raw = format!(
-                    "To: {}\r\nCc: {}\r\nSubject: {}\r\n\r\n{}",
+                    "To: {}\r\n{}Subject: {}\r\n\r\n{}",
                     to, cc, subject, body
                 );
                 let encoded_message = general_purpose::STANDARD.encode(email_raw);
@@ -134,7 +150,7 @@ impl EmailClient {
                     Ok(())
                 } else {
                     let error_text = response.unwrap().text().await.map_err(|f| f.to_string())?;
-                    eprintln!("Error: {:?}", error_text);
+                    tracing::error!("Failed to send email: {}", error_text);
                     Err(format!("Failed to send email: {:?}", error_text))
                 }
             }
@@ -149,8 +165,19 @@ impl EmailClient {
     }
 }
 
-impl TransactionNotifier for EmailClient {
-    async fn transaction_created(&self, transaction: &Transaction) {
-        let _ = self.send_email_gmail(transaction).await;
+impl BookingEventHandler for EmailClient {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    fn handle<'a>(&'a self, event: &'a BookingEvent, booking: &'a Booking) -> HandlerFuture<'a> {
+        Box::pin(async move {
+            match event {
+                // Erased customers have no address left to write to.
+                _ if booking.erased_at().is_some() => Ok(()),
+                BookingEvent::BookingReserved { .. } => self.send_email_gmail(booking.transaction()).await,
+                _ => Ok(()),
+            }
+        })
     }
 }
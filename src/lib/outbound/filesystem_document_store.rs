@@ -0,0 +1,119 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::domain::transactions::models::document::{DocumentError, DocumentId};
+use crate::domain::transactions::ports::DocumentStore;
+
+/// Keeps sealed documents as files under `root`, one directory per booking.
+///
+/// Suitable for a single instance with a persistent volume; deployments with several instances
+/// should use the [S3DocumentStore](super::s3_document_store::S3DocumentStore).
+#[derive(Debug, Clone)]
+pub struct FilesystemDocumentStore {
+    root: PathBuf,
+}
+
+impl FilesystemDocumentStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn dir(&self, booking_id: u64) -> PathBuf {
+        self.root.join("bookings").join(booking_id.to_string())
+    }
+
+    fn path(&self, booking_id: u64, id: &DocumentId) -> PathBuf {
+        self.dir(booking_id).join(format!("{id}.bin"))
+    }
+}
+
+impl DocumentStore for FilesystemDocumentStore {
+    async fn put_document(&self, booking_id: u64, id: &DocumentId, sealed: Vec<u8>) -> Result<(), DocumentError> {
+        let path = self.path(booking_id, id);
+        let dir = path.parent().unwrap_or(Path::new("."));
+        tokio::fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+
+        // Write to a temporary file first, so that a crash never leaves a truncated document.
+        let partial = path.with_extension("partial");
+        tokio::fs::write(&partial, sealed)
+            .await
+            .with_context(|| format!("failed to write {}", partial.display()))?;
+        tokio::fs::rename(&partial, &path)
+            .await
+            .with_context(|| format!("failed to move document into {}", path.display()))?;
+        Ok(())
+    }
+
+    async fn get_document(&self, booking_id: u64, id: &DocumentId) -> Result<Option<Vec<u8>>, DocumentError> {
+        let path = self.path(booking_id, id);
+        match tokio::fs::read(&path).await {
+            Ok(sealed) => Ok(Some(sealed)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(anyhow::Error::new(e).context(format!("failed to read {}", path.display())).into()),
+        }
+    }
+
+    async fn list_documents(&self, booking_id: u64) -> Result<Vec<DocumentId>, DocumentError> {
+        let dir = self.dir(booking_id);
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(anyhow::Error::new(e).context(format!("failed to list {}", dir.display())).into()),
+        };
+
+        let mut ids = Vec::new();
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .with_context(|| format!("failed to list {}", dir.display()))?
+        {
+            // Skips partial writes and anything else that isn't a document.
+            let name = entry.file_name();
+            if let Some(id) = name.to_str().and_then(|name| name.strip_suffix(".bin")) {
+                if let Ok(id) = DocumentId::parse(id) {
+                    ids.push(id);
+                }
+            }
+        }
+        ids.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        Ok(ids)
+    }
+
+    async fn delete_document(&self, booking_id: u64, id: &DocumentId) -> Result<(), DocumentError> {
+        let path = self.path(booking_id, id);
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(anyhow::Error::new(e).context(format!("failed to delete {}", path.display())).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::transactions::models::document::DocumentKind;
+
+    #[tokio::test]
+    async fn test_documents_are_stored_per_booking() {
+        let root = std::env::temp_dir().join(format!("documents-{}", DocumentId::generate(DocumentKind::Pan)));
+        let store = FilesystemDocumentStore::new(&root);
+        let id = DocumentId::generate(DocumentKind::Aadhaar);
+
+        store.put_document(1, &id, b"sealed".to_vec()).await.unwrap();
+        assert_eq!(store.get_document(1, &id).await.unwrap().as_deref(), Some(b"sealed".as_slice()));
+        assert_eq!(store.get_document(2, &id).await.unwrap(), None);
+        assert_eq!(store.list_documents(1).await.unwrap(), vec![id.clone()]);
+        assert_eq!(store.list_documents(2).await.unwrap(), vec![]);
+
+        store.delete_document(1, &id).await.unwrap();
+        store.delete_document(1, &id).await.unwrap();
+        assert_eq!(store.list_documents(1).await.unwrap(), vec![]);
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+}
@@ -1,108 +1,234 @@
+use std::future::Future;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use anyhow::{anyhow, Context};
+use candid::Principal;
 use ic_agent::identity::DelegatedIdentity;
-use ic_agent::Identity;
+use ic_agent::AgentError;
 
-use crate::canister::backend::{RazorpayPayment, RentalTransaction};
-use crate::canister::canister::Canisters;
-use crate::domain::transactions::models::transaction::{Aadhar, Age, CreateTransactionError, CreateTransactionRequest, EmailAddress, MobileNumber, Transaction, UserName, PAN};
+use crate::canister::backend::{BookedSlotsResult, RazorpayPayment, RentalTransaction};
+use crate::canister::canister::{Canisters, RenewingCanisters};
+use crate::domain::transactions::event_bus::EventBus;
+use crate::domain::transactions::models::availability::{AvailabilityError, AvailabilityRange, Interval};
+use crate::domain::transactions::models::event::TransactionEvent;
+use crate::domain::transactions::models::phone_number::PhoneNumber;
+use crate::domain::transactions::models::transaction::{Aadhar, Age, CreateTransactionError, CreateTransactionRequest, EmailAddress, Transaction, UserName, PAN};
 use crate::domain::transactions::ports::TransactionRepository;
-use crate::identity::delegated_identity::DelegatedIdentityWire;
-use crate::identity::identity::extract_identity;
+use crate::identity::admin::{AdminIdentity, AdminKeyInfo, AdminKeyRotationError};
+use crate::identity::ic::IcNetwork;
+use crate::outbound::canister_call::{CallKind, CanisterCallConfig, CanisterCallError};
 
 
 
 #[derive(Debug, Clone)]
 pub struct IcAgentTransactionRepository{
-    admin_private_key: String,
+    network: IcNetwork,
+    /// Makes the queries that need no identity, for the lifetime of the repository.
+    anonymous: Canisters<false>,
+    admins: Arc<RwLock<AdminKeyring>>,
+    delegation_lifetime: Duration,
+    call_config: CanisterCallConfig,
+    /// Receives a [TransactionEvent::CanisterCallRetried] for every call that was retried.
+    events: EventBus,
+}
+
+/// The admin keys the repository may call the canister as, one of them active.
+///
+/// Each call takes the canisters of the active key once and keeps them, so switching keys never
+/// disturbs a call under way. Keys are never dropped. The keyring itself is this process's
+/// alone; the service stores the key rotated to for the other instances.
+#[derive(Debug)]
+struct AdminKeyring {
+    keys: Vec<AdminKey>,
+    active: usize,
+}
+
+impl AdminKeyring {
+    /// The held key of `principal`, if there is one.
+    fn held(&self, principal: Principal) -> Option<AdminKey> {
+        self.keys.iter().find(|key| key.admin.principal() == principal).cloned()
+    }
+
+    /// Make `key` the active key, adding it unless a key of the same principal is held.
+    fn activate(&mut self, key: AdminKey) -> AdminKeyInfo {
+        let principal = key.admin.principal();
+        let index = match self.keys.iter().position(|held| held.admin.principal() == principal) {
+            Some(index) => index,
+            None => {
+                self.keys.push(key);
+                self.keys.len() - 1
+            }
+        };
+        self.active = index;
+        self.keys[index].admin.info(true)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AdminKey {
+    admin: AdminIdentity,
+    /// Calls as the admin, through delegations lasting `delegation_lifetime`.
+    canisters: RenewingCanisters,
 }
 
 impl IcAgentTransactionRepository {
-    pub fn new(admin_private_key: String) -> Self {
-        Self {
-            admin_private_key
-        }
+    /// # Errors
+    ///
+    /// - [AgentError] if no agent can be built for `network`.
+    pub fn new(network: IcNetwork, admin: AdminIdentity, delegation_lifetime: Duration) -> Result<Self, AgentError> {
+        let key = AdminKey {
+            canisters: RenewingCanisters::new(network.clone(), admin.identity(), delegation_lifetime),
+            admin,
+        };
+        Ok(Self {
+            anonymous: Canisters::anonymous(&network)?,
+            network,
+            admins: Arc::new(RwLock::new(AdminKeyring { keys: vec![key], active: 0 })),
+            delegation_lifetime,
+            call_config: CanisterCallConfig::default(),
+            events: EventBus::new(),
+        })
     }
 
-    fn create_identity_for_admin(&self) -> impl Identity {
-        let private_key = self.admin_private_key.clone();
-    
-        let identity = ic_agent::identity::Secp256k1Identity::from_pem(
-            stringreader::StringReader::new(private_key.as_str()),
-        )
-        .unwrap();
-    
-        identity
+    pub fn with_call_config(mut self, call_config: CanisterCallConfig) -> Self {
+        self.call_config = call_config;
+        self
     }
 
-    fn get_admin_principal(&self) -> Result<String, CreateTransactionError> {
-        let identity = self.create_identity_for_admin();
-        let identity = DelegatedIdentity::try_from(DelegatedIdentityWire::delegate(&identity)).map_err( |f| CreateTransactionError::Unknown(anyhow!(format!("Failed to get principal: {f:?}"))))?;
-        identity.sender().map_err(|f| CreateTransactionError::Unknown(anyhow!(format!("Failed to get principal: {f:?}")))).map(|f| f.to_text())
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
     }
 
-    async fn call_check_if_car_available(&self, req: &CreateTransactionRequest) -> Result<RentalTransaction, CreateTransactionError> {
+    // The keyring is only written by swapping whole values, so it is consistent even if a
+    // writer panicked.
+    fn keyring(&self) -> RwLockReadGuard<'_, AdminKeyring> {
+        self.admins.read().unwrap_or_else(PoisonError::into_inner)
+    }
 
-        let secret = req.secret();
+    fn active_admin(&self) -> AdminKey {
+        let keyring = self.keyring();
+        keyring.keys[keyring.active].clone()
+    }
 
-        let identity = extract_identity(Some(secret)).context("failed to set delegated identity")?;
+    /// Switch to `admin` once `check` passes for its key; the active key is left as it was if the
+    /// check fails.
+    async fn switch_admin<F, Fut>(&self, admin: AdminIdentity, check: F) -> Result<AdminKeyInfo, AdminKeyRotationError>
+    where
+        F: FnOnce(AdminKey) -> Fut,
+        Fut: Future<Output = Result<(), AdminKeyRotationError>>,
+    {
+        let principal = admin.principal();
+        // Switching back to a key already held reuses its delegation.
+        let held = self.keyring().held(principal);
+        let key = held.unwrap_or_else(|| AdminKey {
+            canisters: RenewingCanisters::new(self.network.clone(), admin.identity(), self.delegation_lifetime),
+            admin,
+        });
 
-        let identity = DelegatedIdentity::try_from(identity).context("Failed to generate delegated identity from secret")?;
+        check(key.clone()).await?;
+        Ok(self.admins.write().unwrap_or_else(PoisonError::into_inner).activate(key))
+    }
 
-        let caller =identity.sender().map_err(|f| anyhow::format_err!("{f}") )?;
+    /// Run `call` to the canister `method` under the call config, reporting any retries.
+    async fn call<T, F, Fut>(&self, method: &str, kind: CallKind, call: F) -> Result<T, CanisterCallError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, AgentError>>,
+    {
+        let (result, retries) = self.call_config.run(kind, call).await;
+        if retries > 0 {
+            self.events
+                .publish(TransactionEvent::CanisterCallRetried { method: method.to_string(), retries, succeeded: result.is_ok() })
+                .await;
+        }
+        result
+    }
 
-        let canister = Canisters::authenticated(identity);
+    async fn call_check_if_car_available(&self, req: &CreateTransactionRequest) -> Result<RentalTransaction, CreateTransactionError> {
+
+        // The delegation was verified when the request was parsed; the canister checks it again.
+        let identity = DelegatedIdentity::try_from(req.delegated_identity().clone()).context("Failed to build delegated identity")?;
 
-        let check = canister.backend().await.validate_details_and_availaibility(req.car_id(), req.start_time(), req.end_time(), req.customer(caller) ).await.map_err(|f| CreateTransactionError::Unknown(anyhow!(f.to_string())))?;
+        // Agents share the network's HTTP client, so one per customer is cheap to build.
+        let canister = Canisters::authenticated(&self.network, identity).context("Failed to build the customer's agent")?;
+        let now_ns = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| anyhow!(e))?.as_nanos() as u64;
+        if canister.expires_within(Duration::ZERO, now_ns) {
+            return Err(anyhow!("The delegated identity has expired").into());
+        }
+
+        let backend = canister.backend().await;
+        let check = self
+            .call("validate_details_and_availaibility", CallKind::Update, || {
+                backend.validate_details_and_availaibility(req.car_id(), req.start_time(), req.end_time(), req.customer())
+            })
+            .await?;
 
         match check {
             crate::canister::backend::Result_::Ok(r) => Ok(r),
-            crate::canister::backend::Result_::Err(e) => Err(anyhow!(e).into()),
+            crate::canister::backend::Result_::Err(e) => Err(CreateTransactionError::CanisterRejectedError(e)),
         }
 
     }
 
+    /// Query the canister for the bookings of `car_id` overlapping `range`. Queries need no
+    /// identity, so this uses the anonymous agent.
+    async fn call_get_booked_slots(&self, car_id: u64, range: &AvailabilityRange) -> Result<Vec<Interval>, anyhow::Error> {
+        let backend = self.anonymous.backend().await;
+        let slots = self
+            .call("get_booked_slots", CallKind::Query, || backend.get_booked_slots(car_id, range.from(), range.to()))
+            .await?;
+
+        match slots {
+            BookedSlotsResult::Ok(slots) => Ok(slots
+                .into_iter()
+                .map(|slot| Interval { start: slot.start_timestamp, end: slot.end_timestamp })
+                .collect()),
+            BookedSlotsResult::Err(e) => Err(anyhow!(e)),
+        }
+    }
+
     /// Call the transaction function in the canister.
     async fn call_create_transaction(
         &self,
         booking_id: u64,
         payment: &RazorpayPayment,
-    ) -> Result<RentalTransaction, anyhow::Error> {
-
-        let identity = self.create_identity_for_admin();
+    ) -> Result<RentalTransaction, CreateTransactionError> {
 
-        let canister = Canisters::set_arc_id(identity.into());
+        let canister = self.active_admin().canisters.get()?;
 
-        let backend  = canister.backend();
+        let backend  = canister.backend().await;
 
-        let tx = backend.await.reserve_car(booking_id, payment.clone() ).await?;
+        // Only retried if the replica turned it away, as a reservation must not be made twice.
+        let tx = self.call("reserve_car", CallKind::Update, || backend.reserve_car(booking_id, payment.clone())).await?;
 
         match tx {
             crate::canister::backend::Result_::Ok(rental_transaction) => Ok(rental_transaction),
-            crate::canister::backend::Result_::Err(e) => Err(anyhow!(e)),
+            crate::canister::backend::Result_::Err(e) => Err(CreateTransactionError::CanisterRejectedError(e)),
         }
     }
 }
 
 impl TransactionRepository for IcAgentTransactionRepository {
     async fn create_transaction(&self, booking_id: u64, payment: &RazorpayPayment) -> Result<Transaction, CreateTransactionError> {
-        let response = self.call_create_transaction(booking_id, payment).await.map_err(|e| {
-            // Handle specific errors based on your application's needs.
-            CreateTransactionError::Unknown(anyhow!(e))
-        })?;
+        let response = self.call_create_transaction(booking_id, payment).await?;
 
         let customer = response.customer.ok_or( CreateTransactionError::Unknown(anyhow!("Failed to parse Customer")))?;
 
-        // Handle response and return Transaction object
+        // The canister's record was validated when the booking was made, perhaps under older rules.
         Ok(Transaction::new(
         response.booking_id,
         response.car_id,
-         UserName::new(&customer.name)?,  
-        EmailAddress::new(&customer.email)?,
-        Age::new(customer.age)?,
-        customer.country_code.parse::<u16>().map_err(|_| anyhow!("Could not parse country code"))?,
-        MobileNumber::new(customer.mobile_number.parse::<u64>().map_err(|_| anyhow!("Could not parse country code"))?)?,
-        PAN::new(&customer.pan)?,
-        Aadhar::new(&customer.aadhar)?,
+        UserName::from_stored(&customer.name),
+        EmailAddress::from_stored(&customer.email),
+        Age::from_stored(customer.age),
+        PhoneNumber::from_stored(
+            customer.country_code.parse::<u16>().map_err(|_| anyhow!("Could not parse country code"))?,
+            &customer.mobile_number,
+        ),
+        PAN::from_stored(&customer.pan),
+        Aadhar::from_stored(&customer.aadhar),
         response.start_timestamp,
         response.end_timestamp
         ))
@@ -113,6 +239,84 @@ impl TransactionRepository for IcAgentTransactionRepository {
     }
 
     async fn get_principal(&self) -> Result<String, CreateTransactionError> {
-        self.get_admin_principal()
+        Ok(self.active_admin().admin.principal().to_text())
+    }
+
+    async fn booked_intervals(&self, car_id: u64, range: &AvailabilityRange) -> Result<Vec<Interval>, AvailabilityError> {
+        Ok(self.call_get_booked_slots(car_id, range).await?)
+    }
+
+    async fn admin_keys(&self) -> Vec<AdminKeyInfo> {
+        let keyring = self.keyring();
+        keyring.keys.iter().enumerate().map(|(index, key)| key.admin.info(index == keyring.active)).collect()
+    }
+
+    async fn rotate_admin_identity(&self, admin: AdminIdentity) -> Result<AdminKeyInfo, AdminKeyRotationError> {
+        self.switch_admin(admin, |key| async move {
+            let canisters = key.canisters.get()?;
+            canisters.check_backend_controller().await.map_err(|e| AdminKeyRotationError::NotAuthorized {
+                principal: key.admin.principal().to_text(),
+                reason: e.to_string(),
+            })
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pkcs8::der::pem::LineEnding;
+
+    use super::*;
+
+    fn admin(seed: u8) -> AdminIdentity {
+        let key = k256::SecretKey::from_slice(&[seed; 32]).unwrap();
+        AdminIdentity::from_pem(&key.to_sec1_pem(LineEnding::LF).unwrap(), None).unwrap()
+    }
+
+    fn repository() -> IcAgentTransactionRepository {
+        IcAgentTransactionRepository::new(IcNetwork::local(), admin(1), Duration::from_secs(3600)).unwrap()
+    }
+
+    async fn controls_backend(_: AdminKey) -> Result<(), AdminKeyRotationError> {
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_admin_key_that_controls_the_backend_becomes_active() {
+        let repository = repository();
+
+        let info = repository.switch_admin(admin(2), controls_backend).await.unwrap();
+
+        assert_eq!(info, admin(2).info(true));
+        assert_eq!(repository.admin_keys().await, [admin(1).info(false), admin(2).info(true)]);
+        assert_eq!(repository.get_principal().await.unwrap(), admin(2).principal().to_text());
+    }
+
+    #[tokio::test]
+    async fn test_admin_key_that_fails_the_controller_check_is_not_used() {
+        let repository = repository();
+
+        let result = repository
+            .switch_admin(admin(2), |key| async move {
+                Err(AdminKeyRotationError::NotAuthorized {
+                    principal: key.admin.principal().to_text(),
+                    reason: "not a controller".to_string(),
+                })
+            })
+            .await;
+
+        assert!(matches!(result, Err(AdminKeyRotationError::NotAuthorized { .. })), "got {result:?}");
+        assert_eq!(repository.admin_keys().await, [admin(1).info(true)]);
+    }
+
+    #[tokio::test]
+    async fn test_switching_back_to_a_held_admin_key_keeps_one_copy() {
+        let repository = repository();
+        repository.switch_admin(admin(2), controls_backend).await.unwrap();
+
+        repository.switch_admin(admin(1), controls_backend).await.unwrap();
+
+        assert_eq!(repository.admin_keys().await, [admin(1).info(true), admin(2).info(false)]);
     }
 }
@@ -0,0 +1,246 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+
+use crate::domain::transactions::models::availability::{AvailabilityRange, Interval};
+use crate::domain::transactions::models::booking::{Booking, BookingRepositoryError};
+use crate::domain::transactions::models::data_request::DataRequestRecord;
+use crate::domain::transactions::models::event::BookingEvent;
+use crate::domain::transactions::models::hold::{SlotHold, SlotHoldError};
+use crate::domain::transactions::models::outbox::{OutboxEvent, OutboxStatus};
+use crate::domain::transactions::models::phone_number::PhoneNumber;
+use crate::domain::transactions::models::transaction::EmailAddress;
+use crate::domain::transactions::models::verification::{OtpChallenge, VerificationError};
+use crate::domain::transactions::ports::{
+    AdminKeyRepository, BookingRepository, DataRequestRepository, OutboxRepository, SlotHoldRepository, VerificationRepository,
+};
+use crate::identity::admin::AdminIdentity;
+
+/// An embedded [BookingRepository] for single-instance deployments and local development.
+///
+/// Bookings are lost when the process exits and are not shared between machines; use
+/// [PostgresBookingRepository](super::postgres::PostgresBookingRepository) when more than one
+/// instance of the service is running.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryBookingRepository {
+    state: Arc<Mutex<InMemoryState>>,
+}
+
+#[derive(Debug, Default)]
+struct InMemoryState {
+    bookings: BTreeMap<u64, Booking>,
+    outbox: BTreeMap<u64, OutboxEvent>,
+    next_outbox_id: u64,
+    holds: BTreeMap<u64, SlotHold>,
+    otp_challenges: BTreeMap<String, OtpChallenge>,
+    admin_key: Option<AdminIdentity>,
+    data_requests: Vec<DataRequestRecord>,
+}
+
+impl InMemoryBookingRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, InMemoryState>, BookingRepositoryError> {
+        Ok(self
+            .state
+            .lock()
+            .map_err(|e| anyhow!("Could not lock booking store: {e}"))?)
+    }
+}
+
+impl BookingRepository for InMemoryBookingRepository {
+    async fn save_booking(&self, booking: &Booking, events: &[BookingEvent]) -> Result<(), BookingRepositoryError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| anyhow!(e))?.as_secs();
+        let mut state = self.lock()?;
+        state.bookings.insert(booking.booking_id(), booking.clone());
+        for event in events {
+            state.next_outbox_id += 1;
+            let id = state.next_outbox_id;
+            state.outbox.insert(
+                id,
+                OutboxEvent::new(id, event.clone(), OutboxStatus::Pending, 0, now, Vec::new(), None, now),
+            );
+        }
+        Ok(())
+    }
+
+    async fn find_booking(&self, booking_id: u64) -> Result<Option<Booking>, BookingRepositoryError> {
+        Ok(self.lock()?.bookings.get(&booking_id).cloned())
+    }
+
+    async fn find_bookings_by_email(&self, email: &EmailAddress) -> Result<Vec<Booking>, BookingRepositoryError> {
+        Ok(self
+            .lock()?
+            .bookings
+            .values()
+            .filter(|booking| booking.transaction().email() == email)
+            .cloned()
+            .collect())
+    }
+
+    async fn find_bookings_by_phone_number(&self, phone_number: &PhoneNumber) -> Result<Vec<Booking>, BookingRepositoryError> {
+        Ok(self
+            .lock()?
+            .bookings
+            .values()
+            .filter(|booking| booking.transaction().phone_number() == phone_number)
+            .cloned()
+            .collect())
+    }
+
+    async fn find_bookings_by_principal(&self, customer_principal: &str) -> Result<Vec<Booking>, BookingRepositoryError> {
+        Ok(self
+            .lock()?
+            .bookings
+            .values()
+            .filter(|booking| booking.customer_principal() == Some(customer_principal))
+            .cloned()
+            .collect())
+    }
+}
+
+impl OutboxRepository for InMemoryBookingRepository {
+    async fn due_outbox_events(&self, now: u64, limit: u32) -> Result<Vec<OutboxEvent>, BookingRepositoryError> {
+        Ok(self
+            .lock()?
+            .outbox
+            .values()
+            .filter(|event| event.status() == OutboxStatus::Pending && event.next_attempt_at() <= now)
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn find_outbox_event(&self, id: u64) -> Result<Option<OutboxEvent>, BookingRepositoryError> {
+        Ok(self.lock()?.outbox.get(&id).cloned())
+    }
+
+    async fn find_outbox_events_by_booking(&self, booking_id: u64) -> Result<Vec<OutboxEvent>, BookingRepositoryError> {
+        Ok(self
+            .lock()?
+            .outbox
+            .values()
+            .filter(|event| event.event().booking_id() == booking_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn dead_lettered_outbox_events(&self) -> Result<Vec<OutboxEvent>, BookingRepositoryError> {
+        Ok(self
+            .lock()?
+            .outbox
+            .values()
+            .filter(|event| event.status() == OutboxStatus::DeadLettered)
+            .cloned()
+            .collect())
+    }
+
+    async fn update_outbox_event(&self, event: &OutboxEvent) -> Result<(), BookingRepositoryError> {
+        self.lock()?.outbox.insert(event.id(), event.clone());
+        Ok(())
+    }
+
+    async fn run_dispatch_exclusive<F>(&self, job: F) -> Result<Option<F::Output>, BookingRepositoryError>
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        // A single process owns the whole store, so there is nobody to exclude.
+        Ok(Some(job.await))
+    }
+}
+
+impl SlotHoldRepository for InMemoryBookingRepository {
+    async fn place_hold(&self, hold: &SlotHold, now: u64) -> Result<(), SlotHoldError> {
+        let mut state = self.lock()?;
+        state.holds.retain(|_, held| held.expires_at() > now);
+
+        let held_until = state
+            .holds
+            .values()
+            .filter(|held| hold.conflicts_with(held, now))
+            .map(|held| held.expires_at())
+            .max();
+        if let Some(held_until) = held_until {
+            return Err(SlotHoldError::Held {
+                car_id: hold.car_id(),
+                held_until,
+            });
+        }
+
+        state.holds.insert(hold.booking_id(), hold.clone());
+        Ok(())
+    }
+
+    async fn release_hold(&self, booking_id: u64) -> Result<(), BookingRepositoryError> {
+        self.lock()?.holds.remove(&booking_id);
+        Ok(())
+    }
+
+    async fn held_intervals(&self, car_id: u64, range: &AvailabilityRange, now: u64) -> Result<Vec<Interval>, BookingRepositoryError> {
+        Ok(self
+            .lock()?
+            .holds
+            .values()
+            .filter(|hold| hold.car_id() == car_id && hold.expires_at() > now)
+            .filter(|hold| hold.start_time() < range.to() && range.from() < hold.end_time())
+            .map(|hold| Interval { start: hold.start_time(), end: hold.end_time() })
+            .collect())
+    }
+}
+
+impl VerificationRepository for InMemoryBookingRepository {
+    async fn issue_otp<F>(&self, contact: &str, issue: F) -> Result<OtpChallenge, VerificationError>
+    where
+        F: FnOnce(Option<&OtpChallenge>) -> Result<OtpChallenge, VerificationError> + Send,
+    {
+        let mut state = self.lock()?;
+        let challenge = issue(state.otp_challenges.get(contact))?;
+        state.otp_challenges.insert(contact.to_string(), challenge.clone());
+        Ok(challenge)
+    }
+
+    async fn record_otp_attempt(&self, contact: &str) -> Result<Option<OtpChallenge>, VerificationError> {
+        let mut state = self.lock()?;
+        Ok(state.otp_challenges.get_mut(contact).map(|challenge| {
+            *challenge = challenge.clone().attempted();
+            challenge.clone()
+        }))
+    }
+
+    async fn consume_otp(&self, contact: &str) -> Result<(), VerificationError> {
+        let mut state = self.lock()?;
+        if let Some(challenge) = state.otp_challenges.remove(contact) {
+            state.otp_challenges.insert(contact.to_string(), challenge.consumed());
+        }
+        Ok(())
+    }
+
+    async fn remove_otp(&self, contact: &str) -> Result<(), VerificationError> {
+        self.lock()?.otp_challenges.remove(contact);
+        Ok(())
+    }
+}
+
+impl DataRequestRepository for InMemoryBookingRepository {
+    async fn record_data_request(&self, record: &DataRequestRecord) -> Result<(), BookingRepositoryError> {
+        self.lock()?.data_requests.push(record.clone());
+        Ok(())
+    }
+}
+
+impl AdminKeyRepository for InMemoryBookingRepository {
+    async fn save_admin_key(&self, admin: &AdminIdentity) -> Result<(), BookingRepositoryError> {
+        self.lock()?.admin_key = Some(admin.clone());
+        Ok(())
+    }
+
+    async fn find_admin_key(&self) -> Result<Option<AdminIdentity>, BookingRepositoryError> {
+        Ok(self.lock()?.admin_key.clone())
+    }
+}
@@ -1,4 +1,15 @@
 pub mod email_client;
 pub mod prometheus;
 pub mod ic_agent;
-pub mod payment_client;
\ No newline at end of file
+pub mod payment_client;
+pub mod in_memory;
+pub mod postgres;
+pub mod booking_store;
+pub mod webhook_client;
+pub mod audit_log;
+pub mod document_store;
+pub mod filesystem_document_store;
+pub mod s3_document_store;
+pub mod sms_client;
+pub mod otp_sender;
+pub mod canister_call;
\ No newline at end of file
@@ -0,0 +1,38 @@
+use crate::domain::transactions::models::verification::{Contact, VerificationError, OTP_TTL};
+use crate::domain::transactions::ports::VerificationSender;
+
+use super::email_client::EmailClient;
+use super::sms_client::SmsClient;
+
+/// Delivers one-time passwords by email to email addresses and by SMS to mobile numbers.
+#[derive(Debug, Clone)]
+pub struct OtpSender {
+    email: EmailClient,
+    sms: SmsClient,
+}
+
+impl OtpSender {
+    pub fn new(email: EmailClient, sms: SmsClient) -> Self {
+        Self { email, sms }
+    }
+}
+
+impl VerificationSender for OtpSender {
+    async fn send_otp(&self, contact: &Contact, code: &str) -> Result<(), VerificationError> {
+        let valid_minutes = OTP_TTL.as_secs() / 60;
+        match contact {
+            Contact::Email(email) => self
+                .email
+                .send_otp_gmail(&email.0, code, valid_minutes)
+                .await
+                .map_err(VerificationError::Send),
+            Contact::Phone(phone) => {
+                let body = format!("{code} is your FuelEV verification code. It expires in {valid_minutes} minutes.");
+                self.sms
+                    .send_sms(&phone.e164(), &body)
+                    .await
+                    .map_err(|e| VerificationError::Send(e.to_string()))
+            }
+        }
+    }
+}
@@ -1,5 +1,3 @@
-use std::env;
-
 use reqwest::Client;
 use serde_json::Value;
 
@@ -15,6 +13,8 @@ pub struct PaymentClient {
 pub struct PaymentConfig {
     pub payment_key: String, 
     pub payment_secret: String,
+    /// Where Razorpay sends the customer after they pay.
+    pub callback_url: String,
 }
 
 impl PaymentClient {
@@ -29,25 +29,18 @@ impl PaymentService for PaymentClient {
     async fn create_payment_link(
         &self,
         payment_amount_in_inr_f32: f64,
-        booking_id: u64
+        booking_id: u64,
+        expire_by: u64,
     ) -> Result<String, String> {
-        // Payment amount and callback URL
-        // dotenv::dotenv().ok();
-        // let live = env::var("BACKEND").unwrap_or("LIVE".to_string()) == "LIVE" ;
-        let live = true;
-        let callback_url = if !live {
-            "http://localhost:8080/payment"
-        } else {
-            "https://fuelev.in/payment"
-        };
     // API endpoint
     let url = "https://api.razorpay.com/v1/payment_links";
 
     // Create the JSON payload
     let payload = serde_json::json!({
         "amount": (payment_amount_in_inr_f32 * 100.0) as u64, // Convert to paisa
-        "callback_url": callback_url,
+        "callback_url": self.config.callback_url,
         "reference_id": booking_id.to_string(),
+        "expire_by": expire_by,
     });
 
     // Create the reqwest client
@@ -0,0 +1,740 @@
+/*!
+   Module `postgres` provides a Postgres-backed [BookingRepository], shared by every instance of
+   the service, along with advisory-lock helpers for background jobs that must only run on one
+   instance at a time.
+*/
+
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context};
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::{PgPool, PgPoolOptions, PgRow};
+use sqlx::types::Json;
+use sqlx::{Postgres, Row};
+
+use crate::domain::transactions::models::availability::{AvailabilityRange, Interval};
+use crate::domain::transactions::models::booking::{Booking, BookingRepositoryError};
+use crate::domain::transactions::models::data_request::DataRequestRecord;
+use crate::domain::transactions::models::event::BookingEvent;
+use crate::domain::transactions::models::hold::{SlotHold, SlotHoldError};
+use crate::domain::transactions::models::outbox::{OutboxEvent, OutboxStatus};
+use crate::domain::transactions::models::phone_number::PhoneNumber;
+use crate::domain::transactions::models::pii::{PiiCipher, SensitiveField};
+use crate::domain::transactions::models::transaction::{
+    Aadhar, Age, EmailAddress, Transaction, UserName, PAN,
+};
+use crate::domain::transactions::models::verification::{OtpChallenge, VerificationError};
+use crate::domain::transactions::ports::{
+    AdminKeyRepository, BookingRepository, DataRequestRepository, OutboxRepository, SlotHoldRepository, VerificationRepository,
+};
+use crate::identity::admin::AdminIdentity;
+
+const ACQUIRE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Advisory lock key held by the instance currently dispatching the outbox.
+pub const OUTBOX_DISPATCHER_LOCK: i64 = 0x6675_656c_0001;
+
+/// Configuration for the Postgres connection pool.
+#[derive(Debug, Clone)]
+pub struct PostgresConfig {
+    pub url: String,
+    pub max_connections: u32,
+    /// Seals customer details before they are written.
+    pub pii_cipher: PiiCipher,
+}
+
+/// Stores bookings with their PAN, Aadhaar, email and mobile number sealed by a [PiiCipher],
+/// and found by email or mobile number through blind indexes.
+#[derive(Debug, Clone)]
+pub struct PostgresBookingRepository {
+    pool: PgPool,
+    pii_cipher: PiiCipher,
+}
+
+impl PostgresBookingRepository {
+    /// Connects to the database described by `config` and applies any pending migrations.
+    pub async fn new(config: &PostgresConfig) -> anyhow::Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(config.max_connections)
+            .acquire_timeout(ACQUIRE_TIMEOUT)
+            .connect(&config.url)
+            .await
+            .context("failed to connect to postgres")?;
+
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .context("failed to run postgres migrations")?;
+
+        Ok(Self { pool, pii_cipher: config.pii_cipher.clone() })
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Try to take the session-level advisory lock `key` without waiting.
+    ///
+    /// Returns `None` if another session already holds the lock. The lock is held until
+    /// [AdvisoryLock::release] is called or the guard is dropped.
+    pub async fn try_advisory_lock(&self, key: i64) -> anyhow::Result<Option<AdvisoryLock>> {
+        let mut conn = self.pool.acquire().await?;
+        let acquired: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(key)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        Ok(acquired.then(|| AdvisoryLock {
+            key,
+            conn: Some(conn),
+        }))
+    }
+
+    /// Re-encrypt the customer details of bookings that are not sealed under the active key,
+    /// `batch_size` at a time, e.g. after rotating keys. Returns how many were re-encrypted.
+    ///
+    /// Bookings that can't be read, e.g. because their key is no longer configured, are logged
+    /// and skipped, so that they don't hold up the bookings after them.
+    pub async fn reencrypt_bookings(&self, batch_size: u32) -> anyhow::Result<u64> {
+        let mut reencrypted = 0;
+        let mut skipped = 0;
+        // Skipped bookings are still not sealed under the active key, so every batch starts
+        // after the last booking of the one before.
+        let mut after = -1i64;
+        loop {
+            let mut tx = self.pool.begin().await.context("failed to begin transaction")?;
+            let rows = sqlx::query(
+                r#"
+                SELECT * FROM bookings
+                WHERE pii_key_id IS DISTINCT FROM $1 AND erased_at IS NULL AND booking_id > $3
+                ORDER BY booking_id LIMIT $2 FOR UPDATE SKIP LOCKED
+                "#,
+            )
+            .bind(self.pii_cipher.active_key_id())
+            .bind(batch_size as i64)
+            .bind(after)
+            .fetch_all(&mut *tx)
+            .await
+            .context("failed to fetch bookings to re-encrypt")?;
+            if rows.is_empty() {
+                if skipped > 0 {
+                    tracing::warn!("Skipped re-encrypting {} bookings that could not be read", skipped);
+                }
+                return Ok(reencrypted);
+            }
+
+            for row in &rows {
+                after = row.try_get("booking_id")?;
+                let transaction = match self.booking_from_row(row) {
+                    Ok(booking) => booking.transaction().clone(),
+                    Err(e) => {
+                        tracing::error!("Failed to read booking {} to re-encrypt it: {:#}", after, e);
+                        skipped += 1;
+                        continue;
+                    }
+                };
+                let booking_id = transaction.booking_id();
+                sqlx::query(
+                    r#"
+                    UPDATE bookings SET
+                        customer_email = $2, customer_email_index = $3, mobile_number = $4,
+                        mobile_number_index = $5, pan = $6, aadhar = $7, pii_key_id = $8
+                    WHERE booking_id = $1
+                    "#,
+                )
+                .bind(booking_id as i64)
+                .bind(self.pii_cipher.seal(booking_id, transaction.email())?)
+                .bind(self.pii_cipher.blind_index(transaction.email()))
+                .bind(self.pii_cipher.seal(booking_id, transaction.phone_number())?)
+                .bind(self.pii_cipher.blind_index(transaction.phone_number()))
+                .bind(self.pii_cipher.seal(booking_id, transaction.pan())?)
+                .bind(self.pii_cipher.seal(booking_id, transaction.aadhar())?)
+                .bind(self.pii_cipher.active_key_id())
+                .execute(&mut *tx)
+                .await
+                .context("failed to re-encrypt booking")?;
+                reencrypted += 1;
+            }
+            tx.commit().await.context("failed to commit re-encrypted bookings")?;
+        }
+    }
+
+    fn booking_from_row(&self, row: &PgRow) -> anyhow::Result<Booking> {
+        let booking_id = row.try_get::<i64, _>("booking_id")? as u64;
+        let erased_at = row.try_get::<Option<i64>, _>("erased_at")?.map(|erased_at| erased_at as u64);
+        let pii = &self.pii_cipher;
+        // Stored details were validated when the booking was made and may predate today's rules.
+        let transaction = if erased_at.is_some() {
+            Transaction::erased(
+                booking_id,
+                row.try_get::<i64, _>("car_id")? as u64,
+                Age::from_stored(row.try_get::<i16, _>("customer_age")? as u8),
+                row.try_get::<i64, _>("start_time")? as u64,
+                row.try_get::<i64, _>("end_time")? as u64,
+            )
+        } else {
+            Transaction::new(
+            booking_id,
+            row.try_get::<i64, _>("car_id")? as u64,
+            UserName::from_stored(row.try_get("customer_name")?),
+            EmailAddress::from_stored(&pii.open::<EmailAddress>(booking_id, row.try_get("customer_email")?)?),
+            Age::from_stored(row.try_get::<i16, _>("customer_age")? as u8),
+            PhoneNumber::from_stored(
+                row.try_get::<i32, _>("country_code")? as u16,
+                &pii.open::<PhoneNumber>(booking_id, row.try_get("mobile_number")?)?,
+            ),
+            PAN::from_stored(&pii.open::<PAN>(booking_id, row.try_get("pan")?)?),
+            Aadhar::from_stored(&pii.open::<Aadhar>(booking_id, row.try_get("aadhar")?)?),
+            row.try_get::<i64, _>("start_time")? as u64,
+            row.try_get::<i64, _>("end_time")? as u64,
+            )
+        };
+
+        let booking = Booking::new(
+            transaction,
+            row.try_get("total_amount")?,
+            row.try_get::<&str, _>("status")?.parse()?,
+            row.try_get("payment_link")?,
+            row.try_get("payment_id")?,
+        )
+        .with_customer_principal(row.try_get("customer_principal")?);
+        Ok(match erased_at {
+            Some(erased_at) => booking.erased(erased_at),
+            None => booking,
+        })
+    }
+
+    /// `value` of `booking` sealed for storage, or blank once the booking has been erased.
+    fn seal_unless_erased<T: SensitiveField>(&self, booking: &Booking, value: &T) -> anyhow::Result<String> {
+        match booking.erased_at() {
+            Some(_) => Ok(String::new()),
+            None => self.pii_cipher.seal(booking.booking_id(), value),
+        }
+    }
+
+    /// Run `job` only if the advisory lock `key` could be taken, releasing it afterwards.
+    ///
+    /// Returns `None` without running `job` when another instance holds the lock. Use this for
+    /// singleton background jobs.
+    pub async fn run_exclusive<F, T>(&self, key: i64, job: F) -> anyhow::Result<Option<T>>
+    where
+        F: Future<Output = T>,
+    {
+        let Some(lock) = self.try_advisory_lock(key).await? else {
+            return Ok(None);
+        };
+        let output = job.await;
+        // The job has run; a lock that can't be released is freed with its session instead.
+        let key = lock.key();
+        if let Err(e) = lock.release().await {
+            tracing::error!("Failed to release advisory lock {}: {:?}", key, e);
+        }
+        Ok(Some(output))
+    }
+}
+
+/// A held Postgres advisory lock.
+///
+/// Advisory locks belong to the database session, so the guard keeps its connection out of the
+/// pool until the lock is released.
+#[derive(Debug)]
+pub struct AdvisoryLock {
+    key: i64,
+    conn: Option<PoolConnection<Postgres>>,
+}
+
+impl AdvisoryLock {
+    pub fn key(&self) -> i64 {
+        self.key
+    }
+
+    /// Unlock and return the connection to the pool. If unlocking fails, the connection is
+    /// closed instead, which also releases the lock.
+    pub async fn release(mut self) -> anyhow::Result<()> {
+        if let Some(mut conn) = self.conn.take() {
+            if let Err(e) = sqlx::query("SELECT pg_advisory_unlock($1)").bind(self.key).execute(&mut *conn).await {
+                drop(conn.detach());
+                return Err(e.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for AdvisoryLock {
+    fn drop(&mut self) {
+        // Unlocking needs an async round trip, so an unreleased lock closes its session instead
+        // of going back to the pool still locked.
+        if let Some(conn) = self.conn.take() {
+            drop(conn.detach());
+        }
+    }
+}
+
+impl BookingRepository for PostgresBookingRepository {
+    async fn save_booking(&self, booking: &Booking, events: &[BookingEvent]) -> Result<(), BookingRepositoryError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| anyhow!(e))?.as_secs();
+        let transaction = booking.transaction();
+        let mut tx = self.pool.begin().await.context("failed to begin transaction")?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO bookings (
+                booking_id, car_id, customer_name, customer_email, customer_age, country_code,
+                mobile_number, pan, aadhar, start_time, end_time, total_amount, status,
+                payment_link, payment_id, customer_email_index, mobile_number_index, pii_key_id,
+                customer_principal, erased_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20)
+            ON CONFLICT (booking_id) DO UPDATE SET
+                car_id = EXCLUDED.car_id,
+                customer_name = EXCLUDED.customer_name,
+                customer_email = EXCLUDED.customer_email,
+                customer_age = EXCLUDED.customer_age,
+                country_code = EXCLUDED.country_code,
+                mobile_number = EXCLUDED.mobile_number,
+                pan = EXCLUDED.pan,
+                aadhar = EXCLUDED.aadhar,
+                start_time = EXCLUDED.start_time,
+                end_time = EXCLUDED.end_time,
+                total_amount = COALESCE(EXCLUDED.total_amount, bookings.total_amount),
+                status = EXCLUDED.status,
+                payment_link = COALESCE(EXCLUDED.payment_link, bookings.payment_link),
+                payment_id = COALESCE(EXCLUDED.payment_id, bookings.payment_id),
+                customer_email_index = EXCLUDED.customer_email_index,
+                mobile_number_index = EXCLUDED.mobile_number_index,
+                pii_key_id = EXCLUDED.pii_key_id,
+                customer_principal = CASE
+                    WHEN EXCLUDED.erased_at IS NULL THEN COALESCE(EXCLUDED.customer_principal, bookings.customer_principal)
+                END,
+                erased_at = COALESCE(EXCLUDED.erased_at, bookings.erased_at),
+                updated_at = now()
+            "#,
+        )
+        .bind(transaction.booking_id() as i64)
+        .bind(transaction.car_id() as i64)
+        .bind(&transaction.name().0)
+        .bind(self.seal_unless_erased(booking, transaction.email())?)
+        .bind(transaction.age().value() as i16)
+        .bind(transaction.phone_number().country_code() as i32)
+        .bind(self.seal_unless_erased(booking, transaction.phone_number())?)
+        .bind(self.seal_unless_erased(booking, transaction.pan())?)
+        .bind(self.seal_unless_erased(booking, transaction.aadhar())?)
+        .bind(transaction.start_time() as i64)
+        .bind(transaction.end_time() as i64)
+        .bind(booking.total_amount())
+        .bind(booking.status().as_str())
+        .bind(booking.payment_link())
+        .bind(booking.payment_id())
+        .bind(booking.erased_at().is_none().then(|| self.pii_cipher.blind_index(transaction.email())))
+        .bind(booking.erased_at().is_none().then(|| self.pii_cipher.blind_index(transaction.phone_number())))
+        .bind(self.pii_cipher.active_key_id())
+        .bind(booking.customer_principal())
+        .bind(booking.erased_at().map(|erased_at| erased_at as i64))
+        .execute(&mut *tx)
+        .await
+        .context("failed to save booking")?;
+
+        for event in events {
+            sqlx::query(
+                r#"
+                INSERT INTO outbox_events (booking_id, payload, next_attempt_at, created_at)
+                VALUES ($1, $2, $3, $3)
+                "#,
+            )
+            .bind(event.booking_id() as i64)
+            .bind(Json(event))
+            .bind(now as i64)
+            .execute(&mut *tx)
+            .await
+            .context("failed to append outbox event")?;
+        }
+
+        tx.commit().await.context("failed to commit booking")?;
+        Ok(())
+    }
+
+    async fn find_booking(&self, booking_id: u64) -> Result<Option<Booking>, BookingRepositoryError> {
+        let row = sqlx::query("SELECT * FROM bookings WHERE booking_id = $1")
+            .bind(booking_id as i64)
+            .fetch_optional(&self.pool)
+            .await
+            .context("failed to fetch booking")?;
+
+        Ok(row.as_ref().map(|row| self.booking_from_row(row)).transpose()?)
+    }
+
+    async fn find_bookings_by_email(&self, email: &EmailAddress) -> Result<Vec<Booking>, BookingRepositoryError> {
+        let rows = sqlx::query("SELECT * FROM bookings WHERE customer_email_index = $1 ORDER BY booking_id")
+            .bind(self.pii_cipher.blind_index(email))
+            .fetch_all(&self.pool)
+            .await
+            .context("failed to fetch bookings")?;
+
+        Ok(rows.iter().map(|row| self.booking_from_row(row)).collect::<anyhow::Result<_>>()?)
+    }
+
+    async fn find_bookings_by_phone_number(&self, phone_number: &PhoneNumber) -> Result<Vec<Booking>, BookingRepositoryError> {
+        let rows = sqlx::query("SELECT * FROM bookings WHERE mobile_number_index = $1 ORDER BY booking_id")
+            .bind(self.pii_cipher.blind_index(phone_number))
+            .fetch_all(&self.pool)
+            .await
+            .context("failed to fetch bookings")?;
+
+        Ok(rows.iter().map(|row| self.booking_from_row(row)).collect::<anyhow::Result<_>>()?)
+    }
+
+    async fn find_bookings_by_principal(&self, customer_principal: &str) -> Result<Vec<Booking>, BookingRepositoryError> {
+        let rows = sqlx::query("SELECT * FROM bookings WHERE customer_principal = $1 ORDER BY booking_id")
+            .bind(customer_principal)
+            .fetch_all(&self.pool)
+            .await
+            .context("failed to fetch bookings")?;
+
+        Ok(rows.iter().map(|row| self.booking_from_row(row)).collect::<anyhow::Result<_>>()?)
+    }
+}
+
+impl OutboxRepository for PostgresBookingRepository {
+    async fn due_outbox_events(&self, now: u64, limit: u32) -> Result<Vec<OutboxEvent>, BookingRepositoryError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM outbox_events
+            WHERE status = 'pending' AND next_attempt_at <= $1
+            ORDER BY id
+            LIMIT $2
+            "#,
+        )
+        .bind(now as i64)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to fetch due outbox events")?;
+
+        Ok(rows.iter().map(outbox_event_from_row).collect::<anyhow::Result<_>>()?)
+    }
+
+    async fn find_outbox_event(&self, id: u64) -> Result<Option<OutboxEvent>, BookingRepositoryError> {
+        let row = sqlx::query("SELECT * FROM outbox_events WHERE id = $1")
+            .bind(id as i64)
+            .fetch_optional(&self.pool)
+            .await
+            .context("failed to fetch outbox event")?;
+
+        Ok(row.as_ref().map(outbox_event_from_row).transpose()?)
+    }
+
+    async fn find_outbox_events_by_booking(&self, booking_id: u64) -> Result<Vec<OutboxEvent>, BookingRepositoryError> {
+        let rows = sqlx::query("SELECT * FROM outbox_events WHERE booking_id = $1 ORDER BY id")
+            .bind(booking_id as i64)
+            .fetch_all(&self.pool)
+            .await
+            .context("failed to fetch outbox events")?;
+
+        Ok(rows.iter().map(outbox_event_from_row).collect::<anyhow::Result<_>>()?)
+    }
+
+    async fn dead_lettered_outbox_events(&self) -> Result<Vec<OutboxEvent>, BookingRepositoryError> {
+        let rows = sqlx::query("SELECT * FROM outbox_events WHERE status = 'dead_lettered' ORDER BY id")
+            .fetch_all(&self.pool)
+            .await
+            .context("failed to fetch dead-lettered outbox events")?;
+
+        Ok(rows.iter().map(outbox_event_from_row).collect::<anyhow::Result<_>>()?)
+    }
+
+    async fn update_outbox_event(&self, event: &OutboxEvent) -> Result<(), BookingRepositoryError> {
+        sqlx::query(
+            r#"
+            UPDATE outbox_events
+            SET status = $2, attempts = $3, next_attempt_at = $4, completed_handlers = $5, last_error = $6
+            WHERE id = $1
+            "#,
+        )
+        .bind(event.id() as i64)
+        .bind(event.status().as_str())
+        .bind(event.attempts() as i32)
+        .bind(event.next_attempt_at() as i64)
+        .bind(event.completed_handlers())
+        .bind(event.last_error())
+        .execute(&self.pool)
+        .await
+        .context("failed to update outbox event")?;
+
+        Ok(())
+    }
+
+    async fn run_dispatch_exclusive<F>(&self, job: F) -> Result<Option<F::Output>, BookingRepositoryError>
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        Ok(self.run_exclusive(OUTBOX_DISPATCHER_LOCK, job).await?)
+    }
+}
+
+impl SlotHoldRepository for PostgresBookingRepository {
+    async fn place_hold(&self, hold: &SlotHold, now: u64) -> Result<(), SlotHoldError> {
+        let mut tx = self.pool.begin().await.context("failed to begin transaction")?;
+
+        // Serialise hold attempts per car; the lock is released when the transaction ends.
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended('slot_holds/' || $1::text, 0))")
+            .bind(hold.car_id() as i64)
+            .execute(&mut *tx)
+            .await
+            .context("failed to lock car for hold")?;
+
+        sqlx::query("DELETE FROM slot_holds WHERE car_id = $1 AND expires_at <= $2")
+            .bind(hold.car_id() as i64)
+            .bind(now as i64)
+            .execute(&mut *tx)
+            .await
+            .context("failed to remove expired holds")?;
+
+        let held_until: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT max(expires_at) FROM slot_holds
+            WHERE car_id = $1 AND holder_index <> $2 AND start_time < $4 AND $3 < end_time
+            "#,
+        )
+        .bind(hold.car_id() as i64)
+        .bind(self.pii_cipher.blind_index(hold.holder()))
+        .bind(hold.start_time() as i64)
+        .bind(hold.end_time() as i64)
+        .fetch_one(&mut *tx)
+        .await
+        .context("failed to check for conflicting holds")?;
+
+        if let Some(held_until) = held_until {
+            return Err(SlotHoldError::Held {
+                car_id: hold.car_id(),
+                held_until: held_until as u64,
+            });
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO slot_holds (booking_id, car_id, holder_index, start_time, end_time, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (booking_id) DO UPDATE SET
+                car_id = EXCLUDED.car_id,
+                holder_index = EXCLUDED.holder_index,
+                start_time = EXCLUDED.start_time,
+                end_time = EXCLUDED.end_time,
+                expires_at = EXCLUDED.expires_at
+            "#,
+        )
+        .bind(hold.booking_id() as i64)
+        .bind(hold.car_id() as i64)
+        .bind(self.pii_cipher.blind_index(hold.holder()))
+        .bind(hold.start_time() as i64)
+        .bind(hold.end_time() as i64)
+        .bind(hold.expires_at() as i64)
+        .execute(&mut *tx)
+        .await
+        .context("failed to place hold")?;
+
+        tx.commit().await.context("failed to commit hold")?;
+        Ok(())
+    }
+
+    async fn release_hold(&self, booking_id: u64) -> Result<(), BookingRepositoryError> {
+        sqlx::query("DELETE FROM slot_holds WHERE booking_id = $1")
+            .bind(booking_id as i64)
+            .execute(&self.pool)
+            .await
+            .context("failed to release hold")?;
+
+        Ok(())
+    }
+
+    async fn held_intervals(&self, car_id: u64, range: &AvailabilityRange, now: u64) -> Result<Vec<Interval>, BookingRepositoryError> {
+        let rows: Vec<(i64, i64)> = sqlx::query_as(
+            r#"
+            SELECT start_time, end_time FROM slot_holds
+            WHERE car_id = $1 AND expires_at > $2 AND start_time < $4 AND $3 < end_time
+            "#,
+        )
+        .bind(car_id as i64)
+        .bind(now as i64)
+        .bind(range.from() as i64)
+        .bind(range.to() as i64)
+        .fetch_all(&self.pool)
+        .await
+        .context("failed to fetch holds")?;
+
+        Ok(rows.into_iter().map(|(start, end)| Interval { start: start as u64, end: end as u64 }).collect())
+    }
+}
+
+impl VerificationRepository for PostgresBookingRepository {
+    async fn issue_otp<F>(&self, contact: &str, issue: F) -> Result<OtpChallenge, VerificationError>
+    where
+        F: FnOnce(Option<&OtpChallenge>) -> Result<OtpChallenge, VerificationError> + Send,
+    {
+        let mut tx = self.pool.begin().await.context("failed to begin transaction")?;
+
+        // Serialise codes sent to the same contact; the lock is released when the transaction ends.
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended('otp_challenges/' || $1, 0))")
+            .bind(contact)
+            .execute(&mut *tx)
+            .await
+            .context("failed to lock contact for verification")?;
+
+        let previous = sqlx::query("SELECT * FROM otp_challenges WHERE contact = $1")
+            .bind(contact)
+            .fetch_optional(&mut *tx)
+            .await
+            .context("failed to fetch verification code")?
+            .as_ref()
+            .map(otp_challenge_from_row)
+            .transpose()?;
+        let challenge = issue(previous.as_ref())?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO otp_challenges (
+                contact, code_hash, expires_at, attempts, sends_in_window, window_started_at, last_sent_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (contact) DO UPDATE SET
+                code_hash = EXCLUDED.code_hash,
+                expires_at = EXCLUDED.expires_at,
+                attempts = EXCLUDED.attempts,
+                sends_in_window = EXCLUDED.sends_in_window,
+                window_started_at = EXCLUDED.window_started_at,
+                last_sent_at = EXCLUDED.last_sent_at
+            "#,
+        )
+        .bind(challenge.contact())
+        .bind(challenge.code_hash())
+        .bind(challenge.expires_at() as i64)
+        .bind(challenge.attempts() as i32)
+        .bind(challenge.sends_in_window() as i32)
+        .bind(challenge.window_started_at() as i64)
+        .bind(challenge.last_sent_at() as i64)
+        .execute(&mut *tx)
+        .await
+        .context("failed to save verification code")?;
+
+        tx.commit().await.context("failed to commit verification code")?;
+        Ok(challenge)
+    }
+
+    async fn record_otp_attempt(&self, contact: &str) -> Result<Option<OtpChallenge>, VerificationError> {
+        let row = sqlx::query("UPDATE otp_challenges SET attempts = attempts + 1 WHERE contact = $1 RETURNING *")
+            .bind(contact)
+            .fetch_optional(&self.pool)
+            .await
+            .context("failed to record verification attempt")?;
+
+        Ok(row.as_ref().map(otp_challenge_from_row).transpose()?)
+    }
+
+    async fn consume_otp(&self, contact: &str) -> Result<(), VerificationError> {
+        sqlx::query("UPDATE otp_challenges SET code_hash = '', expires_at = 0 WHERE contact = $1")
+            .bind(contact)
+            .execute(&self.pool)
+            .await
+            .context("failed to consume verification code")?;
+
+        Ok(())
+    }
+
+    async fn remove_otp(&self, contact: &str) -> Result<(), VerificationError> {
+        sqlx::query("DELETE FROM otp_challenges WHERE contact = $1")
+            .bind(contact)
+            .execute(&self.pool)
+            .await
+            .context("failed to remove verification code")?;
+
+        Ok(())
+    }
+}
+
+impl DataRequestRepository for PostgresBookingRepository {
+    async fn record_data_request(&self, record: &DataRequestRecord) -> Result<(), BookingRepositoryError> {
+        let booking_ids: Vec<i64> = record.booking_ids.iter().map(|booking_id| *booking_id as i64).collect();
+        sqlx::query(
+            r#"
+            INSERT INTO data_subject_requests (request, subject, outcome, reason, booking_ids, requested_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(record.request.as_str())
+        .bind(record.subject)
+        .bind(record.outcome.as_str())
+        .bind(record.reason.as_deref())
+        .bind(booking_ids)
+        .bind(record.requested_at as i64)
+        .execute(&self.pool)
+        .await
+        .context("failed to record data subject request")?;
+
+        Ok(())
+    }
+}
+
+impl AdminKeyRepository for PostgresBookingRepository {
+    async fn save_admin_key(&self, admin: &AdminIdentity) -> Result<(), BookingRepositoryError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| anyhow!(e))?.as_secs();
+        sqlx::query(
+            r#"
+            INSERT INTO admin_keys (id, principal, sealed_key, activated_at)
+            VALUES (1, $1, $2, $3)
+            ON CONFLICT (id) DO UPDATE SET
+                principal = EXCLUDED.principal,
+                sealed_key = EXCLUDED.sealed_key,
+                activated_at = EXCLUDED.activated_at
+            "#,
+        )
+        .bind(admin.principal().to_text())
+        .bind(self.pii_cipher.seal(0, admin)?)
+        .bind(now as i64)
+        .execute(&self.pool)
+        .await
+        .context("failed to save admin key")?;
+
+        Ok(())
+    }
+
+    async fn find_admin_key(&self) -> Result<Option<AdminIdentity>, BookingRepositoryError> {
+        let sealed: Option<String> = sqlx::query_scalar("SELECT sealed_key FROM admin_keys WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await
+            .context("failed to fetch admin key")?;
+
+        let Some(sealed) = sealed else {
+            return Ok(None);
+        };
+        let pem = self.pii_cipher.open::<AdminIdentity>(0, &sealed)?;
+        Ok(Some(AdminIdentity::from_pem(&pem, None).context("failed to load the stored admin key")?))
+    }
+}
+
+fn otp_challenge_from_row(row: &PgRow) -> anyhow::Result<OtpChallenge> {
+    Ok(OtpChallenge::new(
+        row.try_get("contact")?,
+        row.try_get("code_hash")?,
+        row.try_get::<i64, _>("expires_at")? as u64,
+        row.try_get::<i32, _>("attempts")? as u32,
+        row.try_get::<i32, _>("sends_in_window")? as u32,
+        row.try_get::<i64, _>("window_started_at")? as u64,
+        row.try_get::<i64, _>("last_sent_at")? as u64,
+    ))
+}
+
+fn outbox_event_from_row(row: &PgRow) -> anyhow::Result<OutboxEvent> {
+    let Json(event): Json<BookingEvent> = row.try_get("payload")?;
+    Ok(OutboxEvent::new(
+        row.try_get::<i64, _>("id")? as u64,
+        event,
+        row.try_get::<&str, _>("status")?.parse::<OutboxStatus>()?,
+        row.try_get::<i32, _>("attempts")? as u32,
+        row.try_get::<i64, _>("next_attempt_at")? as u64,
+        row.try_get("completed_handlers")?,
+        row.try_get("last_error")?,
+        row.try_get::<i64, _>("created_at")? as u64,
+    ))
+}
@@ -1,25 +1,92 @@
-use crate::domain::transactions::ports::TransactionMetrics;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
 
-/// An unimplemented example of an adapter to [TransactionMetrics].
-#[derive(Debug, Clone)]
-pub struct Prometheus;
+use crate::domain::transactions::models::booking::Booking;
+use crate::domain::transactions::models::event::{BookingEvent, TransactionEvent};
+use crate::domain::transactions::ports::{BookingEventHandler, HandlerFuture, TransactionEventHandler};
+
+/// An unimplemented example of a metrics subscriber on the
+/// [EventBus](crate::domain::transactions::event_bus::EventBus).
+///
+/// Only the canister call retries are counted so far; clones share the counts.
+#[derive(Debug, Clone, Default)]
+pub struct Prometheus {
+    /// Retries of calls to the canister, per canister method.
+    canister_call_retries: Arc<Mutex<HashMap<String, u64>>>,
+}
 
 impl Prometheus {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// How often calls to the canister `method` have been retried.
+    pub fn canister_call_retries(&self, method: &str) -> u64 {
+        let retries = self.canister_call_retries.lock().unwrap_or_else(PoisonError::into_inner);
+        retries.get(method).copied().unwrap_or_default()
     }
 }
 
-impl TransactionMetrics for Prometheus {
-    async fn record_transaction_creation_success(&self) {
-        // Implement logic to record a successful transaction creation
-        println!("Transaction creation successful.");
-        // Here, you would typically send a metric to Prometheus
+impl TransactionEventHandler for Prometheus {
+    fn name(&self) -> &'static str {
+        "metrics"
     }
 
-    async fn record_transaction_creation_failure(&self) {
-        // Implement logic to record a failed transaction creation
-        println!("Transaction creation failed.");
-        // Here, you would typically send a metric to Prometheus
+    fn handle<'a>(&'a self, event: &'a TransactionEvent) -> HandlerFuture<'a> {
+        Box::pin(async move {
+            match event {
+                TransactionEvent::TransactionCreated { .. } => {
+                    // Implement logic to record a successful transaction creation
+                    tracing::debug!("Transaction creation successful");
+                }
+                TransactionEvent::TransactionCreationFailed { .. } => {
+                    // Implement logic to record a failed transaction creation
+                    tracing::debug!("Transaction creation failed");
+                }
+                TransactionEvent::PaymentLinkCreationFailed { .. } => {
+                    tracing::debug!("Payment link creation failed");
+                }
+                TransactionEvent::DocumentUploaded { .. } | TransactionEvent::DocumentAccessed { .. } => {}
+                TransactionEvent::CanisterCallRetried { method, retries, .. } => {
+                    let mut counts = self.canister_call_retries.lock().unwrap_or_else(PoisonError::into_inner);
+                    *counts.entry(method.clone()).or_default() += u64::from(*retries);
+                }
+            }
+            // Here, you would typically send a metric to Prometheus
+            Ok(())
+        })
+    }
+}
+
+impl BookingEventHandler for Prometheus {
+    fn name(&self) -> &'static str {
+        "metrics"
+    }
+
+    fn handle<'a>(&'a self, event: &'a BookingEvent, _booking: &'a Booking) -> HandlerFuture<'a> {
+        Box::pin(async move {
+            // Here, you would typically increment a per-event counter in Prometheus
+            tracing::debug!("Booking event {} recorded", event.name());
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_canister_call_retries_are_counted_per_method() {
+        let metrics = Prometheus::new();
+        let retried = |method: &str, retries| TransactionEvent::CanisterCallRetried { method: method.to_string(), retries, succeeded: true };
+
+        for event in [retried("reserve_car", 2), retried("get_booked_slots", 1), retried("reserve_car", 1)] {
+            TransactionEventHandler::handle(&metrics.clone(), &event).await.unwrap();
+        }
+
+        assert_eq!(metrics.canister_call_retries("reserve_car"), 3);
+        assert_eq!(metrics.canister_call_retries("get_booked_slots"), 1);
+        assert_eq!(metrics.canister_call_retries("validate_details_and_availaibility"), 0);
     }
 }
@@ -0,0 +1,229 @@
+use anyhow::{anyhow, Context};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, StatusCode, Url};
+use sha2::{Digest, Sha256};
+
+use crate::domain::transactions::models::document::{DocumentError, DocumentId};
+use crate::domain::transactions::ports::DocumentStore;
+
+/// Where an [S3DocumentStore] keeps documents. Any S3-compatible service works, e.g. AWS S3,
+/// Cloudflare R2 or MinIO.
+#[derive(Clone, PartialEq, Eq)]
+pub struct S3Config {
+    /// Base URL of the service, e.g. `https://s3.ap-south-1.amazonaws.com`.
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+impl std::fmt::Debug for S3Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("S3Config")
+            .field("endpoint", &self.endpoint)
+            .field("bucket", &self.bucket)
+            .field("region", &self.region)
+            .field("access_key_id", &self.access_key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Keeps sealed documents as objects in an S3-compatible bucket, addressed path-style and
+/// signed with AWS Signature Version 4.
+#[derive(Debug, Clone)]
+pub struct S3DocumentStore {
+    client: Client,
+    config: S3Config,
+}
+
+impl S3DocumentStore {
+    pub fn new(config: S3Config) -> Self {
+        Self { client: Client::new(), config }
+    }
+
+    fn prefix(booking_id: u64) -> String {
+        format!("bookings/{booking_id}/")
+    }
+
+    fn key(booking_id: u64, id: &DocumentId) -> String {
+        format!("{}{id}", Self::prefix(booking_id))
+    }
+
+    /// Send a request for object `key`, or for the bucket if `key` is empty, signed for the
+    /// current time.
+    async fn send(&self, method: Method, key: &str, query: &[(&str, &str)], body: Vec<u8>) -> anyhow::Result<reqwest::Response> {
+        let path = match key {
+            "" => format!("/{}", self.config.bucket),
+            key => format!("/{}/{}", self.config.bucket, key),
+        };
+        let mut query: Vec<String> = query
+            .iter()
+            .map(|(name, value)| format!("{}={}", uri_encode(name), uri_encode(value)))
+            .collect();
+        query.sort();
+        let query = query.join("&");
+        let mut url = Url::parse(self.config.endpoint.trim_end_matches('/'))
+            .and_then(|endpoint| endpoint.join(&path))
+            .context("invalid S3 endpoint")?;
+        if !query.is_empty() {
+            url.set_query(Some(&query));
+        }
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(anyhow!("S3 endpoint {} has no host", self.config.endpoint)),
+        };
+
+        let now = time::OffsetDateTime::now_utc();
+        let amz_date = format!(
+            "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+            now.year(),
+            now.month() as u8,
+            now.day(),
+            now.hour(),
+            now.minute(),
+            now.second()
+        );
+        let payload_hash = hex(&Sha256::digest(&body));
+        let authorization = self.authorization(method.as_str(), &path, &query, &host, &amz_date, &payload_hash);
+
+        self.client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("authorization", authorization)
+            .body(body)
+            .send()
+            .await
+            .context("S3 request failed")
+    }
+
+    /// The `Authorization` header for a request signing the `host`, `x-amz-content-sha256` and
+    /// `x-amz-date` headers. `query` must already be canonical: encoded and sorted.
+    fn authorization(&self, method: &str, path: &str, query: &str, host: &str, amz_date: &str, payload_hash: &str) -> String {
+        let date = &amz_date[..8];
+        let scope = format!("{date}/{}/s3/aws4_request", self.config.region);
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{method}\n{path}\n{query}\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\n{signed_headers}\n{payload_hash}"
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let key = signing_key(&self.config.secret_access_key, date, &self.config.region, "s3");
+        let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            self.config.access_key_id
+        )
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// The Signature Version 4 key for `date` (`YYYYMMDD`), `region` and `service`.
+fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(format!("AWS4{secret_access_key}").as_bytes(), date.as_bytes());
+    let key = hmac_sha256(&key, region.as_bytes());
+    let key = hmac_sha256(&key, service.as_bytes());
+    hmac_sha256(&key, b"aws4_request")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Percent-encode everything but unreserved characters, as Signature Version 4 requires of
+/// query parameters.
+fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// The object keys in a ListObjectsV2 response.
+fn listed_keys(xml: &str) -> Vec<String> {
+    xml.split("<Key>")
+        .skip(1)
+        .filter_map(|rest| rest.split_once("</Key>").map(|(key, _)| key.to_string()))
+        .collect()
+}
+
+impl DocumentStore for S3DocumentStore {
+    async fn put_document(&self, booking_id: u64, id: &DocumentId, sealed: Vec<u8>) -> Result<(), DocumentError> {
+        let response = self.send(Method::PUT, &Self::key(booking_id, id), &[], sealed).await?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(anyhow!("S3 responded with {} storing document {id}", response.status()).into())
+        }
+    }
+
+    async fn get_document(&self, booking_id: u64, id: &DocumentId) -> Result<Option<Vec<u8>>, DocumentError> {
+        let response = self.send(Method::GET, &Self::key(booking_id, id), &[], Vec::new()).await?;
+        match response.status() {
+            status if status.is_success() => {
+                let sealed = response.bytes().await.context("failed to read document from S3")?;
+                Ok(Some(sealed.to_vec()))
+            }
+            StatusCode::NOT_FOUND => Ok(None),
+            status => Err(anyhow!("S3 responded with {status} loading document {id}").into()),
+        }
+    }
+
+    /// Lists a single page of up to 1000 keys, far more than a booking ever has documents.
+    async fn list_documents(&self, booking_id: u64) -> Result<Vec<DocumentId>, DocumentError> {
+        let prefix = Self::prefix(booking_id);
+        let query = [("list-type", "2"), ("prefix", prefix.as_str())];
+        let response = self.send(Method::GET, "", &query, Vec::new()).await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("S3 responded with {} listing documents of booking {booking_id}", response.status()).into());
+        }
+
+        let xml = response.text().await.context("failed to read document list from S3")?;
+        Ok(listed_keys(&xml)
+            .iter()
+            .filter_map(|key| key.strip_prefix(&prefix))
+            .filter_map(|id| DocumentId::parse(id).ok())
+            .collect())
+    }
+
+    async fn delete_document(&self, booking_id: u64, id: &DocumentId) -> Result<(), DocumentError> {
+        let response = self.send(Method::DELETE, &Self::key(booking_id, id), &[], Vec::new()).await?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::NOT_FOUND => Ok(()),
+            status => Err(anyhow!("S3 responded with {status} deleting document {id}").into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_key_matches_the_aws_example() {
+        // From the AWS documentation on deriving a Signature Version 4 signing key.
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(hex(&key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+    }
+
+    #[test]
+    fn test_listed_keys_are_read_from_the_response() {
+        let xml = "<ListBucketResult><Contents><Key>bookings/7/pan-00</Key></Contents>\
+                   <Contents><Key>bookings/7/aadhaar-01</Key></Contents></ListBucketResult>";
+        assert_eq!(listed_keys(xml), vec!["bookings/7/pan-00", "bookings/7/aadhaar-01"]);
+        assert_eq!(uri_encode("bookings/7/"), "bookings%2F7%2F");
+    }
+}
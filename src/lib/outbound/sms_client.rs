@@ -0,0 +1,56 @@
+use anyhow::{anyhow, Context};
+use reqwest::Client;
+
+/// Credentials for sending SMS through the Twilio Messages API.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SmsConfig {
+    pub account_sid: String,
+    pub auth_token: String,
+    /// The sender number in E.164 form, or an alphanumeric sender ID.
+    pub from: String,
+}
+
+impl std::fmt::Debug for SmsConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SmsConfig")
+            .field("account_sid", &self.account_sid)
+            .field("from", &self.from)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Sends text messages.
+#[derive(Debug, Clone)]
+pub struct SmsClient {
+    client: Client,
+    config: SmsConfig,
+}
+
+impl SmsClient {
+    pub fn new(config: SmsConfig) -> Self {
+        Self { client: Client::new(), config }
+    }
+
+    /// Send `body` to the E.164 number `to`.
+    pub async fn send_sms(&self, to: &str, body: &str) -> anyhow::Result<()> {
+        let config = &self.config;
+        let url = format!("https://api.twilio.com/2010-04-01/Accounts/{}/Messages.json", config.account_sid);
+
+        let response = self
+            .client
+            .post(url)
+            .basic_auth(&config.account_sid, Some(&config.auth_token))
+            .form(&[("To", to), ("From", config.from.as_str()), ("Body", body)])
+            .send()
+            .await
+            .context("SMS request failed")?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            Err(anyhow!("SMS provider responded with {status}: {error_text}"))
+        }
+    }
+}
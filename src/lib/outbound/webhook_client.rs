@@ -0,0 +1,74 @@
+use reqwest::Client;
+use serde::Serialize;
+
+use crate::domain::transactions::models::booking::Booking;
+use crate::domain::transactions::models::event::BookingEvent;
+use crate::domain::transactions::ports::{BookingEventHandler, HandlerFuture};
+
+/// Posts [BookingEvent]s as JSON to the configured webhook URLs.
+#[derive(Debug, Clone)]
+pub struct WebhookClient {
+    client: Client,
+    urls: Vec<String>,
+}
+
+/// The JSON body sent to every webhook.
+#[derive(Debug, Serialize)]
+struct WebhookPayload<'a> {
+    event: &'a BookingEvent,
+    status: &'a str,
+}
+
+impl WebhookClient {
+    pub fn new(urls: Vec<String>) -> Self {
+        Self {
+            client: Client::new(),
+            urls,
+        }
+    }
+
+    async fn post(&self, url: &str, payload: &WebhookPayload<'_>) -> Result<(), String> {
+        let response = self
+            .client
+            .post(url)
+            .json(payload)
+            .send()
+            .await
+            .map_err(|f| f.to_string())?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("{url} responded with {}", response.status()))
+        }
+    }
+}
+
+impl BookingEventHandler for WebhookClient {
+    fn name(&self) -> &'static str {
+        "webhooks"
+    }
+
+    fn handle<'a>(&'a self, event: &'a BookingEvent, booking: &'a Booking) -> HandlerFuture<'a> {
+        Box::pin(async move {
+            let payload = WebhookPayload {
+                event,
+                status: booking.status().as_str(),
+            };
+
+            // Every URL is retried together, so receivers must tolerate duplicate events.
+            let mut errors = Vec::new();
+            for url in &self.urls {
+                if let Err(e) = self.post(url, &payload).await {
+                    errors.push(e);
+                }
+            }
+
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(errors.join("; "))
+            }
+        })
+    }
+}
@@ -1,3 +1,5 @@
+pub mod redaction;
+
 use time::{format_description, OffsetDateTime, UtcOffset};
 
 pub fn format_datetime(timestamp_seconds: u64) -> String {
@@ -5,4 +7,9 @@ pub fn format_datetime(timestamp_seconds: u64) -> String {
     // Converting to IST now
     // let ist_offset = UtcOffset::from_hms(5, 30, 0).unwrap();
     OffsetDateTime::from_unix_timestamp(timestamp_seconds as i64).unwrap()/* .to_offset(ist_offset) */.format(&format).unwrap()
-}
\ No newline at end of file
+}
+
+/// Compare two secrets without leaking the position of the first difference through timing.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,186 @@
+/*!
+   Module `redaction` keeps customer details out of logs: masks for displaying sensitive values,
+   and a tracing field formatter that redacts sensitive fields and scrubs PAN, Aadhaar, email,
+   phone and private key patterns from everything else.
+*/
+
+use std::borrow::Cow;
+use std::fmt::{self, Debug};
+use std::sync::LazyLock;
+
+use regex::Regex;
+use tracing::field::{Field, Visit};
+use tracing_subscriber::field::RecordFields;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::FormatFields;
+
+/// What redacted values are replaced with.
+pub const REDACTED: &str = "[redacted]";
+
+/// Fields whose values are never logged, whatever they contain.
+const SENSITIVE_FIELDS: &[&str] = &[
+    "name",
+    "pan",
+    "aadhar",
+    "aadhaar",
+    "email",
+    "email_address",
+    "mobile_number",
+    "phone_number",
+    "driving_licence_number",
+    "principal_jwk",
+    "delegated_identity",
+    "secret",
+    "token",
+    "authorization",
+    "pem",
+    "passphrase",
+];
+
+/// Patterns of sensitive values, in the order they are scrubbed.
+static PATTERNS: LazyLock<[(Regex, &str); 5]> = LazyLock::new(|| {
+    [
+        // The private key of a JWK.
+        (Regex::new(r#""d"\s*:\s*"[^"]*""#).unwrap(), r#""d":"[redacted]""#),
+        (Regex::new(r"[A-Za-z0-9_.+-]+@[A-Za-z0-9-]+\.[A-Za-z0-9-.]+").unwrap(), "[email]"),
+        (Regex::new(r"\b[A-Z]{5}[0-9]{4}[A-Z]\b").unwrap(), "[pan]"),
+        (Regex::new(r"\b[2-9][0-9]{3}[ -]?[0-9]{4}[ -]?[0-9]{4}\b").unwrap(), "[aadhaar]"),
+        (Regex::new(r"\+[1-9][0-9]{7,14}\b").unwrap(), "[phone]"),
+    ]
+});
+
+/// `value` with all but its first `visible_start` and last `visible_end` characters replaced by
+/// `*`, or entirely masked if it is too short to reveal anything.
+pub fn mask(value: &str, visible_start: usize, visible_end: usize) -> String {
+    let len = value.chars().count();
+    if len <= visible_start + visible_end {
+        return "*".repeat(len);
+    }
+    value
+        .chars()
+        .enumerate()
+        .map(|(i, c)| if i < visible_start || i >= len - visible_end { c } else { '*' })
+        .collect()
+}
+
+/// `text` with every PAN, Aadhaar number, email address, E.164 phone number and JWK private key
+/// replaced by a placeholder.
+pub fn scrub(text: &str) -> Cow<'_, str> {
+    let mut text = Cow::Borrowed(text);
+    for (pattern, replacement) in PATTERNS.iter() {
+        if let Cow::Owned(scrubbed) = pattern.replace_all(&text, *replacement) {
+            text = Cow::Owned(scrubbed);
+        }
+    }
+    text
+}
+
+/// Formats tracing fields like the default formatter, except that [SENSITIVE_FIELDS] are
+/// replaced by [REDACTED] and every other value is [scrub]bed.
+///
+/// ```no_run
+/// tracing_subscriber::fmt().fmt_fields(offchain::utils::redaction::RedactingFields).init();
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RedactingFields;
+
+impl<'writer> FormatFields<'writer> for RedactingFields {
+    fn format_fields<R: RecordFields>(&self, writer: Writer<'writer>, fields: R) -> fmt::Result {
+        let mut visitor = RedactingVisitor {
+            writer,
+            result: Ok(()),
+            delimit: false,
+        };
+        fields.record(&mut visitor);
+        visitor.result
+    }
+}
+
+struct RedactingVisitor<'writer> {
+    writer: Writer<'writer>,
+    result: fmt::Result,
+    delimit: bool,
+}
+
+impl Visit for RedactingVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if self.result.is_err() {
+            return;
+        }
+
+        let name = field.name().trim_start_matches("r#");
+        let delimiter = if self.delimit { " " } else { "" };
+        self.delimit = true;
+        self.result = if SENSITIVE_FIELDS.contains(&name) {
+            write!(self.writer, "{delimiter}{name}={REDACTED}")
+        } else if name == "message" {
+            write!(self.writer, "{delimiter}{}", scrub(&format!("{value:?}")))
+        } else {
+            write!(self.writer, "{delimiter}{name}={}", scrub(&format!("{value:?}")))
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::domain::transactions::models::transaction::Transaction;
+
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn capture(log: impl FnOnce()) -> String {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .fmt_fields(RedactingFields)
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        tracing::subscriber::with_default(subscriber, log);
+        let output = logs.0.lock().unwrap().clone();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_mask_keeps_only_the_visible_ends() {
+        assert_eq!(mask("ABCPU1234F", 0, 4), "******234F");
+        assert_eq!(mask("abc", 2, 2), "***");
+    }
+
+    #[test]
+    fn test_logs_contain_no_pan_or_aadhaar() {
+        let transaction = Transaction::test_fixture(1, 101);
+
+        let logs = capture(|| {
+            tracing::info!(?transaction, "booking saved");
+            tracing::info!(pan = "ABCPU1234F", aadhar = "234567890124", "customer");
+            tracing::error!("Request Failed: canister rejected ABCPU1234F / 2345 6789 0124 for +919876543210");
+            tracing::warn!(reason = %"jwk {\"kty\":\"EC\",\"d\":\"c2VjcmV0\"}", "payment failed");
+        });
+
+        let pan = Regex::new(r"[A-Z]{5}[0-9]{4}[A-Z]").unwrap();
+        let aadhaar = Regex::new(r"[2-9][0-9]{3} ?[0-9]{4} ?[0-9]{4}").unwrap();
+        assert!(!pan.is_match(&logs), "PAN found in logs:\n{logs}");
+        assert!(!aadhaar.is_match(&logs), "Aadhaar found in logs:\n{logs}");
+        for leaked in ["test@example.com", "9876543210", "c2VjcmV0"] {
+            assert!(!logs.contains(leaked), "{leaked} found in logs:\n{logs}");
+        }
+        assert!(logs.contains("pan=[redacted]"), "{logs}");
+        assert!(logs.contains("booking saved"), "{logs}");
+    }
+}